@@ -1,4 +1,12 @@
+mod charts;
+mod favorite_handler;
 mod forms;
 mod handler;
+mod hydration_handler;
 mod meal_handler;
+mod measurement_handler;
+mod notification_handler;
+mod report_handler;
+mod responses;
 pub(crate) mod server;
+mod user_handler;
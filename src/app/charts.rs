@@ -0,0 +1,249 @@
+// Server rendered SVG charts, embedded into the templates with `|safe`
+use std::fmt::Write;
+
+const WIDTH: f32 = 600.0;
+const HEIGHT: f32 = 240.0;
+const PADDING_LEFT: f32 = 45.0;
+const PADDING_RIGHT: f32 = 10.0;
+const PADDING_TOP: f32 = 20.0;
+const PADDING_BOTTOM: f32 = 30.0;
+const GRID_LINES: usize = 4;
+const MAX_X_LABELS: usize = 8;
+
+pub(crate) struct Series {
+    pub(crate) name: String,
+    pub(crate) color: &'static str,
+    // one value per label, None leaves a gap
+    pub(crate) values: Vec<Option<f32>>,
+    pub(crate) dots: bool,
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+struct Frame {
+    count: usize,
+    min: f32,
+    max: f32,
+}
+
+impl Frame {
+    fn new<'a>(count: usize, values: impl Iterator<Item = &'a f32>) -> Frame {
+        let (min, max) = values.fold((f32::MAX, f32::MIN), |(min, max), v| (min.min(*v), max.max(*v)));
+        let (min, max) = if min > max {
+            (0.0, 1.0)
+        } else if (max - min).abs() < f32::EPSILON {
+            (min - 1.0, max + 1.0)
+        } else {
+            (min, max)
+        };
+        Frame { count, min, max }
+    }
+
+    fn plot_width(&self) -> f32 {
+        WIDTH - PADDING_LEFT - PADDING_RIGHT
+    }
+
+    fn plot_height(&self) -> f32 {
+        HEIGHT - PADDING_TOP - PADDING_BOTTOM
+    }
+
+    // center of the slot of label `i`
+    fn x(&self, i: usize) -> f32 {
+        PADDING_LEFT + self.plot_width() * (i as f32 + 0.5) / self.count.max(1) as f32
+    }
+
+    fn y(&self, value: f32) -> f32 {
+        PADDING_TOP + self.plot_height() * (1.0 - (value - self.min) / (self.max - self.min))
+    }
+
+    fn slot_width(&self) -> f32 {
+        self.plot_width() / self.count.max(1) as f32
+    }
+
+    fn open(&self, labels: &[String]) -> String {
+        let mut svg = format!(
+            r#"<svg viewBox="0 0 {} {}" xmlns="http://www.w3.org/2000/svg" class="w-100" font-size="10" font-family="sans-serif">"#,
+            WIDTH, HEIGHT
+        );
+        for i in 0..=GRID_LINES {
+            let value = self.min + (self.max - self.min) * i as f32 / GRID_LINES as f32;
+            let y = self.y(value);
+            let _ = write!(
+                svg,
+                r##"<line x1="{}" x2="{}" y1="{y:.1}" y2="{y:.1}" stroke="#ddd"/><text x="{}" y="{:.1}" text-anchor="end" fill="#666">{:.0}</text>"##,
+                PADDING_LEFT,
+                WIDTH - PADDING_RIGHT,
+                PADDING_LEFT - 4.0,
+                y + 3.0,
+                value
+            );
+        }
+        let step = labels.len().div_ceil(MAX_X_LABELS).max(1);
+        for (i, label) in labels.iter().enumerate().step_by(step) {
+            let _ = write!(
+                svg,
+                r##"<text x="{:.1}" y="{}" text-anchor="middle" fill="#666">{}</text>"##,
+                self.x(i),
+                HEIGHT - PADDING_BOTTOM + 14.0,
+                escape(label)
+            );
+        }
+        svg
+    }
+}
+
+fn legend(svg: &mut String, entries: &[(&str, &str)]) {
+    let mut x = PADDING_LEFT;
+    for (name, color) in entries {
+        let _ = write!(
+            svg,
+            r##"<rect x="{x}" y="4" width="10" height="10" fill="{color}"/><text x="{}" y="13" fill="#333">{}</text>"##,
+            x + 14.0,
+            escape(name)
+        );
+        x += 24.0 + name.len() as f32 * 6.0;
+    }
+}
+
+/// Line chart over the labels, every series is drawn as its own line
+pub(crate) fn line_chart(labels: &[String], series: &[Series]) -> String {
+    let frame = Frame::new(labels.len(), series.iter().flat_map(|s| s.values.iter().flatten()));
+    let mut svg = frame.open(labels);
+    for s in series.iter() {
+        let mut path = String::new();
+        let mut pen_down = false;
+        for (i, value) in s.values.iter().enumerate() {
+            match value {
+                Some(v) => {
+                    let _ = write!(path, "{}{:.1},{:.1} ", if pen_down { "L" } else { "M" }, frame.x(i), frame.y(*v));
+                    pen_down = true;
+                    if s.dots {
+                        let _ = write!(
+                            svg,
+                            r#"<circle cx="{:.1}" cy="{:.1}" r="2.5" fill="{}"><title>{}: {:.1}</title></circle>"#,
+                            frame.x(i),
+                            frame.y(*v),
+                            s.color,
+                            escape(&labels[i]),
+                            v
+                        );
+                    }
+                }
+                None => pen_down = false,
+            }
+        }
+        if !path.is_empty() {
+            let _ = write!(
+                svg,
+                r#"<path d="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+                path.trim_end(),
+                s.color
+            );
+        }
+    }
+    let entries: Vec<(&str, &str)> = series.iter().map(|s| (s.name.as_str(), s.color)).collect();
+    legend(&mut svg, &entries);
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Bar chart with the series stacked on top of each other, `target` draws a dashed line
+pub(crate) fn stacked_bar_chart(labels: &[String], series: &[Series], target: Option<f32>) -> String {
+    let totals: Vec<f32> = (0..labels.len())
+        .map(|i| series.iter().filter_map(|s| s.values.get(i).copied().flatten()).sum())
+        .collect();
+    let bounds = [0.0_f32];
+    let frame = Frame::new(
+        labels.len(),
+        totals.iter().chain(bounds.iter()).chain(target.iter()),
+    );
+    let mut svg = frame.open(labels);
+    let bar_width = frame.slot_width() * 0.7;
+    for (i, label) in labels.iter().enumerate() {
+        let mut base = 0.0;
+        for s in series.iter() {
+            let Some(value) = s.values.get(i).copied().flatten() else {
+                continue;
+            };
+            let top = frame.y(base + value);
+            let _ = write!(
+                svg,
+                r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"><title>{} {}: {:.0}</title></rect>"#,
+                frame.x(i) - bar_width / 2.0,
+                top,
+                bar_width,
+                frame.y(base) - top,
+                s.color,
+                escape(label),
+                escape(&s.name),
+                value
+            );
+            base += value;
+        }
+    }
+    if let Some(target) = target {
+        let y = frame.y(target);
+        let _ = write!(
+            svg,
+            r##"<line x1="{}" x2="{}" y1="{y:.1}" y2="{y:.1}" stroke="#e74c3c" stroke-width="1.5" stroke-dasharray="6 4"/>"##,
+            PADDING_LEFT,
+            WIDTH - PADDING_RIGHT
+        );
+    }
+    let entries: Vec<(&str, &str)> = series.iter().map(|s| (s.name.as_str(), s.color)).collect();
+    legend(&mut svg, &entries);
+    svg.push_str("</svg>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_chart_gaps() {
+        let labels: Vec<String> = ["1", "2", "3"].iter().map(|s| s.to_string()).collect();
+        let svg = line_chart(
+            &labels,
+            &[Series {
+                name: "Weight <kg>".to_string(),
+                color: "#2c3e50",
+                values: vec![Some(80.0), None, Some(79.0)],
+                dots: true,
+            }],
+        );
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+        // the gap starts a new segment
+        assert_eq!(svg.matches('M').count(), 2);
+        assert_eq!(svg.matches('L').count(), 0);
+        assert_eq!(svg.matches("<circle").count(), 2);
+        assert!(svg.contains("Weight &lt;kg&gt;"));
+    }
+
+    #[test]
+    fn test_stacked_bars() {
+        let labels: Vec<String> = ["1", "2"].iter().map(|s| s.to_string()).collect();
+        let series = |name: &str, values: Vec<Option<f32>>| Series {
+            name: name.to_string(),
+            color: "#18bc9c",
+            values,
+            dots: false,
+        };
+        let svg = stacked_bar_chart(
+            &labels,
+            &[
+                series("Protein", vec![Some(400.0), None]),
+                series("Fat", vec![Some(900.0), Some(300.0)]),
+            ],
+            Some(2000.0),
+        );
+        // three bars and two legend entries
+        assert_eq!(svg.matches("<rect").count(), 5);
+        assert!(svg.contains("stroke-dasharray"));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::db;
+use crate::models::favorite::{FavoriteFood, Favorites};
+use crate::models::food::{FoodKey, FoodSource, StoredFood};
+use crate::usda::client::UsdaClient;
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::{Response, StatusCode};
+use axum_oidc::{EmptyAdditionalClaims, OidcClaims};
+
+// star shown next to a food, posting to it toggles the favorite
+#[derive(Template)]
+#[template(path = "product/favorite_button.html")]
+pub(crate) struct FavoriteButton {
+    pub(crate) food_key: FoodKey,
+    pub(crate) starred: bool,
+}
+
+/// Starred foods of the logged in user, none without a login
+pub(crate) fn favorites_of(claims: Option<OidcClaims<EmptyAdditionalClaims>>) -> Favorites {
+    let Some(username) = claims.and_then(|c| c.preferred_username().cloned()) else {
+        return Favorites::default();
+    };
+    let mut con = db::connector::get_connection()
+        .expect("Could not connect to redis,maybe redis is not running");
+    Favorites::of_user(&mut con, username.as_str())
+}
+
+fn json(status: StatusCode, body: String) -> Response<String> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(body)
+        .unwrap()
+}
+
+fn empty(status: StatusCode) -> Response<String> {
+    Response::builder().status(status).body("".into()).unwrap()
+}
+
+// the favorites tab lists stored foods only, FDC foods are fetched and stored if needed
+async fn stored_food(usda: &UsdaClient, key: &FoodKey) -> Result<StoredFood, String> {
+    match (key.source, key.id.parse()) {
+        (FoodSource::Usda, Ok(fdc_id)) => usda
+            .get_food(fdc_id)
+            .await
+            .map(StoredFood::Usda)
+            .map_err(|e| e.to_string()),
+        _ => {
+            let mut con = db::connector::get_connection().map_err(|e| e.to_string())?;
+            key.stored(&mut con).ok_or(format!("Unknown food {}", key))
+        }
+    }
+}
+
+// stars or unstars a food of the logged in user /favorites/:key/toggle
+pub async fn handle_toggle_favorite(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    State(usda): State<UsdaClient>,
+    Path(key): Path<String>,
+) -> Response<String> {
+    let Ok(food_key) = key.parse::<FoodKey>() else {
+        return empty(StatusCode::BAD_REQUEST);
+    };
+    let mut con = db::connector::get_connection().unwrap();
+    let claims = claims.unwrap();
+    let username = claims.preferred_username().unwrap();
+    let starred = !Favorites::of_user(&mut con, username.as_str()).contains(&food_key);
+    if starred {
+        if stored_food(&usda, &food_key).await.is_err() {
+            return empty(StatusCode::NOT_FOUND);
+        }
+        Favorites::add(&mut con, username.as_str(), &food_key).expect("DIDNT SAVE");
+    } else {
+        Favorites::remove(&mut con, username.as_str(), &food_key).expect("DIDNT SAVE");
+    }
+    let t = FavoriteButton { food_key, starred };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(t.render().unwrap())
+        .unwrap()
+}
+
+// GET /api/favorites
+pub async fn handle_api_favorites(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let claims = claims.unwrap();
+    let username = claims.preferred_username().unwrap();
+    let foods: Vec<FavoriteFood> = Favorites::of_user(&mut con, username.as_str())
+        .foods(&mut con)
+        .iter()
+        .map(FavoriteFood::from)
+        .collect();
+    json(StatusCode::OK, serde_json::to_string(&foods).unwrap())
+}
+
+// PUT /api/favorites/:key, unknown foods are rejected
+pub async fn handle_api_add_favorite(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    State(usda): State<UsdaClient>,
+    Path(key): Path<String>,
+) -> Response<String> {
+    let claims = claims.unwrap();
+    let username = claims.preferred_username().unwrap();
+    let food_key = match key.parse::<FoodKey>() {
+        Ok(food_key) => food_key,
+        Err(e) => {
+            let error = serde_json::json!({ "error": e });
+            return json(StatusCode::BAD_REQUEST, error.to_string());
+        }
+    };
+    if let Err(e) = stored_food(&usda, &food_key).await {
+        let error = serde_json::json!({ "error": e });
+        return json(StatusCode::NOT_FOUND, error.to_string());
+    }
+    let mut con = db::connector::get_connection().unwrap();
+    Favorites::add(&mut con, username.as_str(), &food_key).expect("DIDNT SAVE");
+    empty(StatusCode::NO_CONTENT)
+}
+
+// DELETE /api/favorites/:key
+pub async fn handle_api_remove_favorite(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(key): Path<String>,
+) -> Response<String> {
+    let Ok(food_key) = key.parse::<FoodKey>() else {
+        return empty(StatusCode::BAD_REQUEST);
+    };
+    let mut con = db::connector::get_connection().unwrap();
+    let claims = claims.unwrap();
+    let username = claims.preferred_username().unwrap();
+    Favorites::remove(&mut con, username.as_str(), &food_key).expect("DIDNT SAVE");
+    empty(StatusCode::NO_CONTENT)
+}
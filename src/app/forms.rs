@@ -1,3 +1,8 @@
+use chrono::{Duration, NaiveDate};
+use std::collections::HashMap;
+use crate::models::diet::{DietRestriction, DietaryProfile, FoodFlag};
+use crate::models::goals::{GoalTarget, NutritionGoals, GOAL_NUTRIENTS};
+use crate::models::profile::{ActivityLevel, BodyProfile, Sex};
 use serde::{Deserialize, Serialize};
 
 //struct for form that contains id and amount of a product
@@ -5,4 +10,189 @@ use serde::{Deserialize, Serialize};
 pub struct ProductForm {
     pub(crate) product_code: String,
     pub(crate) amount: f32,
+    // set after the user was warned about allergens or diet restrictions
+    #[serde(default)]
+    pub(crate) confirm: bool,
+}
+
+// goals form, every nutrient has a value and a unit which is either "g" or "%"
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct GoalsForm {
+    pub(crate) energy: String,
+    pub(crate) carbohydrates: String,
+    pub(crate) carbohydrates_unit: String,
+    pub(crate) proteins: String,
+    pub(crate) proteins_unit: String,
+    pub(crate) fats: String,
+    pub(crate) fats_unit: String,
+    pub(crate) fiber: String,
+    pub(crate) fiber_unit: String,
+    pub(crate) sugar: String,
+    pub(crate) sugar_unit: String,
+    pub(crate) salt: String,
+    pub(crate) salt_unit: String,
+    pub(crate) sodium: String,
+    pub(crate) sodium_unit: String,
+    pub(crate) water_ml: String,
+}
+
+impl GoalsForm {
+    fn field(&self, name: &str) -> (&str, &str) {
+        match name {
+            "energy" => (&self.energy, "g"),
+            "carbohydrates" => (&self.carbohydrates, &self.carbohydrates_unit),
+            "proteins" => (&self.proteins, &self.proteins_unit),
+            "fats" => (&self.fats, &self.fats_unit),
+            "fiber" => (&self.fiber, &self.fiber_unit),
+            "sugar" => (&self.sugar, &self.sugar_unit),
+            "salt" => (&self.salt, &self.salt_unit),
+            "sodium" => (&self.sodium, &self.sodium_unit),
+            _ => ("", "g"),
+        }
+    }
+
+    // empty or unparsable values clear the goal
+    pub(crate) fn to_goals(&self) -> NutritionGoals {
+        let mut goals = NutritionGoals::default();
+        for (name, ..) in GOAL_NUTRIENTS.iter() {
+            let (value, unit) = self.field(name);
+            let target = value.trim().parse::<f32>().ok().filter(|v| *v > 0.0).map(|v| {
+                if unit == "%" {
+                    GoalTarget::PercentOfEnergy(v)
+                } else {
+                    GoalTarget::Absolute(v)
+                }
+            });
+            goals.set_target(name, target);
+        }
+        goals.water_ml = self.water_ml.trim().parse::<f32>().ok().filter(|v| *v > 0.0);
+        goals
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ProfileForm {
+    pub(crate) sex: String,
+    pub(crate) birth_date: String,
+    pub(crate) height_cm: String,
+    pub(crate) weight_kg: String,
+    pub(crate) body_fat_percent: String,
+    pub(crate) activity_level: String,
+}
+
+impl From<&BodyProfile> for ProfileForm {
+    fn from(profile: &BodyProfile) -> Self {
+        let text = |v: Option<f32>| v.map(|v| v.to_string()).unwrap_or_default();
+        ProfileForm {
+            sex: profile.sex.map(|s| format!("{:?}", s)).unwrap_or_default(),
+            birth_date: profile.birth_date.map(|d| d.to_string()).unwrap_or_default(),
+            height_cm: text(profile.height_cm),
+            weight_kg: text(profile.weight_kg),
+            body_fat_percent: text(profile.body_fat_percent),
+            activity_level: profile.activity_level.key(),
+        }
+    }
+}
+
+impl ProfileForm {
+    pub(crate) fn to_profile(&self) -> BodyProfile {
+        let positive = |s: &str| s.trim().parse::<f32>().ok().filter(|v| *v > 0.0);
+        BodyProfile {
+            sex: Sex::parse(&self.sex),
+            birth_date: self.birth_date.parse().ok(),
+            height_cm: positive(&self.height_cm),
+            weight_kg: positive(&self.weight_kg),
+            body_fat_percent: positive(&self.body_fat_percent).filter(|v| *v < 100.0),
+            activity_level: ActivityLevel::parse(&self.activity_level).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct MeasurementForm {
+    pub(crate) date: String,
+    pub(crate) weight_kg: String,
+    pub(crate) waist_cm: String,
+    pub(crate) body_fat_percent: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct HydrationForm {
+    pub(crate) ml: String,
+}
+
+// checkboxes named allergy_<flag> and restriction_<restriction>, only checked ones are sent
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DietForm {
+    #[serde(flatten)]
+    pub(crate) checked: HashMap<String, String>,
+}
+
+impl DietForm {
+    pub(crate) fn to_diet(&self) -> DietaryProfile {
+        let checked = |prefix: &str| {
+            self.checked
+                .keys()
+                .filter_map(move |key| key.strip_prefix(prefix))
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>()
+        };
+        let mut allergies: Vec<FoodFlag> = checked("allergy_")
+            .iter()
+            .filter_map(|key| FoodFlag::parse(key))
+            .filter(|flag| FoodFlag::ALLERGENS.contains(flag))
+            .collect();
+        allergies.sort();
+        let mut restrictions: Vec<DietRestriction> = checked("restriction_")
+            .iter()
+            .filter_map(|key| DietRestriction::parse(key))
+            .collect();
+        restrictions.sort();
+        DietaryProfile {
+            allergies,
+            restrictions,
+        }
+    }
+}
+
+// options of the quick-pick lists on the search page, `slot` limits them to the slot of the meal
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct QuickPickForm {
+    pub(crate) slot: bool,
+}
+
+// barcode lookup, with a meal the food is shown ready to be added to it
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct BarcodeForm {
+    pub(crate) meal: Option<String>,
+}
+
+// date range given as query, empty dates fall back to a default range
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DateRangeForm {
+    pub(crate) from: String,
+    pub(crate) to: String,
+}
+
+impl DateRangeForm {
+    /// The range, ending today and spanning `default_days` when not given
+    pub(crate) fn range(&self, today: NaiveDate, default_days: i64) -> (NaiveDate, NaiveDate) {
+        let end = self.to.parse().unwrap_or(today);
+        let start = self
+            .from
+            .parse()
+            .unwrap_or(end - Duration::days(default_days - 1));
+        if start > end {
+            (end, start)
+        } else {
+            (start, end)
+        }
+    }
 }
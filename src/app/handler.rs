@@ -1,12 +1,27 @@
+use crate::app::favorite_handler::favorites_of;
+use crate::app::responses::html;
 use crate::db;
-use crate::models::models::{NutritionistSearchQuery, RedisORM};
-use crate::usda::search::cached_search;
+use crate::models::food::FoodItem;
+use crate::models::favorite::Favorites;
+use crate::models::models::NutritionistSearchQuery;
+use crate::open_food_facts;
+use crate::open_food_facts::product::OFF_DATA_SET;
+use crate::open_food_facts::sdk::{OffClient, OffError};
+use crate::app::forms::BarcodeForm;
+use crate::usda::barcode;
+use crate::usda::barcode::BarcodeError;
+use crate::usda::client::UsdaClient;
+use crate::usda::filter::FoodFilter;
+use crate::usda::search::{
+    cached_search, cached_search_of, stored_search, CacheStatus, CachedSearch,
+};
 use crate::usda::search::Food; // bring trait in scope
 use askama::Template;
-use axum::extract::Path;
-use axum::http::{Response, StatusCode};
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, Response, StatusCode};
 use axum_oidc::{EmptyAdditionalClaims, OidcClaims};
 use crate::models::user::{User, UserType};
+use std::fmt::Display;
 
 #[derive(Template)] // this will generate the code...
 #[template(path = "product/search_response_food.html")] // using the template in this path, relative
@@ -15,6 +30,23 @@ struct SearchResponseTemplate<'a> {
     // the name of the struct can be anything
     meal_id: &'a str,
     foods: Vec<Food>,
+    error: Option<String>,
+    data_type: Option<String>,
+    favorites: Favorites,
+}
+
+#[derive(Template)]
+#[template(path = "product/food_view.html")]
+struct FoodTemplate {
+    food: Food,
+}
+
+// placeholders loading the results of each data type as soon as they arrive
+#[derive(Template)]
+#[template(path = "product/search_streaming.html")]
+struct SearchStreamingTemplate<'a> {
+    meal_id: &'a str,
+    data_types: &'a [String],
 }
 
 pub async fn home_handler(oidc_claims: OidcClaims<EmptyAdditionalClaims>,) -> Response<String> {
@@ -36,18 +68,192 @@ pub async fn home_handler(oidc_claims: OidcClaims<EmptyAdditionalClaims>,) -> Re
 }
 
 pub async fn search_usda_handler(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    State(usda): State<UsdaClient>,
+    State(off): State<OffClient>,
     Path(id): Path<String>,
+    headers: HeaderMap,
     x: axum::Form<NutritionistSearchQuery>,
 ) -> Response<String> {
-    let result = cached_search(&*x.query).await;
-    let search_response = SearchResponseTemplate {
-        meal_id: &*id,
-        foods: result,
-    };
+    // htmx requests get the live results per data type, plain form posts wait for all of them.
+    // Stored USDA foods are answered at once, OFF is a single source and never streamed.
+    let filter = x.filter();
+    let mut stored = None;
+    if headers.contains_key("HX-Request")
+        && !x.query.trim().is_empty()
+        && filter.data_type.as_deref() != Some(OFF_DATA_SET)
+    {
+        let mut con = db::connector::get_connection()
+            .expect("Could not connect to redis,maybe redis is not running");
+        stored = stored_search(&mut con, &x.query, usda.data_types());
+        if stored.is_none() {
+            let data_types: Vec<String> = match filter.data_type {
+                Some(data_type) => vec![data_type],
+                None => usda
+                    .data_types()
+                    .iter()
+                    .cloned()
+                    .chain([OFF_DATA_SET.to_string()])
+                    .collect(),
+            };
+            let t = SearchStreamingTemplate {
+                meal_id: &id,
+                data_types: &data_types,
+            };
+            return html(t.render().unwrap());
+        }
+    }
+    let favorites = favorites_of(claims);
+    match filter.data_type.as_deref() {
+        Some(OFF_DATA_SET) => {
+            let result = off_search(&off, &x.query).await;
+            search_response(&id, None, &filter, favorites, result)
+        }
+        Some(data_type) => {
+            // the stored foods are of every data type, the filter keeps the requested one
+            let result = match stored {
+                Some(result) => Ok(result),
+                None => cached_search_of(&usda, &x.query, data_type).await,
+            };
+            search_response(&id, None, &filter, favorites, result)
+        }
+        None => {
+            let usda_search = async {
+                match stored {
+                    Some(result) => Ok(result),
+                    None => cached_search(&usda, &x.query).await,
+                }
+            };
+            let (result, products) = tokio::join!(usda_search, off_search(&off, &x.query));
+            // packaged products come after the USDA foods, a failing source only loses its foods
+            let result = match (result, products) {
+                (Ok(mut result), Ok(products)) => {
+                    result.foods.extend(products.foods);
+                    Ok(result)
+                }
+                (Ok(result), Err(e)) => {
+                    println!("Open Food Facts search failed: {}", e);
+                    Ok(result)
+                }
+                (Err(e), Ok(products)) => {
+                    println!("USDA search failed: {}", e);
+                    Ok(products)
+                }
+                (Err(e), Err(_)) => Err(e),
+            };
+            search_response(&id, None, &filter, favorites, result)
+        }
+    }
+}
+
+// /:id/search/:data_type?query=
+pub async fn search_usda_data_type_handler(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    State(usda): State<UsdaClient>,
+    State(off): State<OffClient>,
+    Path((id, data_type)): Path<(String, String)>,
+    Query(x): Query<NutritionistSearchQuery>,
+) -> Response<String> {
+    if data_type == OFF_DATA_SET {
+        let result = off_search(&off, &x.query).await;
+        return search_response(&id, Some(data_type), &x.filter(), favorites_of(claims), result);
+    }
+    if !usda.data_types().contains(&data_type) {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body("".to_string())
+            .unwrap();
+    }
+    let result = cached_search_of(&usda, &x.query, &data_type).await;
+    search_response(&id, Some(data_type), &x.filter(), favorites_of(claims), result)
+}
+
+// OFF products of a query, an empty query matches nothing instead of every product
+async fn off_search(off: &OffClient, query: &str) -> Result<CachedSearch, OffError> {
+    if query.trim().is_empty() {
+        return Ok(CachedSearch {
+            foods: vec![],
+            status: CacheStatus::Local,
+        });
+    }
+    open_food_facts::sdk::cached_search(off, query).await
+}
 
+// the found foods or the error, the X-Cache header tells where the foods came from
+fn search_response(
+    meal_id: &str,
+    data_type: Option<String>,
+    filter: &FoodFilter,
+    favorites: Favorites,
+    result: Result<CachedSearch, impl Display>,
+) -> Response<String> {
+    let (foods, error, status) = match result {
+        Ok(result) => (filter.apply(result.foods), None, result.status.to_string()),
+        Err(e) => (vec![], Some(e.to_string()), "ERROR".to_string()),
+    };
+    let t = SearchResponseTemplate {
+        meal_id,
+        foods,
+        error,
+        data_type,
+        favorites,
+    };
     Response::builder()
         .status(StatusCode::OK)
         .header("Content-Type", "text/html; charset=utf-8")
-        .body(search_response.render().unwrap())
+        .header("X-Cache", status)
+        .body(t.render().unwrap())
         .unwrap()
 }
+
+// /foods/:fdc_id
+pub async fn food_detail_handler(
+    State(usda): State<UsdaClient>,
+    Path(fdc_id): Path<i32>,
+) -> Response<String> {
+    let food = match usda.get_food(fdc_id).await {
+        Ok(food) => food,
+        Err(e) => {
+            return Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(e.to_string())
+                .unwrap()
+        }
+    };
+    html(FoodTemplate { food }.render().unwrap())
+}
+
+// /foods/barcode/:code?meal=
+pub async fn barcode_handler(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    State(usda): State<UsdaClient>,
+    State(off): State<OffClient>,
+    Path(code): Path<String>,
+    Query(form): Query<BarcodeForm>,
+) -> Response<String> {
+    let result = barcode::lookup(&usda, &off, &code).await;
+    if let Some(meal_id) = form.meal {
+        let result = result.map(|(food, status)| CachedSearch {
+            foods: vec![food],
+            status,
+        });
+        return search_response(
+            &meal_id,
+            None,
+            &FoodFilter::default(),
+            favorites_of(claims),
+            result,
+        );
+    }
+    match result {
+        Ok((food, _)) => html(FoodTemplate { food }.render().unwrap()),
+        Err(BarcodeError::Redis(e)) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(e.to_string())
+            .unwrap(),
+        Err(e) => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(e.to_string())
+            .unwrap(),
+    }
+}
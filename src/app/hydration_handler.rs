@@ -0,0 +1,47 @@
+use crate::app::forms::HydrationForm;
+use crate::app::responses::redirect;
+use crate::db;
+use crate::models::hydration::HydrationLog;
+use crate::models::models::RedisORM;
+use axum::extract::Path;
+use axum::http::Response;
+use axum::Form;
+use axum_oidc::{EmptyAdditionalClaims, OidcClaims};
+use chrono::{Local, NaiveDate};
+
+// add water to the hydration log of a day /hydration/:date
+pub async fn handle_add_water(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(date): Path<String>,
+    form: Form<HydrationForm>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let claims = claims.unwrap();
+    let username = claims.preferred_username().unwrap();
+    let (Ok(date), Ok(ml)) = (date.parse::<NaiveDate>(), form.ml.trim().parse::<f32>()) else {
+        return redirect("/meals");
+    };
+    if ml > 0.0 {
+        let mut log = HydrationLog::for_day(&mut con, username, date);
+        log.add(ml, Local::now().time());
+        log.save(&mut con).expect("DIDNT SAVE");
+    }
+    redirect("/meals")
+}
+
+// removes the last entry of the day
+pub async fn handle_undo_water(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(date): Path<String>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let claims = claims.unwrap();
+    let username = claims.preferred_username().unwrap();
+    if let Ok(date) = date.parse::<NaiveDate>() {
+        let mut log = HydrationLog::for_day(&mut con, username, date);
+        if log.entries.pop().is_some() {
+            log.save(&mut con).expect("DIDNT SAVE");
+        }
+    }
+    redirect("/meals")
+}
@@ -1,18 +1,32 @@
 use std::cmp::PartialEq;
 use std::collections::HashMap;
-use crate::app::forms::ProductForm;
+use crate::app::favorite_handler::favorites_of;
+use crate::app::forms::{DateRangeForm, ProductForm, QuickPickForm};
+use crate::app::user_handler::client_of;
 use crate::db;
+use crate::models::diet::DietConflict;
+use crate::models::favorite::Favorites;
+use crate::models::food::{FoodItem, FoodKey, FoodSource, StoredFood};
+use crate::models::goals::{goal_label, NutritionGoals, GOAL_NUTRIENTS};
+use crate::models::hydration::HydrationLog;
 use crate::models::meal::{DailyMealCombo, Meal, MealType};
 use crate::models::models::RedisORM;
+use crate::models::notification::Notification;
+use crate::nutrition::report::{calendar_month, calendar_range, CalendarDay, ReportPeriod};
+use crate::nutrition::logging::{logging_stats, LoggingStats};
+use crate::nutrition::quality::{score_week, DietQuality};
+use crate::nutrition::usage::{quick_picks, QuickPicks, USAGE_WINDOW_DAYS};
 use crate::models::user::{User, UserType};
-use crate::usda::search::{Food, NutrientValues};
+use crate::usda::client::UsdaClient;
+use crate::usda::filter::{SortOrder, DATA_TYPES};
+use crate::usda::index;
+use crate::usda::search::NutrientValues;
 use askama::Template;
-use axum::extract::Path;
+use axum::extract::{Path, Query, State};
 use axum::http::{Response, StatusCode};
 use axum::Form;
 use axum_oidc::{EmptyAdditionalClaims, OidcClaims};
-use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
-use uuid::Uuid;
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
 use crate::models::meal::MealType::Snack;
 
 #[derive(Template)] // this will generate the code...
@@ -25,6 +39,53 @@ struct MealsTemplate<'a> {
     today: NaiveDate,
     today_has_meal: bool,
     is_nutritionist: bool,
+    goals: NutritionGoals,
+    today_water_ml: f32,
+    // diet quality of the current week
+    week_quality: Option<DietQuality>,
+    logging: LoggingStats,
+    notifications: Vec<Notification>,
+    // shown range and the ranges before and after it
+    start: NaiveDate,
+    end: NaiveDate,
+    previous: (NaiveDate, NaiveDate),
+    next: (NaiveDate, NaiveDate),
+    // url of this page without the range and of the calendar
+    base: String,
+    calendar: String,
+}
+
+#[derive(Template)]
+#[template(path = "meals/calendar_view.html")]
+struct CalendarTemplate {
+    username: String,
+    back: String,
+    // url of the calendar without the date
+    base: String,
+    month: NaiveDate,
+    previous: NaiveDate,
+    next: NaiveDate,
+    weeks: Vec<Vec<CalendarDay>>,
+    has_energy_goal: bool,
+}
+
+// the days shown on the diary, the current week unless a range is picked
+fn diary_range(range: &DateRangeForm, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    if range.from.is_empty() && range.to.is_empty() {
+        ReportPeriod::Week.range(today)
+    } else {
+        range.range(today, 7)
+    }
+}
+
+// the range of the same length right before or after
+fn shift_range((start, end): (NaiveDate, NaiveDate), forward: bool) -> (NaiveDate, NaiveDate) {
+    let length = end - start + chrono::Duration::days(1);
+    if forward {
+        (start + length, end + length)
+    } else {
+        (start - length, end - length)
+    }
 }
 
 #[derive(Template)]
@@ -34,6 +95,8 @@ pub struct MealView {
     macros: NutrientValues,
     edit: bool,
     is_nutritionist: bool,
+    // stars are only shown while editing
+    favorites: Favorites,
 }
 
 #[derive(Template)] // this will generate the code...
@@ -41,15 +104,43 @@ pub struct MealView {
 struct SearchTemplate<'a> {
     // the name of the struct can be anything
     meal_id: &'a str,
+    data_types: [&'static str; 5],
+    // nutrient key and label for the range filter
+    nutrients: Vec<(String, String)>,
+    sorts: [SortOrder; 3],
+    picks: QuickPicks,
+    favorites: Vec<StoredFood>,
+    meal_type: MealType,
+    // quick picks only from meals of the same slot
+    slot_only: bool,
 }
 
 
+// asks before adding a food that clashes with the allergies or diet of the user
+#[derive(Template)]
+#[template(path = "meals/diet_warning.html")]
+struct DietWarningTemplate {
+    meal_id: String,
+    name: String,
+    product_code: String,
+    amount: f32,
+    conflicts: Vec<DietConflict>,
+}
+
 #[derive(Template)] // this will generate the code...
 #[template(path = "user/nutritionist_users.html")] // using the template in this path, relative
 struct NutritionistSubUserTemplate {
     // the name of the struct can be anything
-    users: Vec<User>,
+    // every client with the diet quality of their current week and their logging
+    users: Vec<(User, Option<DietQuality>, LoggingStats)>,
 }
+// fills in the water of the hydration log for every day
+fn add_logged_water(con: &mut redis::Connection, meal_combos: &mut [(NaiveDate, DailyMealCombo)], username: &str) {
+    for (date, combo) in meal_combos.iter_mut() {
+        combo.logged_water_ml = HydrationLog::for_day(con, username, *date).total_ml();
+    }
+}
+
 pub async fn handle_subusers(claims: Option<OidcClaims<EmptyAdditionalClaims>>) -> Response<String> {
     let mut  con = db::connector::get_connection().unwrap();
     let claims = claims.unwrap();
@@ -58,7 +149,16 @@ pub async fn handle_subusers(claims: Option<OidcClaims<EmptyAdditionalClaims>>)
     let usertype = user.user_type;
     dbg!(&usertype);
     return if let UserType::Nutritionist(users) = usertype {
-        let users = users.iter().map(|u| User::check_if_exists_by_id(&mut con,u).unwrap()).collect();
+        // clients who never logged in have no record yet
+        let users = users
+            .iter()
+            .filter_map(|u| {
+                let user = User::check_if_exists_by_id(&mut con, u).ok()?;
+                let meals = user.fetch_user_meals(&mut con);
+                let today = Utc::now().date_naive();
+                Some((user, score_week(&meals, today), logging_stats(&meals, today)))
+            })
+            .collect();
         let t = NutritionistSubUserTemplate {
             users: users,
         };
@@ -91,6 +191,7 @@ pub async fn handle_subusers_view(claims: Option<OidcClaims<EmptyAdditionalClaim
                 macros: nutrients,
                 edit: false,
                 is_nutritionist: true,
+                favorites: Favorites::default(),
             };
             return Response::builder()
                 .status(StatusCode::OK)
@@ -105,36 +206,21 @@ pub async fn handle_subusers_view(claims: Option<OidcClaims<EmptyAdditionalClaim
         .body("You are not allowed to view this meal".to_string())
         .unwrap()
 }
-pub async fn handle_allmeals(claims: Option<OidcClaims<EmptyAdditionalClaims>>) -> Response<String> {
+pub async fn handle_allmeals(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Query(range): Query<DateRangeForm>,
+) -> Response<String> {
     let mut con = crate::db::connector::get_connection()
         .expect("Could not connect to redis,maybe redis is not running");
     let meals = Meal::all(&mut con);
+    let goals = NutritionGoals::default();
+    let today_water_ml = 0.0;
     let claims = claims.unwrap();
     let username = claims.preferred_username().unwrap();
-    // add meal to hashmap of meals where date is the key
-    let mut hash_map = std::collections::HashMap::new();
-
-    meals.iter().for_each(|meal| {
-        if hash_map.contains_key(&meal.date) {
-            let mut m: &mut Vec<Meal>  = hash_map.get_mut(&meal.date).unwrap();
-            m.push(meal.clone());
-        }else{
-            hash_map.insert(meal.date, vec![meal.clone()]);
-        }
-    });
-    let mut meal_combos = Vec::new();
-    //iterate hashmap and add to vector
-    for (key, value) in hash_map.iter() {
-        let mut daily_meal = DailyMealCombo::from_meals_vec(value.clone());
-        daily_meal.date = key.clone();
-        meal_combos.push((key.clone(), daily_meal));
-    }
-
-    // sort by date
-    meal_combos.sort_by(|a,b| a.0.cmp(&b.0));
-    meal_combos.reverse();
-    let today_has_meal = meal_combos.iter().find(|(date, _)| date == &Utc::now().date_naive()).is_some();
-    dbg!(today_has_meal);
+    let (start, end) = diary_range(&range, Utc::now().date_naive());
+    let shown: Vec<Meal> = meals.iter().filter(|m| m.date >= start && m.date <= end).cloned().collect();
+    let meal_combos = DailyMealCombo::group_by_day(&shown);
+    let today_has_meal = meals.iter().any(|m| m.date == Utc::now().date_naive());
 
     let t = MealsTemplate {
         meal_id: "test",
@@ -143,6 +229,17 @@ pub async fn handle_allmeals(claims: Option<OidcClaims<EmptyAdditionalClaims>>)
         today: Utc::now().date_naive(),
         today_has_meal,
         is_nutritionist: false,
+        goals,
+        today_water_ml,
+        week_quality: score_week(&meals, Utc::now().date_naive()),
+        logging: logging_stats(&meals, Utc::now().date_naive()),
+        notifications: vec![],
+        start,
+        end,
+        previous: shift_range((start, end), false),
+        next: shift_range((start, end), true),
+        base: "/allmeals".to_string(),
+        calendar: "/meals/calendar".to_string(),
     };
 
     Response::builder()
@@ -152,7 +249,11 @@ pub async fn handle_allmeals(claims: Option<OidcClaims<EmptyAdditionalClaims>>)
         .unwrap()
 }
 
-pub async fn handle_subuser_meals(claims: Option<OidcClaims<EmptyAdditionalClaims>>,Path(id):Path<String>) -> Response<String> {
+pub async fn handle_subuser_meals(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(id): Path<String>,
+    Query(range): Query<DateRangeForm>,
+) -> Response<String> {
     let mut con = crate::db::connector::get_connection()
         .expect("Could not connect to redis,maybe redis is not running");
 
@@ -160,38 +261,31 @@ pub async fn handle_subuser_meals(claims: Option<OidcClaims<EmptyAdditionalClaim
     let username = claims.preferred_username().unwrap();
     let user = User::check_if_exists_by_id(&mut con,&id).unwrap();
     let meals = user.fetch_user_meals(&mut con);
-    let mut hash_map = std::collections::HashMap::new();
-
-
-    meals.iter().for_each(|meal| {
-        if hash_map.contains_key(&meal.date) {
-            let mut m: &mut Vec<Meal>  = hash_map.get_mut(&meal.date).unwrap();
-            m.push(meal.clone());
-        }else{
-            hash_map.insert(meal.date, vec![meal.clone()]);
-        }
-    });
-    let mut meal_combos = Vec::new();
-    //iterate hashmap and add to vector
-    for (key, value) in hash_map.iter() {
-        let mut daily_meal = DailyMealCombo::from_meals_vec(value.clone());
-        daily_meal.date = key.clone();
-        meal_combos.push((key.clone(), daily_meal));
-    }
-
-    // sort by date
-    meal_combos.sort_by(|a,b| a.0.cmp(&b.0));
-    meal_combos.reverse();
-    let today_has_meal = meal_combos.iter().find(|(date, _)| date == &Utc::now().date_naive()).is_some();
-    dbg!(today_has_meal);
+    let (start, end) = diary_range(&range, Utc::now().date_naive());
+    let mut meal_combos =
+        DailyMealCombo::group_by_day(&Meal::get_meals_for_user_in_range(&mut con, &id, start, end));
+    let today_has_meal = meals.iter().any(|m| m.date == Utc::now().date_naive());
+    add_logged_water(&mut con, &mut meal_combos, &id);
+    let today_water_ml = HydrationLog::for_day(&mut con, &id, Utc::now().date_naive()).total_ml();
 
     let t = MealsTemplate {
         meal_id: "test",
         meals: meal_combos,
-        username: claims.preferred_username().unwrap(),
+        username: &id,
         today: Utc::now().date_naive(),
         today_has_meal,
         is_nutritionist: true,
+        goals: user.goals,
+        today_water_ml,
+        week_quality: score_week(&meals, Utc::now().date_naive()),
+        logging: logging_stats(&meals, Utc::now().date_naive()),
+        notifications: vec![],
+        start,
+        end,
+        previous: shift_range((start, end), false),
+        next: shift_range((start, end), true),
+        base: format!("/nutritionist/meals/user/{}", id),
+        calendar: format!("/nutritionist/calendar/{}", id),
     };
 
     Response::builder()
@@ -217,6 +311,7 @@ pub async fn handle_subuser_meal(claims: Option<OidcClaims<EmptyAdditionalClaims
         macros: nutrients,
         edit: false,
         is_nutritionist: true,
+        favorites: Favorites::default(),
     };
     Response::builder()
         .status(StatusCode::OK)
@@ -225,37 +320,27 @@ pub async fn handle_subuser_meal(claims: Option<OidcClaims<EmptyAdditionalClaims
         .unwrap()
 }
 
-pub async fn handle_meals(claims: Option<OidcClaims<EmptyAdditionalClaims>>) -> Response<String> {
+pub async fn handle_meals(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Query(range): Query<DateRangeForm>,
+) -> Response<String> {
     let mut con = crate::db::connector::get_connection()
         .expect("Could not connect to redis,maybe redis is not running");
 
     let claims = claims.unwrap();
     let username = claims.preferred_username().unwrap();
-    let meals = Meal::get_meals_for_user(&mut con, &username);
-    // add meal to hashmap of meals where date is the key
-    let mut hash_map = std::collections::HashMap::new();
-
-    meals.iter().for_each(|meal| {
-        if hash_map.contains_key(&meal.date) {
-            let mut m: &mut Vec<Meal>  = hash_map.get_mut(&meal.date).unwrap();
-            m.push(meal.clone());
-        }else{
-            hash_map.insert(meal.date, vec![meal.clone()]);
-        }
-    });
-    let mut meal_combos = Vec::new();
-    //iterate hashmap and add to vector
-    for (key, value) in hash_map.iter() {
-        let mut daily_meal = DailyMealCombo::from_meals_vec(value.clone());
-        daily_meal.date = key.clone();
-        meal_combos.push((key.clone(), daily_meal));
-    }
-
-    // sort by date
-    meal_combos.sort_by(|a,b| a.0.cmp(&b.0));
-    meal_combos.reverse();
-    let today_has_meal = meal_combos.iter().find(|(date, _)| date == &Utc::now().date_naive()).is_some();
-    dbg!(today_has_meal);
+    // the logged meals up to today, the logging streaks need the whole history
+    let meals =
+        Meal::get_meals_for_user_in_range(&mut con, username, NaiveDate::MIN, Utc::now().date_naive());
+    let goals = User::check_if_exists_by_id(&mut con, username)
+        .map(|user| user.goals)
+        .unwrap_or_default();
+    let (start, end) = diary_range(&range, Utc::now().date_naive());
+    let mut meal_combos =
+        DailyMealCombo::group_by_day(&Meal::get_meals_for_user_in_range(&mut con, username, start, end));
+    let today_has_meal = meals.iter().any(|m| m.date == Utc::now().date_naive());
+    add_logged_water(&mut con, &mut meal_combos, username);
+    let today_water_ml = HydrationLog::for_day(&mut con, username, Utc::now().date_naive()).total_ml();
 
     let t = MealsTemplate {
         meal_id: "test",
@@ -264,6 +349,17 @@ pub async fn handle_meals(claims: Option<OidcClaims<EmptyAdditionalClaims>>) ->
         today: Utc::now().date_naive(),
         today_has_meal,
         is_nutritionist: false,
+        goals,
+        today_water_ml,
+        week_quality: score_week(&meals, Utc::now().date_naive()),
+        logging: logging_stats(&meals, Utc::now().date_naive()),
+        notifications: Notification::unread_for_user(&mut con, username),
+        start,
+        end,
+        previous: shift_range((start, end), false),
+        next: shift_range((start, end), true),
+        base: "/meals".to_string(),
+        calendar: "/meals/calendar".to_string(),
     };
 
     Response::builder()
@@ -287,8 +383,8 @@ pub async fn handle_create_meal(Path((meal_type,date_str)): Path<(String, String
     };
     let creds = oidc_claims.unwrap();
     let user_name = creds.preferred_username().unwrap().to_string();
-    // hier die meals from user holen
-    let meals = Meal::get_meals_for_user(&mut con, &user_name);
+    // the meals of the user on that date
+    let meals = Meal::get_meals_for_user_in_range(&mut con, &user_name, date, date);
 
     for meal in meals.iter() {
         dbg!(&meal.date, &date, &meal.meal_type, &meal_type);
@@ -319,23 +415,22 @@ pub async fn handle_create_meal(Path((meal_type,date_str)): Path<(String, String
 }
 
 // Display a meal
-pub async fn handle_meal(Path(id): Path<String>) -> Response<String> {
+pub async fn handle_meal(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(id): Path<String>,
+) -> Response<String> {
     let mut con = crate::db::connector::get_connection()
         .expect("Could not connect to redis,maybe redis is not running");
     let mut user = User::example();
     user.id = "TEST_ID".to_string();
-    let mut meal = Meal::fetch_from_uuid(&mut con, &id).expect("DIDNT FIND MEAL");
-    // Using the tera Context struct
-    for content in meal.contents.iter_mut() {
-        let mut c = content.clone();
-        content.product = c.product.generate_nutrient_values() * content.quantity * 0.01;
-    }
+    let meal = Meal::fetch_from_uuid(&mut con, &id).expect("DIDNT FIND MEAL");
     let macros = meal.get_macros();
     let t = MealView {
         meal,
         macros,
         edit: true,
         is_nutritionist: false,
+        favorites: favorites_of(claims),
     };
 
     Response::builder()
@@ -346,6 +441,7 @@ pub async fn handle_meal(Path(id): Path<String>) -> Response<String> {
 }
 
 pub async fn remove_product_from_meal_handler(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
     Path((meal_id, id)): Path<(String, String)>,
 ) -> Response<String> {
     let mut con = db::connector::get_connection().unwrap();
@@ -354,10 +450,6 @@ pub async fn remove_product_from_meal_handler(
     meal.contents
         .retain(|x| x.id != id.to_string().parse().unwrap());
     dbg!(meal.contents.len());
-    for content in meal.contents.iter_mut() {
-        let mut c = content.clone();
-        content.product = c.product.generate_nutrient_values() * content.quantity * 0.01;
-    }
 
     let macros = meal.get_macros();
     meal.save(&mut con).unwrap();
@@ -366,6 +458,7 @@ pub async fn remove_product_from_meal_handler(
         macros: macros,
         edit: true,
         is_nutritionist: true,
+        favorites: favorites_of(claims),
     };
     Response::builder()
         .status(StatusCode::OK)
@@ -373,8 +466,35 @@ pub async fn remove_product_from_meal_handler(
         .unwrap()
 }
 
-pub async fn handle_search_meal_add(Path(id): Path<String>) -> Response<String> {
-    let t = SearchTemplate { meal_id: &id };
+pub async fn handle_search_meal_add(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(id): Path<String>,
+    Query(form): Query<QuickPickForm>,
+) -> Response<String> {
+    let mut con = crate::db::connector::get_connection()
+        .expect("Could not connect to redis,maybe redis is not running");
+    let meal = Meal::fetch_from_uuid(&mut con, &id).expect("DIDNT FIND MEAL");
+    let today = Utc::now().date_naive();
+    let history = Meal::get_meals_for_user_in_range(
+        &mut con,
+        &meal.username,
+        today - Duration::days(USAGE_WINDOW_DAYS),
+        today,
+    );
+    let slot = form.slot.then_some(&meal.meal_type);
+    let t = SearchTemplate {
+        meal_id: &id,
+        picks: quick_picks(&history, slot),
+        favorites: favorites_of(claims).foods(&mut con),
+        meal_type: meal.meal_type.clone(),
+        slot_only: form.slot,
+        data_types: DATA_TYPES,
+        nutrients: GOAL_NUTRIENTS
+            .iter()
+            .map(|(name, unit, ..)| (name.to_string(), format!("{} ({}/100 g)", goal_label(name), unit)))
+            .collect(),
+        sorts: SortOrder::ALL,
+    };
     Response::builder()
         .status(StatusCode::OK)
         .header("Content-Type", "text/html; charset=utf-8")
@@ -384,6 +504,7 @@ pub async fn handle_search_meal_add(Path(id): Path<String>) -> Response<String>
 
 // add a content to a meal /meal/:id/addcontent
 pub async fn handle_add_content_to_meal(
+    State(usda): State<UsdaClient>,
     Path((id)): Path<String>,
     x: Form<ProductForm>,
 ) -> Response<String> {
@@ -391,12 +512,49 @@ pub async fn handle_add_content_to_meal(
         .expect("Could not connect to redis,maybe redis is not running");
 
     let mut meal = Meal::fetch_from_uuid(&mut con, &id).expect("DIDNT FIND MEAL");
-    let prod = Food::fetch_from_uuid(&mut con, &x.product_code).expect("DIDNT FIND PRODUCT");
-    let prod = crate::models::meal::MealContent {
-        product: prod,
-        quantity: x.amount,
-        id: Uuid::new_v4(),
+    let Ok(key) = x.product_code.parse::<FoodKey>() else {
+        return Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body("".into())
+            .unwrap();
     };
+    // the full FDC record has ingredients and portions, the search result is good enough offline
+    let details = match (key.source, key.id.parse()) {
+        (FoodSource::Usda, Ok(fdc_id)) => usda.get_food(fdc_id).await.ok(),
+        _ => None,
+    };
+    let prod = details
+        .map(StoredFood::Usda)
+        .or_else(|| key.stored(&mut con))
+        .expect("DIDNT FIND PRODUCT");
+    if !x.confirm {
+        let diet = User::check_if_exists_by_id(&mut con, &meal.username)
+            .map(|user| user.diet)
+            .unwrap_or_default();
+        let conflicts = if diet.is_empty() {
+            vec![]
+        } else {
+            diet.conflicts(&prod.flags())
+        };
+        if !conflicts.is_empty() {
+            let t = DietWarningTemplate {
+                meal_id: id,
+                name: prod.display_name(),
+                product_code: x.product_code.clone(),
+                amount: x.amount,
+                conflicts,
+            };
+            return Response::builder()
+                .status(StatusCode::OK)
+                .header("Content-Type", "text/html; charset=utf-8")
+                .body(t.render().unwrap())
+                .unwrap();
+        }
+    }
+    if let Some(fdc_id) = prod.fdc_id() {
+        index::record_use(&mut con, fdc_id).ok();
+    }
+    let prod = crate::models::meal::MealContent::new(prod, x.amount);
     meal.contents.append(&mut vec![prod]);
     meal.save(&mut con).expect("DIDNT SAVE");
 
@@ -407,6 +565,64 @@ pub async fn handle_add_content_to_meal(
         .unwrap()
 }
 
+fn render_calendar(
+    con: &mut redis::Connection,
+    user: &User,
+    base: String,
+    back: String,
+    date: &str,
+) -> Response<String> {
+    let month = date
+        .parse::<NaiveDate>()
+        .unwrap_or(Utc::now().date_naive());
+    let (start, end) = calendar_range(month);
+    let meals = Meal::get_meals_for_user_in_range(con, &user.id, start, end);
+    let (month_start, _) = ReportPeriod::Month.range(month);
+    let t = CalendarTemplate {
+        username: user.id.clone(),
+        back,
+        base,
+        month: month_start,
+        previous: ReportPeriod::Month.previous(month_start),
+        next: ReportPeriod::Month.next(month_start),
+        weeks: calendar_month(&meals, &user.goals, month),
+        has_energy_goal: user.goals.energy.is_some(),
+    };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(t.render().unwrap())
+        .unwrap()
+}
+
+// month calendar with the energy of every day /meals/calendar/:date
+pub async fn handle_calendar(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(date): Path<String>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let user = User::check_if_exists_or_create(&mut con, claims.unwrap()).unwrap();
+    render_calendar(&mut con, &user, "/meals/calendar".to_string(), "/meals".to_string(), &date)
+}
+
+pub async fn handle_subuser_calendar(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path((username, date)): Path<(String, String)>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let client = match client_of(&mut con, claims, &username) {
+        Ok(client) => client,
+        Err(response) => return *response,
+    };
+    render_calendar(
+        &mut con,
+        &client,
+        format!("/nutritionist/calendar/{}", username),
+        format!("/nutritionist/meals/user/{}", username),
+        &date,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::{DateTime, NaiveDateTime};
@@ -423,3 +639,4 @@ mod tests {
         new_meal.save(&mut con).unwrap()
     }
 }
+
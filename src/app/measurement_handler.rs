@@ -0,0 +1,174 @@
+use crate::app::charts::{line_chart, Series};
+use crate::app::forms::MeasurementForm;
+use crate::app::responses::redirect;
+use crate::app::user_handler::client_of;
+use crate::db;
+use crate::models::meal::Meal;
+use crate::models::measurement::BodyMeasurement;
+use crate::models::models::RedisORM;
+use crate::models::user::User;
+use crate::nutrition::weight::{estimate_tdee, trend, weekly_rate, TrendPoint};
+use askama::Template;
+use axum::extract::Path;
+use axum::http::{Response, StatusCode};
+use axum::Form;
+use axum_oidc::{EmptyAdditionalClaims, OidcClaims};
+use chrono::{Duration, NaiveDate, Utc};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+// days looked back for the rate of change and the TDEE estimate
+const ANALYSIS_DAYS: i64 = 28;
+
+#[derive(Template)]
+#[template(path = "user/weight_view.html")]
+struct WeightTemplate {
+    username: String,
+    editable: bool,
+    back: String,
+    today: NaiveDate,
+    points: Vec<(TrendPoint, BodyMeasurement)>,
+    chart: String,
+    weekly_rate: Option<String>,
+    average_intake: Option<String>,
+    estimated_tdee: Option<String>,
+}
+
+/// Energy logged per day, only days with at least one meal are included
+fn daily_energy(meals: &[Meal], from: NaiveDate, to: NaiveDate) -> BTreeMap<NaiveDate, f32> {
+    let mut days = BTreeMap::new();
+    for meal in meals.iter().filter(|m| m.date >= from && m.date <= to) {
+        *days.entry(meal.date).or_insert(0.0) += meal.get_macros().energy;
+    }
+    days
+}
+
+fn render_weight(con: &mut redis::Connection, user: &User, editable: bool, back: String) -> Response<String> {
+    let measurements = BodyMeasurement::get_for_user(con, &user.id);
+    let readings: Vec<(NaiveDate, f32)> = measurements.iter().map(|m| (m.date, m.weight_kg)).collect();
+    let points = trend(&readings);
+
+    let today = Utc::now().date_naive();
+    let from = today - Duration::days(ANALYSIS_DAYS);
+    let recent: Vec<TrendPoint> = points.iter().filter(|p| p.date >= from).cloned().collect();
+    let rate = weekly_rate(&recent);
+    let energy = daily_energy(&Meal::get_meals_for_user(con, &user.id), from, today);
+    let average_intake = if energy.is_empty() {
+        None
+    } else {
+        Some(energy.values().sum::<f32>() / energy.len() as f32)
+    };
+    let estimated_tdee = match (average_intake, rate) {
+        (Some(intake), Some(rate)) => Some(format!("{:.0}", estimate_tdee(intake, rate))),
+        _ => None,
+    };
+
+    let labels: Vec<String> = points.iter().map(|p| p.date.format("%d.%m.").to_string()).collect();
+    let chart = line_chart(
+        &labels,
+        &[
+            Series {
+                name: "Weight".to_string(),
+                color: "#95a5a6",
+                values: points.iter().map(|p| Some(p.weight)).collect(),
+                dots: true,
+            },
+            Series {
+                name: "Trend".to_string(),
+                color: "#2c3e50",
+                values: points.iter().map(|p| Some(p.trend)).collect(),
+                dots: false,
+            },
+        ],
+    );
+
+    let mut rows: Vec<(TrendPoint, BodyMeasurement)> = points.into_iter().zip(measurements).collect();
+    rows.reverse();
+    let t = WeightTemplate {
+        username: user.id.clone(),
+        editable,
+        back,
+        today,
+        points: rows,
+        chart,
+        weekly_rate: rate.map(|r| format!("{:+.2}", r)),
+        average_intake: average_intake.map(|i| format!("{:.0}", i)),
+        estimated_tdee,
+    };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(t.render().unwrap())
+        .unwrap()
+}
+
+pub async fn handle_weight(claims: Option<OidcClaims<EmptyAdditionalClaims>>) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let user = User::check_if_exists_or_create(&mut con, claims.unwrap()).unwrap();
+    render_weight(&mut con, &user, true, "/meals".to_string())
+}
+
+// adds a measurement, an existing one of the same day is replaced
+pub async fn handle_add_measurement(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    form: Form<MeasurementForm>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let mut user = User::check_if_exists_or_create(&mut con, claims.unwrap()).unwrap();
+    let positive = |s: &str| s.trim().parse::<f32>().ok().filter(|v| *v > 0.0);
+    let (Some(weight_kg), Ok(date)) = (positive(&form.weight_kg), form.date.parse::<NaiveDate>()) else {
+        return redirect("/weight");
+    };
+    let measurements = BodyMeasurement::get_for_user(&mut con, &user.id);
+    let id = measurements
+        .iter()
+        .find(|m| m.date == date)
+        .map(|m| m.id)
+        .unwrap_or_else(Uuid::new_v4);
+    let measurement = BodyMeasurement {
+        id,
+        username: user.id.clone(),
+        date,
+        weight_kg,
+        waist_cm: positive(&form.waist_cm),
+        body_fat_percent: positive(&form.body_fat_percent).filter(|v| *v < 100.0),
+    };
+    measurement.save(&mut con).expect("DIDNT SAVE");
+
+    // the latest measurement keeps the body profile up to date
+    if measurements.iter().all(|m| m.date <= date) {
+        user.profile.weight_kg = Some(measurement.weight_kg);
+        if measurement.body_fat_percent.is_some() {
+            user.profile.body_fat_percent = measurement.body_fat_percent;
+        }
+        user.save(&mut con).expect("DIDNT SAVE");
+    }
+    redirect("/weight")
+}
+
+pub async fn handle_delete_measurement(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(id): Path<String>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let claims = claims.unwrap();
+    let username = claims.preferred_username().unwrap().to_string();
+    if let Some(measurement) = BodyMeasurement::fetch_from_uuid(&mut con, &id) {
+        if measurement.username == username {
+            measurement.delete(&mut con).expect("DIDNT DELETE");
+        }
+    }
+    redirect("/weight")
+}
+
+pub async fn handle_subuser_weight(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(username): Path<String>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let client = match client_of(&mut con, claims, &username) {
+        Ok(client) => client,
+        Err(response) => return *response,
+    };
+    render_weight(&mut con, &client, false, format!("/nutritionist/meals/user/{}", username))
+}
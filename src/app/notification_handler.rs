@@ -0,0 +1,27 @@
+use crate::db;
+use crate::models::models::RedisORM;
+use crate::models::notification::Notification;
+use axum::extract::Path;
+use axum::http::{Response, StatusCode};
+use axum_oidc::{EmptyAdditionalClaims, OidcClaims};
+
+// dismisses a notification of the logged in user /notifications/:id/read
+pub async fn handle_read_notification(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(id): Path<String>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let claims = claims.unwrap();
+    let username = claims.preferred_username().unwrap();
+    if let Some(mut notification) = Notification::fetch_from_uuid(&mut con, &id) {
+        if notification.username == username.as_str() {
+            notification.read = true;
+            notification.save(&mut con).expect("DIDNT SAVE");
+        }
+    }
+    Response::builder()
+        .status(StatusCode::SEE_OTHER)
+        .header("Location", "/meals")
+        .body("".into())
+        .unwrap()
+}
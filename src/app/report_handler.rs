@@ -0,0 +1,219 @@
+use crate::app::charts::{stacked_bar_chart, Series};
+use crate::app::forms::DateRangeForm;
+use crate::app::responses::html;
+use crate::app::user_handler::client_of;
+use crate::db;
+use crate::models::meal::Meal;
+use crate::models::user::User;
+use crate::nutrition::contribution::{contributions, NutrientContribution};
+use crate::nutrition::report::{build_report, NutritionReport, ReportPeriod};
+use crate::usda::nutrients;
+use askama::Template;
+use axum::extract::{Path, Query};
+use axum::http::{Response, StatusCode};
+use axum_oidc::{EmptyAdditionalClaims, OidcClaims};
+use chrono::{NaiveDate, Utc};
+
+// default length of the contribution analysis
+const CONTRIBUTION_DAYS: i64 = 28;
+
+#[derive(Template)]
+#[template(path = "reports/contribution_view.html")]
+struct ContributionTemplate {
+    username: String,
+    back: String,
+    start: NaiveDate,
+    end: NaiveDate,
+    contributions: Vec<NutrientContribution>,
+}
+
+#[derive(Template)]
+#[template(path = "reports/report_view.html")]
+struct ReportTemplate {
+    username: String,
+    // prefix of the report urls, /reports or /nutritionist/reports/:username
+    base: String,
+    back: String,
+    // url of the full food contribution analysis
+    contributions: String,
+    report: NutritionReport,
+    previous: NaiveDate,
+    next: NaiveDate,
+    chart: String,
+}
+
+// daily energy split by the macros it comes from, with the energy goal as target line
+fn energy_chart(report: &NutritionReport, energy_goal: Option<f32>) -> String {
+    let labels: Vec<String> = report
+        .days
+        .iter()
+        .map(|d| match report.period {
+            ReportPeriod::Week => d.date.format("%a").to_string(),
+            ReportPeriod::Month => d.date.format("%d").to_string(),
+        })
+        .collect();
+    let series = |name: &str,
+                  color: &'static str,
+                  kcal: fn(f32) -> f32,
+                  pick: fn(&crate::usda::search::NutrientValues) -> f32| Series {
+        name: name.to_string(),
+        color,
+        values: report
+            .days
+            .iter()
+            .map(|d| d.logged.then(|| kcal(pick(&d.values))))
+            .collect(),
+        dots: false,
+    };
+    stacked_bar_chart(
+        &labels,
+        &[
+            series(
+                "Protein kcal",
+                "#3498db",
+                |g| g * nutrients::KCAL_PER_G_PROTEIN,
+                |v| v.proteins,
+            ),
+            series(
+                "Carbohydrate kcal",
+                "#f39c12",
+                |g| g * nutrients::KCAL_PER_G_CARBOHYDRATE,
+                |v| v.carbohydrates,
+            ),
+            series(
+                "Fat kcal",
+                "#18bc9c",
+                |g| g * nutrients::KCAL_PER_G_FAT,
+                |v| v.fats,
+            ),
+        ],
+        energy_goal,
+    )
+}
+
+// urls of the pages of a user, differ for the user and their nutritionist
+struct Links {
+    reports: String,
+    contributions: String,
+    back: String,
+}
+
+impl Links {
+    fn own() -> Links {
+        Links {
+            reports: "/reports".to_string(),
+            contributions: "/contributions".to_string(),
+            back: "/meals".to_string(),
+        }
+    }
+
+    fn client(username: &str) -> Links {
+        Links {
+            reports: format!("/nutritionist/reports/{}", username),
+            contributions: format!("/nutritionist/contributions/{}", username),
+            back: format!("/nutritionist/meals/user/{}", username),
+        }
+    }
+}
+
+fn render_report(
+    con: &mut redis::Connection,
+    user: &User,
+    links: Links,
+    period: &str,
+    date: &str,
+) -> Response<String> {
+    let (Some(period), Ok(date)) = (ReportPeriod::parse(period), date.parse::<NaiveDate>()) else {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body("Unknown report".to_string())
+            .unwrap();
+    };
+    let (start, end) = period.range(date);
+    let meals = Meal::get_meals_for_user_in_range(con, &user.id, start, end);
+    let report = build_report(&meals, &user.goals, period, date);
+    let t = ReportTemplate {
+        username: user.id.clone(),
+        base: links.reports,
+        back: links.back,
+        contributions: links.contributions,
+        previous: period.previous(report.start),
+        next: period.next(report.start),
+        chart: energy_chart(&report, user.goals.energy),
+        report,
+    };
+    html(t.render().unwrap())
+}
+
+fn render_contributions(
+    con: &mut redis::Connection,
+    user: &User,
+    links: Links,
+    range: &DateRangeForm,
+) -> Response<String> {
+    let (start, end) = range.range(Utc::now().date_naive(), CONTRIBUTION_DAYS);
+    let meals = Meal::get_meals_for_user_in_range(con, &user.id, start, end);
+    let t = ContributionTemplate {
+        username: user.id.clone(),
+        back: links.back,
+        start,
+        end,
+        contributions: contributions(&meals, start, end),
+    };
+    html(t.render().unwrap())
+}
+
+// redirect to the report of the current week
+pub async fn handle_reports() -> Response<String> {
+    Response::builder()
+        .status(StatusCode::SEE_OTHER)
+        .header(
+            "Location",
+            format!("/reports/week/{}", Utc::now().date_naive()),
+        )
+        .body("".into())
+        .unwrap()
+}
+
+pub async fn handle_report(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path((period, date)): Path<(String, String)>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let user = User::check_if_exists_or_create(&mut con, claims.unwrap()).unwrap();
+    render_report(&mut con, &user, Links::own(), &period, &date)
+}
+
+pub async fn handle_subuser_report(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path((username, period, date)): Path<(String, String, String)>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let client = match client_of(&mut con, claims, &username) {
+        Ok(client) => client,
+        Err(response) => return *response,
+    };
+    render_report(&mut con, &client, Links::client(&username), &period, &date)
+}
+
+pub async fn handle_contributions(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Query(range): Query<DateRangeForm>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let user = User::check_if_exists_or_create(&mut con, claims.unwrap()).unwrap();
+    render_contributions(&mut con, &user, Links::own(), &range)
+}
+
+pub async fn handle_subuser_contributions(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(username): Path<String>,
+    Query(range): Query<DateRangeForm>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let client = match client_of(&mut con, claims, &username) {
+        Ok(client) => client,
+        Err(response) => return *response,
+    };
+    render_contributions(&mut con, &client, Links::client(&username), &range)
+}
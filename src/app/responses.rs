@@ -0,0 +1,35 @@
+// Responses shared by the handlers
+use axum::http::{Response, StatusCode};
+
+pub(crate) fn html(body: String) -> Response<String> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(body)
+        .unwrap()
+}
+
+pub(crate) fn redirect(location: &str) -> Response<String> {
+    Response::builder()
+        .status(StatusCode::SEE_OTHER)
+        .header("Location", location)
+        .body("".into())
+        .unwrap()
+}
+
+// a nutritionist asking for a user who is not their client
+pub(crate) fn forbidden() -> Response<String> {
+    Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body("You are not allowed to view this user".to_string())
+        .unwrap()
+}
+
+pub(crate) fn not_found(message: &str) -> Response<String> {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(message.to_string())
+        .unwrap()
+}
@@ -1,10 +1,28 @@
-use crate::app::forms::ProductForm;
-use crate::app::meal_handler::{handle_allmeals, handle_create_meal, handle_meals, handle_subuser_meal, handle_subuser_meals, handle_subusers, handle_subusers_view};
+use crate::app::meal_handler::{handle_allmeals, handle_calendar, handle_create_meal, handle_meals, handle_subuser_calendar, handle_subuser_meal, handle_subuser_meals, handle_subusers, handle_subusers_view};
+use crate::app::hydration_handler::{handle_add_water, handle_undo_water};
+use crate::app::measurement_handler::{
+    handle_add_measurement, handle_delete_measurement, handle_subuser_weight, handle_weight,
+};
+use crate::app::notification_handler::handle_read_notification;
+use crate::app::report_handler::{
+    handle_contributions, handle_report, handle_reports, handle_subuser_contributions,
+    handle_subuser_report,
+};
+use crate::app::user_handler::{
+    handle_accept_proposal, handle_accept_subuser_proposal, handle_diet, handle_goals,
+    handle_profile, handle_save_diet, handle_save_goals, handle_save_profile,
+    handle_save_subuser_diet, handle_save_subuser_goals, handle_save_subuser_profile,
+    handle_subuser_diet, handle_subuser_goals, handle_subuser_profile,
+};
+use crate::app::favorite_handler::{
+    handle_api_add_favorite, handle_api_favorites, handle_api_remove_favorite,
+    handle_toggle_favorite,
+};
 use crate::app::{handler, meal_handler};
 use axum::error_handling::HandleErrorLayer;
-use axum::extract::Path;
+use axum::extract::{FromRef, Path};
 use axum::http::{Response, StatusCode, Uri};
-use axum::routing::{delete, get, post};
+use axum::routing::{delete, get, post, put};
 use axum::{response::IntoResponse, Router};
 use axum_oidc::{
     error::MiddlewareError, EmptyAdditionalClaims, OidcAuthLayer, OidcClaims, OidcLoginLayer,
@@ -16,6 +34,27 @@ use tower_sessions::{
     Expiry, MemoryStore, SessionManagerLayer,
 };
 use crate::app::handler::home_handler;
+use crate::open_food_facts::sdk::OffClient;
+use crate::usda::client::UsdaClient;
+
+/// Shared by all handlers, extracted with `State<T>` for each part
+#[derive(Clone)]
+pub(crate) struct AppState {
+    pub(crate) usda: UsdaClient,
+    pub(crate) off: OffClient,
+}
+
+impl FromRef<AppState> for UsdaClient {
+    fn from_ref(state: &AppState) -> Self {
+        state.usda.clone()
+    }
+}
+
+impl FromRef<AppState> for OffClient {
+    fn from_ref(state: &AppState) -> Self {
+        state.off.clone()
+    }
+}
 
 // static once cell for Templates with tera
 
@@ -55,12 +94,20 @@ pub async fn serve() {
         );
 
     let port = std::env::var("NUT_PORT").unwrap_or("8000".to_string());
+    let state = AppState {
+        usda: UsdaClient::from_env(),
+        off: OffClient::from_env(),
+    };
     let app = Router::new()
         .route(
             "/",
             get(home_handler),
         )
         .route("/:id/search", post(handler::search_usda_handler))
+        .route(
+            "/:id/search/:data_type",
+            get(handler::search_usda_data_type_handler),
+        )
         .route(
             "/newmeal/:type/:date",
             get(handle_create_meal),
@@ -68,37 +115,76 @@ pub async fn serve() {
         .route("/meals", get(handle_meals))
         .route("/nutritionist", get(handle_subusers))
         .route("/nutritionist/meals/user/:username", get(handle_subuser_meals))
+        .route("/meals/calendar/:date", get(handle_calendar))
+        .route("/nutritionist/calendar/:username/:date", get(handle_subuser_calendar))
         .route("/nutritionist/meals/:id", get(handle_subusers_view))
-        .route("/allmeals", get(handle_allmeals))
+        .route("/goals", get(handle_goals).post(handle_save_goals))
         .route(
-            "/meals/:id/search",
-            get(|id: Path<String>| async { meal_handler::handle_search_meal_add(id).await }),
+            "/nutritionist/goals/:username",
+            get(handle_subuser_goals).post(handle_save_subuser_goals),
         )
+        .route("/profile", get(handle_profile).post(handle_save_profile))
+        .route("/profile/accept/:formula", post(handle_accept_proposal))
         .route(
-            "/meals/:id",
-            get(|id: Path<String>| async { meal_handler::handle_meal(id).await }),
+            "/nutritionist/profile/:username",
+            get(handle_subuser_profile).post(handle_save_subuser_profile),
+        )
+        .route(
+            "/nutritionist/profile/:username/accept/:formula",
+            post(handle_accept_subuser_proposal),
         )
+        .route("/diet", get(handle_diet).post(handle_save_diet))
+        .route(
+            "/nutritionist/diet/:username",
+            get(handle_subuser_diet).post(handle_save_subuser_diet),
+        )
+        .route("/weight", get(handle_weight).post(handle_add_measurement))
+        .route("/weight/:id/delete", post(handle_delete_measurement))
+        .route("/nutritionist/weight/:username", get(handle_subuser_weight))
+        .route("/hydration/:date", post(handle_add_water))
+        .route("/hydration/:date/undo", post(handle_undo_water))
+        .route("/notifications/:id/read", post(handle_read_notification))
+        .route("/favorites/:key/toggle", post(handle_toggle_favorite))
+        .route("/api/favorites", get(handle_api_favorites))
+        .route(
+            "/api/favorites/:key",
+            put(handle_api_add_favorite).delete(handle_api_remove_favorite),
+        )
+        .route("/reports", get(handle_reports))
+        .route("/reports/:period/:date", get(handle_report))
+        .route(
+            "/nutritionist/reports/:username/:period/:date",
+            get(handle_subuser_report),
+        )
+        .route("/contributions", get(handle_contributions))
+        .route(
+            "/nutritionist/contributions/:username",
+            get(handle_subuser_contributions),
+        )
+        .route("/allmeals", get(handle_allmeals))
+        .route("/meals/:id/search", get(meal_handler::handle_search_meal_add))
         .route(
             "/meals/:id",
-            post(|id: Path<String>, x: axum::Form<ProductForm>| async {
-                meal_handler::handle_add_content_to_meal(id, x).await
-            }),
+            get(meal_handler::handle_meal),
         )
+        .route("/meals/:id", post(meal_handler::handle_add_content_to_meal))
         .route(
             "/meals/:id/:code",
-            delete(|info: Path<(String, String)>| async {
-                meal_handler::remove_product_from_meal_handler(info).await
-            }),
+            delete(meal_handler::remove_product_from_meal_handler),
         )
         .route("/foo/:id", get(authenticated))
         .route("/usda", get(handler::search_usda_handler))
+        .route("/foods/:fdc_id", get(handler::food_detail_handler))
+        .route("/foods/barcode/:code", get(handler::barcode_handler))
         .route("/logout", get(logout))
         .layer(oidc_login_service)
         //.route("/", get(maybe_authenticated))
         .layer(oidc_auth_service)
-        .layer(session_layer);
+        .layer(session_layer)
+        .with_state(state);
 
     // run our app with hyper, listening globally on port 3000
+    crate::notify::reminder::spawn_reminders(crate::notify::notifiers_from_env());
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{port}"))
         .await
         .unwrap();
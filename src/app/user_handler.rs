@@ -0,0 +1,332 @@
+use crate::app::forms::{DietForm, GoalsForm, ProfileForm};
+use crate::app::responses::{forbidden, not_found, redirect};
+use crate::db;
+use crate::models::diet::{DietRestriction, FoodFlag};
+use crate::models::goals::{goal_label, GoalTarget, NutritionGoals, GOAL_NUTRIENTS};
+use crate::models::models::RedisORM;
+use crate::models::profile::ActivityLevel;
+use crate::models::user::User;
+use crate::nutrition::energy::{propose, propose_all, BmrFormula, EnergyProposal};
+use askama::Template;
+use axum::extract::Path;
+use axum::http::{Response, StatusCode};
+use axum::Form;
+use axum_oidc::{EmptyAdditionalClaims, OidcClaims};
+use chrono::Utc;
+
+pub struct GoalRow {
+    name: String,
+    label: String,
+    unit: String,
+    value: String,
+    percent: bool,
+    allows_percent: bool,
+}
+
+#[derive(Template)]
+#[template(path = "user/goals_view.html")]
+struct GoalsTemplate {
+    username: String,
+    action: String,
+    back: String,
+    rows: Vec<GoalRow>,
+    water_ml: String,
+}
+
+#[derive(Template)]
+#[template(path = "user/profile_view.html")]
+struct ProfileTemplate {
+    username: String,
+    action: String,
+    back: String,
+    form: ProfileForm,
+    activity_levels: Vec<(String, String)>,
+    proposals: Vec<EnergyProposal>,
+}
+
+#[derive(Template)]
+#[template(path = "user/diet_view.html")]
+struct DietTemplate {
+    username: String,
+    action: String,
+    back: String,
+    // (key, label, checked)
+    allergies: Vec<(String, String, bool)>,
+    restrictions: Vec<(String, String, bool)>,
+}
+
+fn goal_rows(goals: &NutritionGoals) -> Vec<GoalRow> {
+    GOAL_NUTRIENTS
+        .iter()
+        .map(|(name, unit, _, kcal_per_gram)| {
+            let (value, percent) = match (*name, goals.target(name)) {
+                ("energy", _) => (goals.energy.map(|v| v.to_string()), false),
+                (_, Some(GoalTarget::Absolute(v))) => (Some(v.to_string()), false),
+                (_, Some(GoalTarget::PercentOfEnergy(v))) => (Some(v.to_string()), true),
+                (_, None) => (None, false),
+            };
+            GoalRow {
+                name: name.to_string(),
+                label: goal_label(name),
+                unit: unit.to_string(),
+                value: value.unwrap_or_default(),
+                percent,
+                allows_percent: kcal_per_gram.is_some(),
+            }
+        })
+        .collect()
+}
+
+fn render_goals(user: &User, action: String, back: String) -> Response<String> {
+    let t = GoalsTemplate {
+        username: user.id.clone(),
+        action,
+        back,
+        rows: goal_rows(&user.goals),
+        water_ml: user.goals.water_ml.map(|v| v.to_string()).unwrap_or_default(),
+    };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(t.render().unwrap())
+        .unwrap()
+}
+
+// the client of the logged in nutritionist, a user who never logged in has no record yet
+pub(crate) fn client_of(
+    con: &mut redis::Connection,
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    username: &str,
+) -> Result<User, Box<Response<String>>> {
+    let user = User::check_if_exists_or_create(con, claims.unwrap()).unwrap();
+    if !user.is_nutritionist_of(username) {
+        return Err(Box::new(forbidden()));
+    }
+    User::check_if_exists_by_id(con, username).map_err(|_| Box::new(not_found("Unknown user")))
+}
+
+pub async fn handle_goals(claims: Option<OidcClaims<EmptyAdditionalClaims>>) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let user = User::check_if_exists_or_create(&mut con, claims.unwrap()).unwrap();
+    render_goals(&user, "/goals".to_string(), "/meals".to_string())
+}
+
+pub async fn handle_save_goals(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    form: Form<GoalsForm>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let mut user = User::check_if_exists_or_create(&mut con, claims.unwrap()).unwrap();
+    user.goals = form.to_goals();
+    user.save(&mut con).expect("DIDNT SAVE");
+    redirect("/meals")
+}
+
+pub async fn handle_subuser_goals(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(username): Path<String>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let client = match client_of(&mut con, claims, &username) {
+        Ok(client) => client,
+        Err(response) => return *response,
+    };
+    render_goals(
+        &client,
+        format!("/nutritionist/goals/{}", username),
+        format!("/nutritionist/meals/user/{}", username),
+    )
+}
+
+pub async fn handle_save_subuser_goals(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(username): Path<String>,
+    form: Form<GoalsForm>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let mut client = match client_of(&mut con, claims, &username) {
+        Ok(client) => client,
+        Err(response) => return *response,
+    };
+    client.goals = form.to_goals();
+    client.save(&mut con).expect("DIDNT SAVE");
+    redirect(&format!("/nutritionist/meals/user/{}", username))
+}
+
+fn render_profile(user: &User, action: String, back: String) -> Response<String> {
+    let t = ProfileTemplate {
+        username: user.id.clone(),
+        action,
+        back,
+        form: ProfileForm::from(&user.profile),
+        activity_levels: ActivityLevel::ALL
+            .iter()
+            .map(|level| (level.key(), level.to_string()))
+            .collect(),
+        proposals: propose_all(&user.profile, Utc::now().date_naive()),
+    };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(t.render().unwrap())
+        .unwrap()
+}
+
+// replaces the goals of the user with the proposal of the formula, if the profile allows it
+fn accept_proposal(user: &mut User, formula: &str) -> bool {
+    let proposal = BmrFormula::parse(formula)
+        .and_then(|formula| propose(&user.profile, formula, Utc::now().date_naive()));
+    match proposal {
+        Some(proposal) => {
+            user.goals = proposal.goals;
+            true
+        }
+        None => false,
+    }
+}
+
+pub async fn handle_profile(claims: Option<OidcClaims<EmptyAdditionalClaims>>) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let user = User::check_if_exists_or_create(&mut con, claims.unwrap()).unwrap();
+    render_profile(&user, "/profile".to_string(), "/meals".to_string())
+}
+
+pub async fn handle_save_profile(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    form: Form<ProfileForm>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let mut user = User::check_if_exists_or_create(&mut con, claims.unwrap()).unwrap();
+    user.profile = form.to_profile();
+    user.save(&mut con).expect("DIDNT SAVE");
+    redirect("/profile")
+}
+
+pub async fn handle_accept_proposal(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(formula): Path<String>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let mut user = User::check_if_exists_or_create(&mut con, claims.unwrap()).unwrap();
+    if accept_proposal(&mut user, &formula) {
+        user.save(&mut con).expect("DIDNT SAVE");
+        return redirect("/goals");
+    }
+    redirect("/profile")
+}
+
+pub async fn handle_subuser_profile(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(username): Path<String>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let client = match client_of(&mut con, claims, &username) {
+        Ok(client) => client,
+        Err(response) => return *response,
+    };
+    render_profile(
+        &client,
+        format!("/nutritionist/profile/{}", username),
+        format!("/nutritionist/meals/user/{}", username),
+    )
+}
+
+pub async fn handle_save_subuser_profile(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(username): Path<String>,
+    form: Form<ProfileForm>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let mut client = match client_of(&mut con, claims, &username) {
+        Ok(client) => client,
+        Err(response) => return *response,
+    };
+    client.profile = form.to_profile();
+    client.save(&mut con).expect("DIDNT SAVE");
+    redirect(&format!("/nutritionist/profile/{}", username))
+}
+
+pub async fn handle_accept_subuser_proposal(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path((username, formula)): Path<(String, String)>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let mut client = match client_of(&mut con, claims, &username) {
+        Ok(client) => client,
+        Err(response) => return *response,
+    };
+    if accept_proposal(&mut client, &formula) {
+        client.save(&mut con).expect("DIDNT SAVE");
+        return redirect(&format!("/nutritionist/goals/{}", username));
+    }
+    redirect(&format!("/nutritionist/profile/{}", username))
+}
+
+fn render_diet(user: &User, action: String, back: String) -> Response<String> {
+    let t = DietTemplate {
+        username: user.id.clone(),
+        action,
+        back,
+        allergies: FoodFlag::ALLERGENS
+            .iter()
+            .map(|flag| (flag.key(), flag.to_string(), user.diet.allergies.contains(flag)))
+            .collect(),
+        restrictions: DietRestriction::ALL
+            .iter()
+            .map(|r| (r.key(), r.to_string(), user.diet.restrictions.contains(r)))
+            .collect(),
+    };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(t.render().unwrap())
+        .unwrap()
+}
+
+pub async fn handle_diet(claims: Option<OidcClaims<EmptyAdditionalClaims>>) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let user = User::check_if_exists_or_create(&mut con, claims.unwrap()).unwrap();
+    render_diet(&user, "/diet".to_string(), "/meals".to_string())
+}
+
+pub async fn handle_save_diet(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    form: Form<DietForm>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let mut user = User::check_if_exists_or_create(&mut con, claims.unwrap()).unwrap();
+    user.diet = form.to_diet();
+    user.save(&mut con).expect("DIDNT SAVE");
+    redirect("/diet")
+}
+
+pub async fn handle_subuser_diet(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(username): Path<String>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let client = match client_of(&mut con, claims, &username) {
+        Ok(client) => client,
+        Err(response) => return *response,
+    };
+    render_diet(
+        &client,
+        format!("/nutritionist/diet/{}", username),
+        format!("/nutritionist/meals/user/{}", username),
+    )
+}
+
+pub async fn handle_save_subuser_diet(
+    claims: Option<OidcClaims<EmptyAdditionalClaims>>,
+    Path(username): Path<String>,
+    form: Form<DietForm>,
+) -> Response<String> {
+    let mut con = db::connector::get_connection().unwrap();
+    let mut client = match client_of(&mut con, claims, &username) {
+        Ok(client) => client,
+        Err(response) => return *response,
+    };
+    client.diet = form.to_diet();
+    client.save(&mut con).expect("DIDNT SAVE");
+    redirect(&format!("/nutritionist/diet/{}", username))
+}
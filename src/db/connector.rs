@@ -112,3 +112,26 @@ pub(crate) fn get_set(con: &mut redis::Connection, key: String) -> Vec<String> {
 pub(crate) fn remove_from_set(p0: &mut Connection, p1: String, p2: String) -> RedisResult<()> {
     redis::cmd("SREM").arg(p1).arg(p2).query(p0)
 }
+
+pub(crate) fn key_exists(con: &mut redis::Connection, key: String) -> bool {
+    redis::cmd("EXISTS").arg(key).query(con).unwrap_or(false)
+}
+
+pub(crate) fn add_to_sorted_set(
+    con: &mut redis::Connection,
+    key: String,
+    score: i64,
+    val: String,
+) -> redis::RedisResult<()> {
+    redis::cmd("ZADD").arg(key).arg(score).arg(val).query(con)
+}
+
+/// Members with a score between min and max, both inclusive
+pub(crate) fn get_sorted_set_range(con: &mut redis::Connection, key: String, min: i64, max: i64) -> Vec<String> {
+    redis::cmd("ZRANGEBYSCORE")
+        .arg(key)
+        .arg(min)
+        .arg(max)
+        .query(con)
+        .unwrap_or_default()
+}
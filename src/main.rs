@@ -1,10 +1,19 @@
 mod app;
 mod db;
 mod models;
+mod notify;
+mod nutrition;
+mod open_food_facts;
 mod usda;
 
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("import-fdc") {
+        dotenvy::dotenv().ok();
+        usda::import::run(&args[2..]);
+        return;
+    }
     app::server::serve().await;
 }
 #[cfg(test)]
@@ -1,3 +1,13 @@
+pub(crate) mod custom_food;
+pub(crate) mod diet;
+pub(crate) mod favorite;
+pub(crate) mod food;
+pub(crate) mod goals;
+pub(crate) mod hydration;
 pub(crate) mod meal;
+pub(crate) mod measurement;
 pub(crate) mod models;
+pub(crate) mod notification;
+pub(crate) mod profile;
+pub(crate) mod recipe;
 pub(crate) mod user;
@@ -0,0 +1,57 @@
+use crate::models::food::{FoodItem, FoodKey, FoodSource};
+use crate::models::models::RedisORM;
+use crate::usda::search::NutrientValues;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// A food a user entered with its own nutrients, e.g. a local bakery's bread, stored as
+// custom_food:<uuid>
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct CustomFood {
+    pub(crate) id: Uuid,
+    pub(crate) username: String,
+    pub(crate) name: String,
+    pub(crate) food_category: Option<String>,
+    /// Nutrients per 100 g as entered
+    pub(crate) nutrients: NutrientValues,
+}
+
+impl FoodItem for CustomFood {
+    fn key(&self) -> FoodKey {
+        FoodKey {
+            source: FoodSource::Custom,
+            id: self.id.to_string(),
+        }
+    }
+
+    fn display_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn nutrients_per_100g(&self) -> NutrientValues {
+        self.nutrients.clone()
+    }
+}
+
+impl RedisORM for CustomFood {
+    fn example() -> Self
+    where
+        Self: Sized,
+    {
+        CustomFood {
+            id: Uuid::new_v4(),
+            username: "12345".to_string(),
+            name: "".to_string(),
+            food_category: None,
+            nutrients: NutrientValues::default(),
+        }
+    }
+
+    fn redis_type_name() -> String {
+        "custom_food".to_string()
+    }
+
+    fn redis_id(&self) -> String {
+        self.id.to_string()
+    }
+}
@@ -0,0 +1,562 @@
+use crate::models::food::StoredFood;
+use crate::usda::search::Food;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+// Something a food contains that matters for allergies or diets, the first 14 are the
+// allergens that have to be declared in the EU
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum FoodFlag {
+    Gluten,
+    Milk,
+    Eggs,
+    Peanuts,
+    TreeNuts,
+    Soy,
+    Fish,
+    Crustaceans,
+    Molluscs,
+    Sesame,
+    Celery,
+    Mustard,
+    Sulphites,
+    Lupin,
+    Meat,
+    Pork,
+    Alcohol,
+    // other animal products like honey
+    AnimalProduct,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum DietRestriction {
+    Vegetarian,
+    Vegan,
+    Halal,
+    Kosher,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub(crate) struct DietaryProfile {
+    pub(crate) allergies: Vec<FoodFlag>,
+    pub(crate) restrictions: Vec<DietRestriction>,
+}
+
+// A flag of a food that clashes with the dietary profile
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DietConflict {
+    pub(crate) flag: FoodFlag,
+    // "allergy" or the restriction that forbids the flag
+    pub(crate) reason: String,
+}
+
+// Curated keywords matched as whole words against the name and category of a food,
+// the phrases of the second list are removed first to avoid false positives
+const FLAG_KEYWORDS: [(FoodFlag, &[&str], &[&str]); 18] = [
+    (
+        FoodFlag::Gluten,
+        &[
+            "wheat",
+            "bread",
+            "breaded",
+            "pasta",
+            "spaghetti",
+            "macaroni",
+            "noodle",
+            "flour",
+            "barley",
+            "rye",
+            "couscous",
+            "semolina",
+            "bulgur",
+            "spelt",
+            "seitan",
+            "cracker",
+            "cookie",
+            "cake",
+            "pizza",
+            "beer",
+            "malt",
+            "biscuit",
+            "croissant",
+            "bagel",
+            "muffin",
+            "pretzel",
+            "pastry",
+            "baked product",
+        ],
+        &[
+            "rice flour",
+            "corn flour",
+            "almond flour",
+            "coconut flour",
+            "rice noodle",
+            "rice cake",
+        ],
+    ),
+    (
+        FoodFlag::Milk,
+        &[
+            "milk", "cheese", "butter", "cream", "yogurt", "yoghurt", "whey", "casein", "lactose",
+            "kefir", "ghee", "custard", "dairy",
+        ],
+        &[
+            "peanut butter",
+            "almond butter",
+            "nut butter",
+            "cocoa butter",
+            "apple butter",
+            "coconut milk",
+            "almond milk",
+            "soy milk",
+            "oat milk",
+            "rice milk",
+            "coconut cream",
+            "cream of tartar",
+            "dairy and egg product",
+        ],
+    ),
+    (
+        FoodFlag::Eggs,
+        &["egg", "mayonnaise", "meringue", "omelet", "omelette"],
+        &["dairy and egg product"],
+    ),
+    (FoodFlag::Peanuts, &["peanut"], &[]),
+    (
+        FoodFlag::TreeNuts,
+        &[
+            "nut",
+            "almond",
+            "walnut",
+            "cashew",
+            "hazelnut",
+            "pecan",
+            "pistachio",
+            "macadamia",
+            "brazilnut",
+            "praline",
+            "marzipan",
+        ],
+        &["nut and seed product"],
+    ),
+    (
+        FoodFlag::Soy,
+        &[
+            "soy", "soya", "soybean", "tofu", "tempeh", "edamame", "miso",
+        ],
+        &[],
+    ),
+    (
+        FoodFlag::Fish,
+        &[
+            "fish", "finfish", "salmon", "tuna", "cod", "trout", "sardine", "anchovy", "anchovie",
+            "mackerel", "herring", "tilapia", "halibut", "haddock", "pollock", "carp", "catfish",
+        ],
+        &["finfish and shellfish product"],
+    ),
+    (
+        FoodFlag::Crustaceans,
+        &[
+            "shrimp",
+            "prawn",
+            "crab",
+            "lobster",
+            "crayfish",
+            "crawfish",
+            "shellfish",
+            "krill",
+        ],
+        &["finfish and shellfish product"],
+    ),
+    (
+        FoodFlag::Molluscs,
+        &[
+            "mussel",
+            "clam",
+            "oyster",
+            "scallop",
+            "squid",
+            "octopus",
+            "snail",
+            "calamari",
+            "cuttlefish",
+            "abalone",
+        ],
+        &[],
+    ),
+    (FoodFlag::Sesame, &["sesame", "tahini", "hummu"], &[]),
+    (FoodFlag::Celery, &["celery", "celeriac"], &[]),
+    (FoodFlag::Mustard, &["mustard"], &[]),
+    (FoodFlag::Sulphites, &["wine", "sulfite", "sulphite"], &[]),
+    (FoodFlag::Lupin, &["lupin", "lupine"], &[]),
+    (
+        FoodFlag::Meat,
+        &[
+            "meat",
+            "beef",
+            "chicken",
+            "turkey",
+            "lamb",
+            "veal",
+            "duck",
+            "goose",
+            "venison",
+            "mutton",
+            "goat",
+            "rabbit",
+            "bison",
+            "sausage",
+            "frankfurter",
+            "hot dog",
+            "jerky",
+            "poultry",
+            "gelatin",
+        ],
+        &["coconut meat", "goat cheese", "goat milk"],
+    ),
+    (
+        FoodFlag::Pork,
+        &[
+            "pork",
+            "ham",
+            "bacon",
+            "lard",
+            "prosciutto",
+            "salami",
+            "pepperoni",
+            "chorizo",
+            "pancetta",
+        ],
+        &[],
+    ),
+    (
+        FoodFlag::Alcohol,
+        &[
+            "alcoholic",
+            "beer",
+            "wine",
+            "vodka",
+            "whiskey",
+            "whisky",
+            "rum",
+            "gin",
+            "brandy",
+            "liquor",
+            "liqueur",
+        ],
+        &["non alcoholic", "wine vinegar"],
+    ),
+    (
+        FoodFlag::AnimalProduct,
+        &["honey", "beeswax", "carmine", "shellac"],
+        &[],
+    ),
+];
+
+// lowercase words without plural s, padded with spaces so phrases match on word boundaries
+fn normalize(text: &str) -> String {
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let words: Vec<String> = words
+        .iter()
+        .map(|w| {
+            let w = w.to_lowercase();
+            match w.strip_suffix('s') {
+                Some(stem) if !stem.ends_with('s') && stem.len() > 2 => stem.to_string(),
+                _ => w,
+            }
+        })
+        .collect();
+    format!(" {} ", words.join(" "))
+}
+
+/// Flags derived from the name and category of a food
+pub(crate) fn derive_flags(text: &str) -> Vec<FoodFlag> {
+    let text = normalize(text);
+    let mut flags: Vec<FoodFlag> = FLAG_KEYWORDS
+        .iter()
+        .filter(|(_, keywords, exclusions)| {
+            let text = exclusions.iter().fold(text.clone(), |text, phrase| {
+                text.replace(&normalize(phrase), " ")
+            });
+            keywords.iter().any(|k| text.contains(&normalize(k)))
+        })
+        .map(|(flag, ..)| *flag)
+        .collect();
+    // pork is meat
+    if flags.contains(&FoodFlag::Pork) && !flags.contains(&FoodFlag::Meat) {
+        flags.push(FoodFlag::Meat);
+        flags.sort();
+    }
+    flags
+}
+
+impl FoodFlag {
+    pub(crate) const ALLERGENS: [FoodFlag; 14] = [
+        FoodFlag::Gluten,
+        FoodFlag::Milk,
+        FoodFlag::Eggs,
+        FoodFlag::Peanuts,
+        FoodFlag::TreeNuts,
+        FoodFlag::Soy,
+        FoodFlag::Fish,
+        FoodFlag::Crustaceans,
+        FoodFlag::Molluscs,
+        FoodFlag::Sesame,
+        FoodFlag::Celery,
+        FoodFlag::Mustard,
+        FoodFlag::Sulphites,
+        FoodFlag::Lupin,
+    ];
+
+    pub fn key(&self) -> String {
+        format!("{:?}", self).to_lowercase()
+    }
+
+    /// Parses a key, also accepts Open Food Facts allergen tags like "en:milk"
+    pub(crate) fn parse(s: &str) -> Option<FoodFlag> {
+        let s = s.trim().to_lowercase();
+        let s = s.strip_prefix("en:").unwrap_or(&s);
+        match s {
+            "gluten" => Some(FoodFlag::Gluten),
+            "milk" | "lactose" => Some(FoodFlag::Milk),
+            "eggs" => Some(FoodFlag::Eggs),
+            "peanuts" => Some(FoodFlag::Peanuts),
+            "treenuts" | "nuts" => Some(FoodFlag::TreeNuts),
+            "soy" | "soybeans" => Some(FoodFlag::Soy),
+            "fish" => Some(FoodFlag::Fish),
+            "crustaceans" => Some(FoodFlag::Crustaceans),
+            "molluscs" => Some(FoodFlag::Molluscs),
+            "sesame" | "sesame-seeds" => Some(FoodFlag::Sesame),
+            "celery" => Some(FoodFlag::Celery),
+            "mustard" => Some(FoodFlag::Mustard),
+            "sulphites" | "sulphur-dioxide-and-sulphites" => Some(FoodFlag::Sulphites),
+            "lupin" => Some(FoodFlag::Lupin),
+            "meat" => Some(FoodFlag::Meat),
+            "pork" => Some(FoodFlag::Pork),
+            "alcohol" => Some(FoodFlag::Alcohol),
+            "animalproduct" => Some(FoodFlag::AnimalProduct),
+            _ => None,
+        }
+    }
+}
+
+impl Display for FoodFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            FoodFlag::Milk => "Milk / Lactose",
+            FoodFlag::TreeNuts => "Tree nuts",
+            FoodFlag::AnimalProduct => "Animal product",
+            _ => return write!(f, "{:?}", self),
+        };
+        write!(f, "{}", label)
+    }
+}
+
+impl DietRestriction {
+    pub(crate) const ALL: [DietRestriction; 4] = [
+        DietRestriction::Vegetarian,
+        DietRestriction::Vegan,
+        DietRestriction::Halal,
+        DietRestriction::Kosher,
+    ];
+
+    pub fn key(&self) -> String {
+        format!("{:?}", self).to_lowercase()
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<DietRestriction> {
+        DietRestriction::ALL.into_iter().find(|r| r.key() == s)
+    }
+
+    /// Flags a food must not have to fit the diet
+    pub(crate) fn forbidden(&self) -> &'static [FoodFlag] {
+        match self {
+            DietRestriction::Vegetarian => &[
+                FoodFlag::Meat,
+                FoodFlag::Pork,
+                FoodFlag::Fish,
+                FoodFlag::Crustaceans,
+                FoodFlag::Molluscs,
+            ],
+            DietRestriction::Vegan => &[
+                FoodFlag::Meat,
+                FoodFlag::Pork,
+                FoodFlag::Fish,
+                FoodFlag::Crustaceans,
+                FoodFlag::Molluscs,
+                FoodFlag::Milk,
+                FoodFlag::Eggs,
+                FoodFlag::AnimalProduct,
+            ],
+            DietRestriction::Halal => &[FoodFlag::Pork, FoodFlag::Alcohol],
+            DietRestriction::Kosher => &[FoodFlag::Pork, FoodFlag::Crustaceans, FoodFlag::Molluscs],
+        }
+    }
+}
+
+impl Display for DietRestriction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl DietaryProfile {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.allergies.is_empty() && self.restrictions.is_empty()
+    }
+
+    /// Every flag of the food that the user has to avoid, with the reason why
+    pub(crate) fn conflicts(&self, flags: &[FoodFlag]) -> Vec<DietConflict> {
+        let mut conflicts = vec![];
+        for flag in flags {
+            if self.allergies.contains(flag) {
+                conflicts.push(DietConflict {
+                    flag: *flag,
+                    reason: "allergy".to_string(),
+                });
+            }
+            for restriction in self.restrictions.iter() {
+                if restriction.forbidden().contains(flag) {
+                    conflicts.push(DietConflict {
+                        flag: *flag,
+                        reason: format!("not {}", restriction),
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+impl Food {
+    /// Allergen and diet flags derived from the name, FDC category and ingredients, together
+    /// with the allergen and trace tags of Open Food Facts products
+    pub fn flags(&self) -> Vec<FoodFlag> {
+        let mut flags = derive_flags(&format!(
+            "{} {}",
+            self.description,
+            self.food_category.clone().unwrap_or_default()
+        ));
+        flags.extend(self.ingredient_flags());
+        flags.extend(
+            self.allergen_tags
+                .iter()
+                .filter_map(|tag| FoodFlag::parse(tag)),
+        );
+        flags.sort();
+        flags.dedup();
+        flags
+    }
+}
+
+impl StoredFood {
+    /// Flags of the food, of the name of a custom food and of all contents of a recipe
+    pub fn flags(&self) -> Vec<FoodFlag> {
+        let mut flags = match self {
+            StoredFood::Custom(custom) => derive_flags(&format!(
+                "{} {}",
+                custom.name,
+                custom.food_category.clone().unwrap_or_default()
+            )),
+            StoredFood::Recipe(recipe) => recipe
+                .contents
+                .iter()
+                .flat_map(|c| c.product.flags())
+                .collect(),
+            _ => self.food().map(|food| food.flags()).unwrap_or_default(),
+        };
+        flags.sort();
+        flags.dedup();
+        flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_flags() {
+        assert_eq!(
+            derive_flags("Bread, whole-wheat, commercially prepared"),
+            vec![FoodFlag::Gluten]
+        );
+        assert_eq!(
+            derive_flags("Peanut butter, smooth style"),
+            vec![FoodFlag::Peanuts]
+        );
+        assert_eq!(
+            derive_flags("Cheese, cheddar Dairy and Egg Products"),
+            vec![FoodFlag::Milk]
+        );
+        assert_eq!(derive_flags("Eggplant, raw"), vec![]);
+        assert_eq!(
+            derive_flags("Nuts, coconut meat, raw"),
+            vec![FoodFlag::TreeNuts]
+        );
+        assert_eq!(
+            derive_flags("Bacon, pre-sliced Pork Products"),
+            vec![FoodFlag::Meat, FoodFlag::Pork]
+        );
+        assert_eq!(
+            derive_flags("Crustaceans, shrimp, raw Finfish and Shellfish Products"),
+            vec![FoodFlag::Crustaceans]
+        );
+    }
+
+    #[test]
+    fn test_parse_off_tags() {
+        assert_eq!(FoodFlag::parse("en:milk"), Some(FoodFlag::Milk));
+        assert_eq!(FoodFlag::parse("en:nuts"), Some(FoodFlag::TreeNuts));
+        assert_eq!(
+            FoodFlag::parse("en:sulphur-dioxide-and-sulphites"),
+            Some(FoodFlag::Sulphites)
+        );
+        assert_eq!(
+            FoodFlag::parse(&FoodFlag::AnimalProduct.key()),
+            Some(FoodFlag::AnimalProduct)
+        );
+        assert_eq!(FoodFlag::parse("en:unknown"), None);
+    }
+
+    #[test]
+    fn test_flags_of_off_tags() {
+        let mut food = Food::new(1, "Chocolate bar", vec![]);
+        food.allergen_tags = vec!["en:milk".to_string(), "en:soybeans".to_string()];
+        // traces of nuts
+        food.allergen_tags.push("en:nuts".to_string());
+        food.allergen_tags.push("en:unknown".to_string());
+        assert_eq!(
+            food.flags(),
+            vec![FoodFlag::Milk, FoodFlag::TreeNuts, FoodFlag::Soy]
+        );
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let profile = DietaryProfile {
+            allergies: vec![FoodFlag::Gluten],
+            restrictions: vec![DietRestriction::Vegan, DietRestriction::Halal],
+        };
+        assert!(profile
+            .conflicts(&derive_flags("Apples, raw, with skin"))
+            .is_empty());
+        let conflicts = profile.conflicts(&derive_flags("Pizza, pepperoni topping"));
+        let reasons: Vec<(FoodFlag, &str)> = conflicts
+            .iter()
+            .map(|c| (c.flag, c.reason.as_str()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (FoodFlag::Gluten, "allergy"),
+                (FoodFlag::Meat, "not Vegan"),
+                (FoodFlag::Pork, "not Vegan"),
+                (FoodFlag::Pork, "not Halal"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use crate::db;
+use crate::models::food::{FoodItem, FoodKey, StoredFood};
+use crate::usda::search::NutrientValues;
+use redis::{Connection, RedisResult};
+use serde::Serialize;
+
+// Foods a user starred, stored as a set of food keys per user
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Favorites {
+    pub(crate) keys: Vec<FoodKey>,
+}
+
+impl Favorites {
+    fn user_set_key(username: &str) -> String {
+        "favorites:".to_string() + username
+    }
+
+    pub(crate) fn of_user(con: &mut Connection, username: &str) -> Favorites {
+        let mut keys: Vec<FoodKey> = db::connector::get_set(con, Self::user_set_key(username))
+            .iter()
+            .filter_map(|key| key.parse().ok())
+            .collect();
+        keys.sort_by_key(|key| key.to_string());
+        Favorites { keys }
+    }
+
+    pub(crate) fn add(con: &mut Connection, username: &str, key: &FoodKey) -> RedisResult<()> {
+        db::connector::add_to_set(con, Self::user_set_key(username), key.to_string())
+    }
+
+    pub(crate) fn remove(con: &mut Connection, username: &str, key: &FoodKey) -> RedisResult<()> {
+        db::connector::remove_from_set(con, Self::user_set_key(username), key.to_string())
+    }
+
+    pub fn contains(&self, key: &FoodKey) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// The starred foods by name, foods missing from the store are left out
+    pub(crate) fn foods(&self, con: &mut Connection) -> Vec<StoredFood> {
+        let mut foods: Vec<StoredFood> = self
+            .keys
+            .iter()
+            .filter_map(|key| key.stored(con))
+            .collect();
+        foods.sort_by_key(|f| f.display_name());
+        foods
+    }
+}
+
+/// A favorite as returned by the API, nutrients per 100 g
+#[derive(Debug, Serialize)]
+pub(crate) struct FavoriteFood {
+    /// FDC id of USDA foods
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) fdc_id: Option<i32>,
+    /// Source and id, as accepted when adding a food to a meal
+    pub(crate) key: String,
+    pub(crate) name: String,
+    /// FDC data set of USDA and Open Food Facts foods
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) data_set: Option<String>,
+    pub(crate) nutrients: NutrientValues,
+}
+
+impl From<&StoredFood> for FavoriteFood {
+    fn from(food: &StoredFood) -> Self {
+        FavoriteFood {
+            fdc_id: food.fdc_id(),
+            key: food.key().to_string(),
+            name: food.display_name(),
+            data_set: food.food().map(|f| f.data_set),
+            nutrients: food.nutrients_per_100g(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::custom_food::CustomFood;
+    use crate::models::models::RedisORM;
+    use crate::usda::nutrients;
+    use crate::usda::search::{Food, Nutrient};
+
+    #[test]
+    fn test_favorite_food_json() {
+        let food = StoredFood::Usda(Food::new(
+            171688,
+            "Apples, raw, with skin",
+            vec![Nutrient::new(nutrients::ENERGY, "Energy", 52.0, "KCAL")],
+        ));
+        let json = serde_json::to_value(FavoriteFood::from(&food)).unwrap();
+        assert_eq!(json["fdc_id"], 171688);
+        assert_eq!(json["key"], "usda:171688");
+        assert_eq!(json["data_set"], "Foundation");
+        assert_eq!(json["nutrients"]["energy"], 52.0);
+        let custom = StoredFood::Custom(CustomFood {
+            name: "Grandma's bread".to_string(),
+            ..CustomFood::example()
+        });
+        let json = serde_json::to_value(FavoriteFood::from(&custom)).unwrap();
+        assert!(json.get("fdc_id").is_none());
+        assert_eq!(json["key"], custom.key().to_string());
+        assert_eq!(json["name"], "Grandma's bread");
+        let favorites = Favorites {
+            keys: vec!["usda:171688".parse().unwrap()],
+        };
+        assert!(favorites.contains(&food.key()));
+    }
+}
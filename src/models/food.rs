@@ -0,0 +1,365 @@
+// Source agnostic view of a food: where it comes from, how it is named and what it provides.
+// Each source keeps its own record, `StoredFood` holds any of them so meals can mix them.
+use crate::models::custom_food::CustomFood;
+use crate::models::models::RedisORM;
+use crate::models::recipe::Recipe;
+use crate::open_food_facts::product::Product;
+use crate::usda::search::{Food, NutrientValues};
+use redis::Connection;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) enum FoodSource {
+    /// FoodData Central, keyed by the FDC id
+    #[default]
+    #[serde(rename = "usda")]
+    Usda,
+    /// Open Food Facts, keyed by the barcode
+    #[serde(rename = "off")]
+    OpenFoodFacts,
+    /// Entered by a user, keyed by a uuid
+    #[serde(rename = "custom")]
+    Custom,
+    /// Made of other foods, keyed by a uuid
+    #[serde(rename = "recipe")]
+    Recipe,
+}
+
+impl FoodSource {
+    const ALL: [FoodSource; 4] = [
+        FoodSource::Usda,
+        FoodSource::OpenFoodFacts,
+        FoodSource::Custom,
+        FoodSource::Recipe,
+    ];
+
+    pub(crate) fn prefix(&self) -> &'static str {
+        match self {
+            FoodSource::Usda => "usda",
+            FoodSource::OpenFoodFacts => "off",
+            FoodSource::Custom => "custom",
+            FoodSource::Recipe => "recipe",
+        }
+    }
+}
+
+/// Source and id of a food, written as "usda:171688", "off:3017620422003" or
+/// "recipe:67e55044-10b1-426f-9247-bb680e5fe0c8"
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct FoodKey {
+    pub(crate) source: FoodSource,
+    pub(crate) id: String,
+}
+
+impl Display for FoodKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.source.prefix(), self.id)
+    }
+}
+
+impl FromStr for FoodKey {
+    type Err = String;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let (prefix, id) = key
+            .split_once(':')
+            .ok_or(format!("food key {} has no source", key))?;
+        let source = FoodSource::ALL
+            .into_iter()
+            .find(|s| s.prefix() == prefix)
+            .ok_or(format!("unknown food source {}", prefix))?;
+        if id.is_empty() {
+            return Err(format!("food key {} has no id", key));
+        }
+        Ok(FoodKey {
+            source,
+            id: id.to_string(),
+        })
+    }
+}
+
+// stored as its text form, so keys read like the ones sent by forms
+impl Serialize for FoodKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for FoodKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl FoodKey {
+    /// The stored food of the key
+    pub(crate) fn stored(&self, con: &mut Connection) -> Option<StoredFood> {
+        match self.source {
+            FoodSource::Usda => {
+                Food::fetch_from_uuid(con, &self.id.parse::<i32>().ok()?.to_string())
+                    .filter(|food| food.source() == FoodSource::Usda)
+                    .map(|food| StoredFood::Usda(food.generate_nutrient_values()))
+            }
+            FoodSource::OpenFoodFacts => {
+                Product::fetch_from_uuid(con, &self.id).map(StoredFood::OpenFoodFacts)
+            }
+            FoodSource::Custom => {
+                CustomFood::fetch_from_uuid(con, &self.id).map(StoredFood::Custom)
+            }
+            FoodSource::Recipe => Recipe::fetch_from_uuid(con, &self.id).map(StoredFood::Recipe),
+        }
+    }
+}
+
+/// A food of any source as it is logged in meals, starred and listed in the quick picks
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) enum StoredFood {
+    #[serde(rename = "usda")]
+    Usda(Food),
+    #[serde(rename = "off")]
+    OpenFoodFacts(Product),
+    #[serde(rename = "custom")]
+    Custom(CustomFood),
+    #[serde(rename = "recipe")]
+    Recipe(Recipe),
+}
+
+impl StoredFood {
+    fn item(&self) -> &dyn FoodItem {
+        match self {
+            StoredFood::Usda(food) => food,
+            StoredFood::OpenFoodFacts(product) => product,
+            StoredFood::Custom(custom) => custom,
+            StoredFood::Recipe(recipe) => recipe,
+        }
+    }
+
+    /// The FDC record of USDA foods and the one Open Food Facts products convert to, which the
+    /// beverage detection, Nutri-Score and NOVA group are computed from
+    pub(crate) fn food(&self) -> Option<Food> {
+        match self {
+            StoredFood::Usda(food) => Some(food.clone()),
+            StoredFood::OpenFoodFacts(product) => product.clone().into_food(),
+            StoredFood::Custom(_) | StoredFood::Recipe(_) => None,
+        }
+    }
+
+    /// FDC id of USDA foods, which have a details page
+    pub(crate) fn fdc_id(&self) -> Option<i32> {
+        match self {
+            StoredFood::Usda(food) => Some(food.id),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn food_category(&self) -> Option<String> {
+        match self {
+            StoredFood::Custom(custom) => custom.food_category.clone(),
+            StoredFood::Recipe(_) => None,
+            _ => self.food()?.food_category,
+        }
+    }
+
+    /// Water in ml per 100 g the food provides if it is a beverage, a recipe provides the water
+    /// of its beverages. Assumes 1 g = 1 ml without water data.
+    pub(crate) fn hydration_per_100g(&self) -> f32 {
+        if let StoredFood::Recipe(recipe) = self {
+            let grams = recipe.total_grams();
+            if grams <= 0.0 {
+                return 0.0;
+            }
+            let water: f32 = recipe.contents.iter().map(|c| c.hydration_ml()).sum();
+            return water / grams * 100.0;
+        }
+        match self.food() {
+            Some(food) if food.is_beverage() => food.water_per_100g().unwrap_or(100.0),
+            _ => 0.0,
+        }
+    }
+}
+
+impl FoodItem for StoredFood {
+    fn key(&self) -> FoodKey {
+        self.item().key()
+    }
+
+    fn display_name(&self) -> String {
+        self.item().display_name()
+    }
+
+    fn nutrients_per_100g(&self) -> NutrientValues {
+        self.item().nutrients_per_100g()
+    }
+}
+
+pub(crate) trait FoodItem {
+    fn key(&self) -> FoodKey;
+    /// Name shown in search results, meals and reports
+    fn display_name(&self) -> String;
+    /// Nutrients per 100 g
+    fn nutrients_per_100g(&self) -> NutrientValues;
+}
+
+impl FoodItem for Food {
+    fn key(&self) -> FoodKey {
+        let id = match (self.source(), &self.gtin_upc) {
+            (FoodSource::OpenFoodFacts, Some(code)) => code.clone(),
+            _ => self.id.to_string(),
+        };
+        FoodKey {
+            source: self.source(),
+            id,
+        }
+    }
+
+    fn display_name(&self) -> String {
+        let with_brand = |prefix: &str, brands: &[&Option<String>]| {
+            let mut name = format!("{}{}", prefix, self.description);
+            for brand in brands.iter().copied().flatten() {
+                name.push_str(", ");
+                name.push_str(brand);
+            }
+            name
+        };
+        match self.source() {
+            FoodSource::OpenFoodFacts => with_brand("OFF: ", &[&self.brand_owner]),
+            _ => match self.data_set.as_str() {
+                "Branded" => with_brand("BRANDED: ", &[&self.brand_name, &self.brand_owner]),
+                "Survey (FNDDS)" => format!("Survey: {}", self.description),
+                "Foundation" => format!("Foundation: {}", self.description),
+                _ => format!("SR Legacy: {}", self.description),
+            },
+        }
+    }
+
+    fn nutrients_per_100g(&self) -> NutrientValues {
+        self.get_numerical_macros()
+    }
+}
+
+impl FoodItem for Product {
+    fn key(&self) -> FoodKey {
+        FoodKey {
+            source: FoodSource::OpenFoodFacts,
+            id: self.code.clone(),
+        }
+    }
+
+    fn display_name(&self) -> String {
+        self.clone()
+            .into_food()
+            .map(|food| food.display_name())
+            .unwrap_or(format!("OFF: {}", self.code))
+    }
+
+    fn nutrients_per_100g(&self) -> NutrientValues {
+        self.clone()
+            .into_food()
+            .map(|food| food.nutrients_per_100g())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::meal::MealContent;
+    use crate::open_food_facts::models::Nutriments;
+
+    #[test]
+    fn test_food_key() {
+        let key: FoodKey = "off:3017620422003".parse().unwrap();
+        assert_eq!(key.source, FoodSource::OpenFoodFacts);
+        assert_eq!(key.to_string(), "off:3017620422003");
+        assert_eq!(
+            "usda:171688".parse::<FoodKey>().unwrap().to_string(),
+            "usda:171688"
+        );
+        assert!("171688".parse::<FoodKey>().is_err());
+        assert!("off:".parse::<FoodKey>().is_err());
+        assert!("fridge:1".parse::<FoodKey>().is_err());
+    }
+
+    #[test]
+    fn test_sources_share_one_interface() {
+        let product = Product {
+            code: "3017620422003".to_string(),
+            product_name: Some("Nutella".to_string()),
+            brands: Some("Ferrero".to_string()),
+            nutriments: Some(Nutriments {
+                energy_kcal_100g: Some(539.0),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut oats = Food::new(173904, "Oats", vec![]);
+        oats.data_set = "SR Legacy".to_string();
+        let off_food = product.clone().into_food().unwrap();
+        let content = MealContent::new(StoredFood::Usda(oats.clone()), 40.0);
+        let bread = CustomFood {
+            name: "Grandma's bread".to_string(),
+            ..CustomFood::example()
+        };
+        let recipe = Recipe {
+            name: "Bread with spread".to_string(),
+            contents: vec![
+                MealContent::new(StoredFood::Custom(bread.clone()), 60.0),
+                MealContent::new(StoredFood::OpenFoodFacts(product.clone()), 20.0),
+            ],
+            ..Recipe::example()
+        };
+        let items: [&dyn FoodItem; 6] = [&product, &off_food, &oats, &content, &bread, &recipe];
+        let keys: Vec<String> = items.iter().map(|i| i.key().to_string()).collect();
+        assert_eq!(
+            keys,
+            [
+                "off:3017620422003".to_string(),
+                "off:3017620422003".to_string(),
+                "usda:173904".to_string(),
+                "usda:173904".to_string(),
+                format!("custom:{}", bread.id),
+                format!("recipe:{}", recipe.id),
+            ]
+        );
+        assert_eq!(
+            recipe.key().to_string().parse::<FoodKey>(),
+            Ok(recipe.key())
+        );
+        // 20 g of the spread in 80 g
+        assert_eq!(recipe.nutrients_per_100g().energy, 134.75);
+        assert_eq!(product.display_name(), "OFF: Nutella, Ferrero");
+        assert_eq!(oats.display_name(), "SR Legacy: Oats");
+        assert_eq!(product.nutrients_per_100g().energy, 539.0);
+    }
+
+    #[test]
+    fn test_stored_without_source() {
+        let product = Product {
+            code: "3017620422003".to_string(),
+            product_name: Some("Nutella".to_string()),
+            nutriments: Some(Nutriments::default()),
+            ..Default::default()
+        };
+        let mut json = serde_json::to_value(product.into_food().unwrap()).unwrap();
+        json.as_object_mut().unwrap().remove("source");
+        let food: Food = serde_json::from_value(json).unwrap();
+        assert_eq!(food.key().to_string(), "off:3017620422003");
+
+        // meal contents logged before foods had a source
+        let json = serde_json::json!({
+            "product": serde_json::to_value(Food::new(171688, "Apples", vec![])).unwrap(),
+            "quantity": 100.0,
+            "id": "67e55044-10b1-426f-9247-bb680e5fe0c8",
+        });
+        let content: MealContent = serde_json::from_value(json).unwrap();
+        assert_eq!(content.key().to_string(), "usda:171688");
+        let json = serde_json::to_value(&content).unwrap();
+        assert!(json["product"]["usda"].is_object());
+        let content: MealContent = serde_json::from_value(json).unwrap();
+        assert_eq!(content.key().to_string(), "usda:171688");
+    }
+}
@@ -0,0 +1,293 @@
+use crate::models::hydration::DEFAULT_WATER_GOAL_ML;
+use crate::usda::nutrients;
+use crate::usda::search::NutrientValues;
+use serde::{Deserialize, Serialize};
+
+// Energy targets within this fraction count as reached
+const ON_TRACK_TOLERANCE: f32 = 0.1;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) enum GoalTarget {
+    // grams per day
+    Absolute(f32),
+    // share of the daily energy goal, e.g. 30.0 for 30%
+    PercentOfEnergy(f32),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub(crate) struct NutritionGoals {
+    // kcal per day
+    pub(crate) energy: Option<f32>,
+    pub(crate) carbohydrates: Option<GoalTarget>,
+    pub(crate) proteins: Option<GoalTarget>,
+    pub(crate) fats: Option<GoalTarget>,
+    pub(crate) fiber: Option<GoalTarget>,
+    pub(crate) sugar: Option<GoalTarget>,
+    pub(crate) salt: Option<GoalTarget>,
+    pub(crate) sodium: Option<GoalTarget>,
+    // ml of water per day
+    pub(crate) water_ml: Option<f32>,
+}
+
+// How a consumed amount is judged against its target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum GoalKind {
+    // should be met closely
+    Target,
+    // should at least be reached
+    Minimum,
+    // should not be exceeded
+    Limit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum GoalStatus {
+    Under,
+    OnTrack,
+    Over,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct GoalProgress {
+    pub(crate) name: String,
+    pub(crate) unit: String,
+    pub(crate) consumed: f32,
+    pub(crate) target: f32,
+    pub(crate) status: GoalStatus,
+}
+
+// (name, unit, kind, kcal per gram if the nutrient can be set as % of energy)
+pub(crate) const GOAL_NUTRIENTS: [(&str, &str, GoalKind, Option<f32>); 8] = [
+    ("energy", "kcal", GoalKind::Target, None),
+    ("carbohydrates", "g", GoalKind::Target, Some(nutrients::KCAL_PER_G_CARBOHYDRATE)),
+    ("proteins", "g", GoalKind::Minimum, Some(nutrients::KCAL_PER_G_PROTEIN)),
+    ("fats", "g", GoalKind::Target, Some(nutrients::KCAL_PER_G_FAT)),
+    ("fiber", "g", GoalKind::Minimum, None),
+    ("sugar", "g", GoalKind::Limit, Some(nutrients::KCAL_PER_G_CARBOHYDRATE)),
+    ("salt", "g", GoalKind::Limit, None),
+    ("sodium", "g", GoalKind::Limit, None),
+];
+
+pub(crate) fn goal_label(name: &str) -> String {
+    let mut label = name.to_string();
+    if let Some(first) = label.get_mut(..1) {
+        first.make_ascii_uppercase();
+    }
+    label
+}
+
+impl GoalTarget {
+    /// The target in grams, percentages need an energy goal and a nutrient that provides energy
+    pub(crate) fn grams(&self, energy_goal: Option<f32>, kcal_per_gram: Option<f32>) -> Option<f32> {
+        match self {
+            GoalTarget::Absolute(grams) => Some(*grams),
+            GoalTarget::PercentOfEnergy(percent) => {
+                Some(energy_goal? * percent * 0.01 / kcal_per_gram?)
+            }
+        }
+    }
+}
+
+impl GoalKind {
+    pub(crate) fn status(&self, consumed: f32, target: f32) -> GoalStatus {
+        match self {
+            GoalKind::Target if consumed < target * (1.0 - ON_TRACK_TOLERANCE) => GoalStatus::Under,
+            GoalKind::Target if consumed > target * (1.0 + ON_TRACK_TOLERANCE) => GoalStatus::Over,
+            GoalKind::Minimum if consumed < target => GoalStatus::Under,
+            GoalKind::Limit if consumed > target => GoalStatus::Over,
+            _ => GoalStatus::OnTrack,
+        }
+    }
+}
+
+impl NutritionGoals {
+    pub(crate) fn target(&self, name: &str) -> Option<&GoalTarget> {
+        match name {
+            "carbohydrates" => self.carbohydrates.as_ref(),
+            "proteins" => self.proteins.as_ref(),
+            "fats" => self.fats.as_ref(),
+            "fiber" => self.fiber.as_ref(),
+            "sugar" => self.sugar.as_ref(),
+            "salt" => self.salt.as_ref(),
+            "sodium" => self.sodium.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn set_target(&mut self, name: &str, target: Option<GoalTarget>) {
+        match name {
+            "energy" => {
+                self.energy = match target {
+                    Some(GoalTarget::Absolute(kcal)) => Some(kcal),
+                    _ => None,
+                }
+            }
+            "carbohydrates" => self.carbohydrates = target,
+            "proteins" => self.proteins = target,
+            "fats" => self.fats = target,
+            "fiber" => self.fiber = target,
+            "sugar" => self.sugar = target,
+            "salt" => self.salt = target,
+            "sodium" => self.sodium = target,
+            _ => {}
+        }
+    }
+
+    pub(crate) fn water_goal_ml(&self) -> f32 {
+        self.water_ml.unwrap_or(DEFAULT_WATER_GOAL_ML)
+    }
+
+    /// Hydration progress, hydration has no upper limit so it is at most on track
+    pub(crate) fn hydration_progress(&self, consumed_ml: f32) -> GoalProgress {
+        let target = self.water_goal_ml();
+        GoalProgress {
+            name: "water".to_string(),
+            unit: "ml".to_string(),
+            consumed: consumed_ml,
+            target,
+            status: GoalKind::Minimum.status(consumed_ml, target),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self == &NutritionGoals::default()
+    }
+
+    /// Daily target of a nutrient in its display unit
+    pub(crate) fn resolved(&self, name: &str) -> Option<f32> {
+        if name == "energy" {
+            return self.energy;
+        }
+        let (_, _, _, kcal_per_gram) = GOAL_NUTRIENTS.iter().find(|(n, ..)| *n == name)?;
+        self.target(name)?.grams(self.energy, *kcal_per_gram)
+    }
+
+    /// Progress of the consumed values against every goal that is set
+    pub(crate) fn progress(&self, values: NutrientValues) -> Vec<GoalProgress> {
+        GOAL_NUTRIENTS
+            .iter()
+            .filter_map(|(name, unit, kind, _)| {
+                let target = self.resolved(name)?;
+                let consumed = values.get(name)?;
+                Some(GoalProgress {
+                    name: name.to_string(),
+                    unit: unit.to_string(),
+                    consumed,
+                    target,
+                    status: kind.status(consumed, target),
+                })
+            })
+            .collect()
+    }
+}
+
+impl NutrientValues {
+    pub(crate) fn get(&self, name: &str) -> Option<f32> {
+        match name {
+            "energy" => Some(self.energy),
+            "carbohydrates" => Some(self.carbohydrates),
+            "proteins" => Some(self.proteins),
+            "fats" => Some(self.fats),
+            "fiber" => Some(self.fiber),
+            "sugar" => Some(self.sugar),
+            "salt" => Some(self.salt),
+            "sodium" => Some(self.sodium),
+            _ => None,
+        }
+    }
+}
+
+impl GoalProgress {
+    pub fn label(&self) -> String {
+        goal_label(&self.name)
+    }
+
+    // width of the progress bar, capped at 100
+    pub fn percent(&self) -> u32 {
+        if self.target <= 0.0 {
+            return 100;
+        }
+        ((self.consumed / self.target * 100.0).round() as u32).min(100)
+    }
+
+    pub fn bar_class(&self) -> &'static str {
+        match self.status {
+            GoalStatus::OnTrack => "bg-success",
+            GoalStatus::Under => "bg-info",
+            GoalStatus::Over => "bg-danger",
+        }
+    }
+
+    pub fn indicator(&self) -> &'static str {
+        match self.status {
+            GoalStatus::OnTrack => "✓",
+            GoalStatus::Under => "▼ under",
+            GoalStatus::Over => "▲ over",
+        }
+    }
+
+    fn rounded(&self, value: f32) -> String {
+        if self.unit == "kcal" || self.unit == "ml" {
+            format!("{:.0}", value)
+        } else {
+            format!("{:.1}", value)
+        }
+    }
+
+    pub fn consumed_rounded(&self) -> String {
+        self.rounded(self.consumed)
+    }
+
+    pub fn target_rounded(&self) -> String {
+        self.rounded(self.target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_of_energy() {
+        let goals = NutritionGoals {
+            energy: Some(2000.0),
+            fats: Some(GoalTarget::PercentOfEnergy(30.0)),
+            proteins: Some(GoalTarget::Absolute(120.0)),
+            salt: Some(GoalTarget::PercentOfEnergy(5.0)),
+            ..NutritionGoals::default()
+        };
+        assert!((goals.resolved("fats").unwrap() - 2000.0 * 0.3 / 9.0).abs() < 0.001);
+        assert_eq!(goals.resolved("proteins"), Some(120.0));
+        // salt provides no energy, so a share of energy can not be resolved
+        assert_eq!(goals.resolved("salt"), None);
+
+        let goals = NutritionGoals {
+            fats: Some(GoalTarget::PercentOfEnergy(30.0)),
+            ..NutritionGoals::default()
+        };
+        assert_eq!(goals.resolved("fats"), None);
+    }
+
+    #[test]
+    fn test_progress_status() {
+        let goals = NutritionGoals {
+            energy: Some(2000.0),
+            proteins: Some(GoalTarget::Absolute(100.0)),
+            salt: Some(GoalTarget::Absolute(6.0)),
+            ..NutritionGoals::default()
+        };
+        let values = NutrientValues {
+            energy: 2500.0,
+            proteins: 80.0,
+            salt: 5.0,
+            ..NutrientValues::default()
+        };
+        let progress = goals.progress(values);
+        assert_eq!(progress.len(), 3);
+        assert_eq!(progress[0].status, GoalStatus::Over);
+        assert_eq!(progress[0].percent(), 100);
+        assert_eq!(progress[1].status, GoalStatus::Under);
+        assert_eq!(progress[1].percent(), 80);
+        assert_eq!(progress[2].status, GoalStatus::OnTrack);
+    }
+}
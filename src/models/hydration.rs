@@ -0,0 +1,69 @@
+use crate::db::connector::default_fetch_from_uuid;
+use crate::models::models::RedisORM;
+use chrono::{NaiveDate, NaiveTime};
+use redis::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// used when the user has not set a hydration goal
+pub(crate) const DEFAULT_WATER_GOAL_ML: f32 = 2000.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct HydrationEntry {
+    pub(crate) id: Uuid,
+    pub(crate) ml: f32,
+    pub(crate) time: NaiveTime,
+}
+
+// Water drunk by a user on one day, stored as hydration:<username>:<date>
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct HydrationLog {
+    pub(crate) username: String,
+    pub(crate) date: NaiveDate,
+    pub(crate) entries: Vec<HydrationEntry>,
+}
+
+impl HydrationLog {
+    /// The log of the day or an empty one if nothing was logged yet
+    pub(crate) fn for_day(con: &mut Connection, username: &str, date: NaiveDate) -> HydrationLog {
+        default_fetch_from_uuid(con, &Self::redis_type_name(), format!("{}:{}", username, date))
+            .unwrap_or(HydrationLog {
+                username: username.to_string(),
+                date,
+                entries: vec![],
+            })
+    }
+
+    pub(crate) fn add(&mut self, ml: f32, time: NaiveTime) {
+        self.entries.push(HydrationEntry {
+            id: Uuid::new_v4(),
+            ml,
+            time,
+        });
+    }
+
+    pub(crate) fn total_ml(&self) -> f32 {
+        self.entries.iter().map(|e| e.ml).sum()
+    }
+}
+
+impl RedisORM for HydrationLog {
+    fn example() -> Self
+    where
+        Self: Sized,
+    {
+        HydrationLog {
+            username: "12345".to_string(),
+            date: chrono::Utc::now().date_naive(),
+            entries: vec![],
+        }
+    }
+
+    fn redis_type_name() -> String {
+        "hydration".to_string()
+    }
+
+    fn redis_id(&self) -> String {
+        format!("{}:{}", self.username, self.date)
+    }
+}
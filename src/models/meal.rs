@@ -1,13 +1,17 @@
 use crate::db;
 use crate::db::connector;
 use crate::db::connector::{default_fetch_from_uuid, default_save};
+use crate::models::food::{FoodItem, FoodKey, StoredFood};
 use crate::models::models::RedisORM;
+use crate::nutrition::nutriscore::{day_score, NutriScore};
+use crate::nutrition::quality::{score_day, DietQuality};
 use crate::usda::search::{Food, NutrientValues};
 use redis::{Connection, RedisResult};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::fmt::{Debug, Display, Error};
 use std::ops::{Add, Mul};
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate};
+use std::collections::BTreeMap;
 use tower_sessions::cookie::time::error::Format;
 use uuid::Uuid;
 
@@ -28,6 +32,9 @@ pub(crate) struct DailyMealCombo {
     pub(crate) dinner: Option<Meal>,
     pub(crate) snack: Option<Meal>,
     pub(crate) username: String,
+    // water logged in the hydration log, beverages in the meals are added by `hydration_ml`
+    #[serde(default)]
+    pub(crate) logged_water_ml: f32,
 }
 
 impl Meal {
@@ -44,6 +51,70 @@ impl DailyMealCombo {
         self.date.to_string()
     }
 
+    pub fn meals(&self) -> Vec<&Meal> {
+        [&self.breakfast, &self.lunch, &self.dinner, &self.snack]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    pub(crate) fn get_macros(&self) -> NutrientValues {
+        self.meals()
+            .iter()
+            .fold(NutrientValues::default(), |sum, meal| sum + meal.get_macros())
+    }
+
+    pub(crate) fn diet_quality(&self) -> Option<DietQuality> {
+        score_day(&self.meals())
+    }
+
+    pub(crate) fn nutri_score(&self) -> Option<NutriScore> {
+        day_score(&self.meals())
+    }
+
+    pub fn hydration_ml(&self) -> f32 {
+        self.logged_water_ml + self.meals().iter().map(|m| m.hydration_ml()).sum::<f32>()
+    }
+
+    /// Groups meals by their date, newest day first
+    pub fn group_by_day(meals: &[Meal]) -> Vec<(NaiveDate, DailyMealCombo)> {
+        let mut days: BTreeMap<NaiveDate, Vec<Meal>> = BTreeMap::new();
+        for meal in meals.iter() {
+            days.entry(meal.date).or_default().push(meal.clone());
+        }
+        days.into_iter()
+            .rev()
+            .map(|(date, meals)| (date, DailyMealCombo::from_meals_vec(meals)))
+            .collect()
+    }
+
+    fn slot(&self, meal_type: &MealType) -> Option<&Meal> {
+        match meal_type {
+            MealType::Breakfast => self.breakfast.as_ref(),
+            MealType::Lunch => self.lunch.as_ref(),
+            MealType::Dinner => self.dinner.as_ref(),
+            MealType::Snack => self.snack.as_ref(),
+        }
+    }
+
+    /// Required slots without any food logged, an empty meal does not count
+    pub fn missing_slots(&self) -> Vec<MealType> {
+        REQUIRED_SLOTS
+            .iter()
+            .filter(|t| self.slot(t).is_none_or(|m| m.contents.is_empty()))
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.missing_slots().is_empty()
+    }
+
+    /// True if any food was logged this day
+    pub fn has_logged(&self) -> bool {
+        self.meals().iter().any(|m| !m.contents.is_empty())
+    }
+
     pub fn from_meals_vec(meals: Vec<Meal>) -> DailyMealCombo {
         let mut breakfast = None;
         let mut lunch = None;
@@ -66,6 +137,7 @@ impl DailyMealCombo {
             dinner,
             snack,
             username,
+            logged_water_ml: 0.0,
         }
     }
 
@@ -81,7 +153,10 @@ impl Display for Meal {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+// Slots that have to be logged for a day to count as complete, snacks are optional
+pub(crate) const REQUIRED_SLOTS: [MealType; 3] = [MealType::Breakfast, MealType::Lunch, MealType::Dinner];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) enum MealType{
     Breakfast,
     Lunch,
@@ -111,6 +186,8 @@ impl Add for NutrientValues {
             salt: self.salt + rhs.salt,
             sodium: self.sodium + rhs.sodium,
             sugar: self.sugar + rhs.sugar,
+            saturated_fat: self.saturated_fat + rhs.saturated_fat,
+            estimated: self.estimated || rhs.estimated,
         }
     }
 }
@@ -120,31 +197,87 @@ impl Meal {
         let mut nutriments = NutrientValues::default();
         let prods = self.contents.clone();
         for content in prods.into_iter() {
-            let mut i = content.product.get_numerical_macros() * content.quantity * 0.01;
+            let mut i = content.nutrients_per_100g() * content.quantity * 0.01;
             nutriments = nutriments + i;
         }
         nutriments
     }
 
+    pub(crate) fn hydration_ml(&self) -> f32 {
+        self.contents.iter().map(|c| c.hydration_ml()).sum()
+    }
+
     pub(crate) fn get_kcal(&self) -> Result<f32, Error> {
         let mut kcal = 0.0;
         let prods = self.contents.clone();
         for product in prods.into_iter() {
-            let prod = product.product.clone().get_numerical_macros();
-            kcal += prod.energy;
+            kcal += product.kcal();
         }
         Ok(kcal)
     }
 
     fn add_meal_to_user(&self, con: &mut Connection, user_id: &str) {
+        Self::index_dates(con, &self.username);
         db::connector::add_to_set(
             con,
             "meals:".to_string() + &*self.username,
             self.id.to_string(),
         )
+        .unwrap();
+        db::connector::add_to_sorted_set(
+            con,
+            Self::date_index_key(&self.username),
+            Self::date_score(self.date),
+            self.id.to_string(),
+        )
         .unwrap()
     }
 
+    // sorted set of the meal ids of a user scored by their date
+    fn date_index_key(user_id: &str) -> String {
+        "meal_dates:".to_string() + user_id
+    }
+
+    fn date_score(date: NaiveDate) -> i64 {
+        date.num_days_from_ce() as i64
+    }
+
+    // meals saved before the date index existed are indexed on first use
+    fn index_dates(con: &mut Connection, user_id: &str) {
+        if db::connector::key_exists(con, Self::date_index_key(user_id)) {
+            return;
+        }
+        for meal in Self::get_meals_for_user(con, user_id) {
+            db::connector::add_to_sorted_set(
+                con,
+                Self::date_index_key(user_id),
+                Self::date_score(meal.date),
+                meal.id.to_string(),
+            )
+            .unwrap();
+        }
+    }
+
+    /// Meals of a user between start and end (both inclusive) using the date index
+    pub fn get_meals_for_user_in_range(
+        con: &mut Connection,
+        user_id: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Vec<Meal> {
+        Self::index_dates(con, user_id);
+        let meal_ids = db::connector::get_sorted_set_range(
+            con,
+            Self::date_index_key(user_id),
+            Self::date_score(start),
+            Self::date_score(end),
+        );
+        meal_ids
+            .iter()
+            .filter_map(|id| Meal::fetch_from_uuid(con, id))
+            .collect()
+    }
+
     pub fn get_meals_for_user(con: &mut Connection, user_id: &str) -> Vec<Meal> {
         let mut meals = vec![];
         let meal_ids = db::connector::get_set(con, "meals:".to_string() + user_id);
@@ -160,7 +293,12 @@ impl Meal {
             "meals:".to_string() + &*self.username,
             self.id.to_string(),
         )
-        .unwrap()
+        .unwrap();
+        redis::cmd("ZREM")
+            .arg(Self::date_index_key(&self.username))
+            .arg(self.id.to_string())
+            .query::<()>(con)
+            .unwrap()
     }
 }
 impl RedisORM for Meal {
@@ -201,17 +339,84 @@ impl RedisORM for Meal {
 // tokio tests
 #[cfg(test)]
 mod tests {
+    use super::MealContent;
+    use crate::models::food::StoredFood;
     use crate::models::models::RedisORM;
     use crate::models::user::User;
+    use crate::usda::search::Food;
     use crate::{db, models};
 
     #[tokio::test]
     async fn test_meal() {}
+
+    #[test]
+    fn test_beverage_hydration() {
+        let mut juice = Food::example();
+        juice.food_category = Some("Fruit Juices".to_string());
+        let content = MealContent::new(StoredFood::Usda(juice), 330.0);
+        assert_eq!(content.hydration_ml(), 330.0);
+
+        let mut bread = Food::example();
+        bread.food_category = Some("Baked Products".to_string());
+        let content = MealContent::new(StoredFood::Usda(bread), 100.0);
+        assert_eq!(content.hydration_ml(), 0.0);
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MealContent {
-    pub(crate) product: Food,
+    /// The food as it was when it was logged, so later updates of its source keep the meal
+    #[serde(deserialize_with = "logged_food")]
+    pub(crate) product: StoredFood,
     pub(crate) quantity: f32,
     pub(crate) id: Uuid,
 }
+
+// contents logged before foods had a source hold the bare FDC record
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LoggedFood {
+    Stored(StoredFood),
+    Fdc(Food),
+}
+
+fn logged_food<'de, D: Deserializer<'de>>(deserializer: D) -> Result<StoredFood, D::Error> {
+    Ok(match LoggedFood::deserialize(deserializer)? {
+        LoggedFood::Stored(food) => food,
+        LoggedFood::Fdc(food) => StoredFood::Usda(food),
+    })
+}
+
+impl FoodItem for MealContent {
+    fn key(&self) -> FoodKey {
+        self.product.key()
+    }
+
+    fn display_name(&self) -> String {
+        self.product.display_name()
+    }
+
+    fn nutrients_per_100g(&self) -> NutrientValues {
+        self.product.nutrients_per_100g()
+    }
+}
+
+impl MealContent {
+    pub(crate) fn new(product: StoredFood, quantity: f32) -> MealContent {
+        MealContent {
+            product,
+            quantity,
+            id: Uuid::new_v4(),
+        }
+    }
+
+    /// Energy of the logged quantity in kcal
+    pub fn kcal(&self) -> f32 {
+        self.nutrients_per_100g().energy * self.quantity * 0.01
+    }
+
+    /// Water the content provides if it is a beverage
+    pub(crate) fn hydration_ml(&self) -> f32 {
+        self.product.hydration_per_100g() * self.quantity * 0.01
+    }
+}
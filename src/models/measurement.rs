@@ -0,0 +1,79 @@
+use crate::db;
+use crate::db::connector::default_save;
+use crate::models::models::RedisORM;
+use chrono::NaiveDate;
+use redis::{Connection, RedisResult};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// One body measurement per user and day
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct BodyMeasurement {
+    pub(crate) id: Uuid,
+    pub(crate) username: String,
+    pub(crate) date: NaiveDate,
+    pub(crate) weight_kg: f32,
+    pub(crate) waist_cm: Option<f32>,
+    pub(crate) body_fat_percent: Option<f32>,
+}
+
+impl BodyMeasurement {
+    fn user_set_key(username: &str) -> String {
+        "measurements:".to_string() + username
+    }
+
+    /// All measurements of a user, oldest first
+    pub(crate) fn get_for_user(con: &mut Connection, username: &str) -> Vec<BodyMeasurement> {
+        let ids = db::connector::get_set(con, Self::user_set_key(username));
+        let mut measurements: Vec<BodyMeasurement> = ids
+            .iter()
+            .filter_map(|id| BodyMeasurement::fetch_from_uuid(con, id))
+            .collect();
+        measurements.sort_by_key(|m| m.date);
+        measurements
+    }
+
+    pub(crate) fn delete(&self, con: &mut Connection) -> RedisResult<()> {
+        db::connector::remove_from_set(con, Self::user_set_key(&self.username), self.id.to_string())?;
+        redis::cmd("DEL")
+            .arg(format!("{}:{}", Self::redis_type_name(), self.id))
+            .query(con)
+    }
+
+    pub fn waist_text(&self) -> String {
+        self.waist_cm.map(|v| format!("{:.1} cm", v)).unwrap_or_default()
+    }
+
+    pub fn body_fat_text(&self) -> String {
+        self.body_fat_percent.map(|v| format!("{:.1} %", v)).unwrap_or_default()
+    }
+}
+
+impl RedisORM for BodyMeasurement {
+    fn save(&self, con: &mut Connection) -> RedisResult<()> {
+        db::connector::add_to_set(con, Self::user_set_key(&self.username), self.id.to_string())?;
+        default_save(con, &Self::redis_type_name(), &self.id.to_string(), self)
+    }
+
+    fn example() -> Self
+    where
+        Self: Sized,
+    {
+        BodyMeasurement {
+            id: Uuid::new_v4(),
+            username: "12345".to_string(),
+            date: chrono::Utc::now().date_naive(),
+            weight_kg: 70.0,
+            waist_cm: None,
+            body_fat_percent: None,
+        }
+    }
+
+    fn redis_type_name() -> String {
+        "measurement".to_string()
+    }
+
+    fn redis_id(&self) -> String {
+        self.id.to_string()
+    }
+}
@@ -35,6 +35,16 @@ pub trait RedisORM {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
 pub(crate) struct NutritionistSearchQuery {
     pub(crate) query: String,
+    // filters, empty means any
+    pub(crate) data_type: String,
+    pub(crate) brand: String,
+    pub(crate) category: String,
+    // a goal nutrient with its bounds per 100 g
+    pub(crate) nutrient: String,
+    pub(crate) min: String,
+    pub(crate) max: String,
+    pub(crate) sort: String,
 }
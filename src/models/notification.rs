@@ -0,0 +1,71 @@
+use crate::db;
+use crate::db::connector::default_save;
+use crate::models::models::RedisORM;
+use chrono::NaiveDateTime;
+use redis::{Connection, RedisResult};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// In-app message shown on the diary until the user dismisses it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Notification {
+    pub(crate) id: Uuid,
+    pub(crate) username: String,
+    pub(crate) message: String,
+    pub(crate) created: NaiveDateTime,
+    pub(crate) read: bool,
+}
+
+impl Notification {
+    pub(crate) fn new(username: &str, message: &str) -> Notification {
+        Notification {
+            id: Uuid::new_v4(),
+            username: username.to_string(),
+            message: message.to_string(),
+            created: chrono::Utc::now().naive_utc(),
+            read: false,
+        }
+    }
+
+    fn user_set_key(username: &str) -> String {
+        "notifications:".to_string() + username
+    }
+
+    /// Unread notifications of a user, newest first
+    pub(crate) fn unread_for_user(con: &mut Connection, username: &str) -> Vec<Notification> {
+        let ids = db::connector::get_set(con, Self::user_set_key(username));
+        let mut notifications: Vec<Notification> = ids
+            .iter()
+            .filter_map(|id| Notification::fetch_from_uuid(con, id))
+            .filter(|n| !n.read)
+            .collect();
+        notifications.sort_by_key(|n| std::cmp::Reverse(n.created));
+        notifications
+    }
+
+    pub fn created_text(&self) -> String {
+        self.created.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+impl RedisORM for Notification {
+    fn save(&self, con: &mut Connection) -> RedisResult<()> {
+        db::connector::add_to_set(con, Self::user_set_key(&self.username), self.id.to_string())?;
+        default_save(con, &Self::redis_type_name(), &self.id.to_string(), self)
+    }
+
+    fn example() -> Self
+    where
+        Self: Sized,
+    {
+        Notification::new("12345", "Time to log your meals")
+    }
+
+    fn redis_type_name() -> String {
+        "notification".to_string()
+    }
+
+    fn redis_id(&self) -> String {
+        self.id.to_string()
+    }
+}
@@ -0,0 +1,92 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub(crate) enum Sex {
+    Female,
+    Male,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub(crate) enum ActivityLevel {
+    #[default]
+    Sedentary,
+    Light,
+    Moderate,
+    Active,
+    VeryActive,
+}
+
+// Body metrics used to calculate energy requirements, everything is optional until entered
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub(crate) struct BodyProfile {
+    pub(crate) sex: Option<Sex>,
+    pub(crate) birth_date: Option<NaiveDate>,
+    pub(crate) height_cm: Option<f32>,
+    pub(crate) weight_kg: Option<f32>,
+    pub(crate) body_fat_percent: Option<f32>,
+    #[serde(default)]
+    pub(crate) activity_level: ActivityLevel,
+}
+
+impl BodyProfile {
+    pub(crate) fn age(&self, today: NaiveDate) -> Option<u32> {
+        today.years_since(self.birth_date?)
+    }
+
+    pub(crate) fn lean_body_mass_kg(&self) -> Option<f32> {
+        Some(self.weight_kg? * (1.0 - self.body_fat_percent? * 0.01))
+    }
+}
+
+impl Sex {
+    pub(crate) fn parse(s: &str) -> Option<Sex> {
+        match s {
+            "Female" => Some(Sex::Female),
+            "Male" => Some(Sex::Male),
+            _ => None,
+        }
+    }
+}
+
+impl ActivityLevel {
+    pub(crate) const ALL: [ActivityLevel; 5] = [
+        ActivityLevel::Sedentary,
+        ActivityLevel::Light,
+        ActivityLevel::Moderate,
+        ActivityLevel::Active,
+        ActivityLevel::VeryActive,
+    ];
+
+    // physical activity level multiplier applied to the BMR
+    pub(crate) fn factor(&self) -> f32 {
+        match self {
+            ActivityLevel::Sedentary => 1.2,
+            ActivityLevel::Light => 1.375,
+            ActivityLevel::Moderate => 1.55,
+            ActivityLevel::Active => 1.725,
+            ActivityLevel::VeryActive => 1.9,
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<ActivityLevel> {
+        ActivityLevel::ALL.into_iter().find(|level| level.key() == s)
+    }
+
+    pub fn key(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl Display for ActivityLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActivityLevel::Sedentary => write!(f, "Sedentary (little or no exercise)"),
+            ActivityLevel::Light => write!(f, "Light (exercise 1-3 days/week)"),
+            ActivityLevel::Moderate => write!(f, "Moderate (exercise 3-5 days/week)"),
+            ActivityLevel::Active => write!(f, "Active (exercise 6-7 days/week)"),
+            ActivityLevel::VeryActive => write!(f, "Very active (physical job or training twice a day)"),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use crate::models::food::{FoodItem, FoodKey, FoodSource};
+use crate::models::meal::MealContent;
+use crate::models::models::RedisORM;
+use crate::usda::search::NutrientValues;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// A dish made of other foods, stored as recipe:<uuid>. Its nutrients are those of the whole
+// dish scaled to 100 g, so it is added to meals like any other food.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Recipe {
+    pub(crate) id: Uuid,
+    pub(crate) username: String,
+    pub(crate) name: String,
+    pub(crate) contents: Vec<MealContent>,
+}
+
+impl Recipe {
+    /// Weight of the whole dish in g
+    pub(crate) fn total_grams(&self) -> f32 {
+        self.contents.iter().map(|c| c.quantity).sum()
+    }
+}
+
+impl FoodItem for Recipe {
+    fn key(&self) -> FoodKey {
+        FoodKey {
+            source: FoodSource::Recipe,
+            id: self.id.to_string(),
+        }
+    }
+
+    fn display_name(&self) -> String {
+        format!("Recipe: {}", self.name)
+    }
+
+    fn nutrients_per_100g(&self) -> NutrientValues {
+        let grams = self.total_grams();
+        if grams <= 0.0 {
+            return NutrientValues::default();
+        }
+        let total = self
+            .contents
+            .iter()
+            .fold(NutrientValues::default(), |sum, c| {
+                sum + c.nutrients_per_100g() * c.quantity * 0.01
+            });
+        total * (100.0 / grams)
+    }
+}
+
+impl RedisORM for Recipe {
+    fn example() -> Self
+    where
+        Self: Sized,
+    {
+        Recipe {
+            id: Uuid::new_v4(),
+            username: "12345".to_string(),
+            name: "".to_string(),
+            contents: vec![],
+        }
+    }
+
+    fn redis_type_name() -> String {
+        "recipe".to_string()
+    }
+
+    fn redis_id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::food::StoredFood;
+    use crate::usda::nutrients;
+    use crate::usda::search::{Food, Nutrient};
+
+    #[test]
+    fn test_nutrients_per_100g() {
+        let oats = Food::new(
+            173904,
+            "Oats",
+            vec![
+                Nutrient::new(nutrients::ENERGY, "Energy", 380.0, "KCAL"),
+                Nutrient::new(nutrients::PROTEIN, "Protein", 13.0, "G"),
+            ],
+        );
+        let milk = Food::new(
+            171265,
+            "Milk, whole",
+            vec![
+                Nutrient::new(nutrients::ENERGY, "Energy", 60.0, "KCAL"),
+                Nutrient::new(nutrients::PROTEIN, "Protein", 3.0, "G"),
+            ],
+        );
+        let porridge = Recipe {
+            name: "Porridge".to_string(),
+            contents: vec![
+                MealContent::new(StoredFood::Usda(oats), 50.0),
+                MealContent::new(StoredFood::Usda(milk), 150.0),
+            ],
+            ..Recipe::example()
+        };
+        // 190 + 90 kcal and 6.5 + 4.5 g protein in 200 g
+        let values = porridge.nutrients_per_100g();
+        assert_eq!(values.energy, 140.0);
+        assert_eq!(values.proteins, 5.5);
+        assert_eq!(porridge.key().source, FoodSource::Recipe);
+        assert_eq!(Recipe::example().nutrients_per_100g().energy, 0.0);
+    }
+}
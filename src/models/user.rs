@@ -1,8 +1,11 @@
 use std::cmp::PartialEq;
 use axum_oidc::{EmptyAdditionalClaims, OidcClaims};
 use crate::db;
+use crate::models::diet::DietaryProfile;
+use crate::models::goals::NutritionGoals;
 use crate::models::meal::Meal;
 use crate::models::models::RedisORM;
+use crate::models::profile::BodyProfile;
 use redis::{Connection, RedisError, RedisResult};
 use serde::{Deserialize, Serialize};
 
@@ -12,6 +15,12 @@ pub(crate) struct User {
     pub(crate) name: String,
     pub(crate) email: String,
     pub(crate) user_type: UserType,
+    #[serde(default)]
+    pub(crate) goals: NutritionGoals,
+    #[serde(default)]
+    pub(crate) profile: BodyProfile,
+    #[serde(default)]
+    pub(crate) diet: DietaryProfile,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -31,6 +40,9 @@ impl RedisORM for User {
             name: "TEST".to_string(),
             email: "test@test.de".to_string(),
             user_type: UserType::User,
+            goals: NutritionGoals::default(),
+            profile: BodyProfile::default(),
+            diet: DietaryProfile::default(),
         }
     }
 
@@ -83,10 +95,19 @@ impl User {
             name: name.to_string(),
             email: email.to_string(),
             user_type,
+            goals: NutritionGoals::default(),
+            profile: BodyProfile::default(),
+            diet: DietaryProfile::default(),
         };
         user.save(con)?;
         return Ok(user);
     }
+    pub(crate) fn is_nutritionist_of(&self, username: &str) -> bool {
+        match &self.user_type {
+            UserType::Nutritionist(users) => users.iter().any(|u| u == username),
+            _ => false,
+        }
+    }
     fn create_default_user(con: &mut Connection) -> Result<User, RedisError> {
         let user = User::example();
         user.save(con)?;
@@ -0,0 +1,22 @@
+pub mod in_app;
+pub mod reminder;
+pub mod smtp;
+
+use crate::models::user::User;
+use std::error::Error;
+
+/// A channel notifications are delivered through, implementations are blocking
+pub(crate) trait Notifier: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn notify(&self, user: &User, subject: &str, message: &str) -> Result<(), Box<dyn Error>>;
+}
+
+/// In-app notifications are always delivered, emails only if SMTP_HOST is set
+pub(crate) fn notifiers_from_env() -> Vec<Box<dyn Notifier>> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = vec![Box::new(in_app::InAppNotifier)];
+    if let Some(smtp) = smtp::SmtpNotifier::from_env() {
+        notifiers.push(Box::new(smtp));
+    }
+    notifiers
+}
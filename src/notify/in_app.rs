@@ -0,0 +1,20 @@
+use crate::models::models::RedisORM;
+use crate::models::notification::Notification;
+use crate::models::user::User;
+use crate::notify::Notifier;
+use std::error::Error;
+
+// Stores the notification in redis, it is shown on the diary
+pub(crate) struct InAppNotifier;
+
+impl Notifier for InAppNotifier {
+    fn name(&self) -> &'static str {
+        "in-app"
+    }
+
+    fn notify(&self, user: &User, _subject: &str, message: &str) -> Result<(), Box<dyn Error>> {
+        let mut con = crate::db::connector::get_connection()?;
+        Notification::new(&user.id, message).save(&mut con)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+// Background task nudging users who have not logged anything today
+use crate::models::meal::Meal;
+use crate::models::models::RedisORM;
+use crate::models::user::{User, UserType};
+use crate::notify::Notifier;
+use crate::nutrition::logging::{logging_stats, LoggingStats};
+use chrono::{NaiveDateTime, Timelike, Utc};
+use redis::{Connection, RedisResult};
+use std::sync::Arc;
+use std::time::Duration;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+// a reminded user is remembered a bit longer than a day
+const REMINDED_EXPIRY_SECONDS: i64 = 36 * 60 * 60;
+const DEFAULT_REMINDER_HOUR: u32 = 18;
+
+/// Hour of the day (UTC) from which reminders are sent, REMINDER_HOUR in the environment
+fn reminder_hour() -> u32 {
+    std::env::var("REMINDER_HOUR")
+        .ok()
+        .and_then(|h| h.parse().ok())
+        .filter(|h| *h < 24)
+        .unwrap_or(DEFAULT_REMINDER_HOUR)
+}
+
+pub(crate) fn reminder_message(stats: &LoggingStats) -> String {
+    if stats.current_streak > 0 {
+        format!(
+            "You haven't logged any food today. Log your meals to keep your {} day streak going!",
+            stats.current_streak
+        )
+    } else {
+        "You haven't logged any food today. Take a minute to log your meals.".to_string()
+    }
+}
+
+// marks the user as reminded today, false if that already happened
+fn mark_reminded(con: &mut Connection, username: &str, now: NaiveDateTime) -> RedisResult<bool> {
+    let key = format!("reminded:{}:{}", username, now.date());
+    let set: Option<String> = redis::cmd("SET")
+        .arg(key)
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(REMINDED_EXPIRY_SECONDS)
+        .query(con)?;
+    Ok(set.is_some())
+}
+
+/// Sends a reminder through every notifier to each user without food logged today,
+/// at most once per day and not before the reminder hour
+pub(crate) fn send_reminders(
+    con: &mut Connection,
+    notifiers: &[Box<dyn Notifier>],
+    now: NaiveDateTime,
+) {
+    if now.hour() < reminder_hour() {
+        return;
+    }
+    for user in User::all(con) {
+        if !matches!(user.user_type, UserType::User) {
+            continue;
+        }
+        let meals = Meal::get_meals_for_user(con, &user.id);
+        let stats = logging_stats(&meals, now.date());
+        if stats.logged_today || !mark_reminded(con, &user.id, now).unwrap_or(false) {
+            continue;
+        }
+        let message = reminder_message(&stats);
+        for notifier in notifiers.iter() {
+            if let Err(e) = notifier.notify(&user, "Time to log your meals", &message) {
+                println!("{} reminder for {} failed: {}", notifier.name(), user.id, e);
+            }
+        }
+    }
+}
+
+/// Checks for due reminders in the background every few minutes
+pub(crate) fn spawn_reminders(notifiers: Vec<Box<dyn Notifier>>) {
+    let notifiers = Arc::new(notifiers);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let notifiers = notifiers.clone();
+            // redis and the notifiers are blocking
+            let _ =
+                tokio::task::spawn_blocking(move || match crate::db::connector::get_connection() {
+                    Ok(mut con) => send_reminders(&mut con, &notifiers, Utc::now().naive_utc()),
+                    Err(e) => println!("reminders skipped, no redis connection: {}", e),
+                })
+                .await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_reminder_message() {
+        let stats = logging_stats(&[], NaiveDate::from_ymd_opt(2024, 5, 10).unwrap());
+        assert!(!reminder_message(&stats).contains("streak"));
+        let stats = LoggingStats {
+            current_streak: 4,
+            ..stats
+        };
+        assert!(reminder_message(&stats).contains("4 day streak"));
+    }
+}
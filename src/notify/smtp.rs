@@ -0,0 +1,184 @@
+// Minimal SMTP client without authentication or TLS, meant for a local relay like mailpit
+use crate::models::user::User;
+use crate::notify::Notifier;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub(crate) struct SmtpNotifier {
+    host: String,
+    port: u16,
+    from: String,
+}
+
+impl SmtpNotifier {
+    pub(crate) fn new(host: &str, port: u16, from: &str) -> SmtpNotifier {
+        SmtpNotifier {
+            host: host.to_string(),
+            port,
+            from: from.to_string(),
+        }
+    }
+
+    /// Configured by SMTP_HOST, SMTP_PORT (default 1025) and SMTP_FROM
+    pub(crate) fn from_env() -> Option<SmtpNotifier> {
+        let host = std::env::var("SMTP_HOST").ok().filter(|h| !h.is_empty())?;
+        let port = std::env::var("SMTP_PORT")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(1025);
+        let from = std::env::var("SMTP_FROM").unwrap_or("nutritionist@localhost".to_string());
+        Some(SmtpNotifier::new(&host, port, &from))
+    }
+
+    pub(crate) fn send_mail(
+        &self,
+        to: &str,
+        subject: &str,
+        body: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let addr = (self.host.as_str(), self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or("SMTP host not found")?;
+        let stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut session = Session {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+        session.expect(220)?;
+        session.command("EHLO nutritionist", 250)?;
+        session.command(&format!("MAIL FROM:<{}>", self.from), 250)?;
+        session.command(&format!("RCPT TO:<{}>", to), 250)?;
+        session.command("DATA", 354)?;
+        // lines starting with a dot are escaped by doubling it
+        let body: String = body
+            .lines()
+            .map(|l| {
+                if l.starts_with('.') {
+                    format!(".{}\r\n", l)
+                } else {
+                    format!("{}\r\n", l)
+                }
+            })
+            .collect();
+        let message = format!(
+            "From: <{}>\r\nTo: <{}>\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}.",
+            self.from, to, subject, body
+        );
+        session.command(&message, 250)?;
+        session.command("QUIT", 221)?;
+        Ok(())
+    }
+}
+
+struct Session {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Session {
+    // reads a possibly multiline reply ("250-..." lines followed by "250 ...")
+    fn expect(&mut self, code: u16) -> Result<(), Box<dyn Error>> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err("SMTP connection closed".into());
+            }
+            let reply: u16 = line
+                .get(..3)
+                .and_then(|c| c.parse().ok())
+                .ok_or("invalid SMTP reply")?;
+            if reply != code {
+                return Err(format!("unexpected SMTP reply: {}", line.trim_end()).into());
+            }
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok(());
+            }
+        }
+    }
+
+    fn command(&mut self, command: &str, code: u16) -> Result<(), Box<dyn Error>> {
+        self.writer.write_all(command.as_bytes())?;
+        self.writer.write_all(b"\r\n")?;
+        self.expect(code)
+    }
+}
+
+impl Notifier for SmtpNotifier {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    fn notify(&self, user: &User, subject: &str, message: &str) -> Result<(), Box<dyn Error>> {
+        if user.email.is_empty() {
+            return Ok(());
+        }
+        self.send_mail(&user.email, subject, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_send_mail() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // fake server answering every command and recording what it received
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut received = vec![];
+            writer.write_all(b"220 fake ESMTP\r\n").unwrap();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end().to_string();
+                let reply: &[u8] = if in_data {
+                    if line == "." {
+                        in_data = false;
+                        b"250 queued\r\n"
+                    } else {
+                        received.push(line);
+                        continue;
+                    }
+                } else if line.starts_with("EHLO") {
+                    b"250-fake\r\n250 SIZE 1000\r\n"
+                } else if line == "DATA" {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line == "QUIT" {
+                    writer.write_all(b"221 bye\r\n").unwrap();
+                    break;
+                } else {
+                    received.push(line);
+                    b"250 ok\r\n"
+                };
+                writer.write_all(reply).unwrap();
+            }
+            received
+        });
+
+        SmtpNotifier::new("127.0.0.1", port, "app@localhost")
+            .send_mail("user@localhost", "Reminder", "Log your meals\n.hidden")
+            .unwrap();
+        let received = server.join().unwrap();
+        assert_eq!(received[0], "MAIL FROM:<app@localhost>");
+        assert_eq!(received[1], "RCPT TO:<user@localhost>");
+        assert!(received.contains(&"Subject: Reminder".to_string()));
+        assert!(received.contains(&"..hidden".to_string()));
+    }
+}
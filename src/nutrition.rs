@@ -0,0 +1,9 @@
+pub(crate) mod contribution;
+pub(crate) mod energy;
+pub(crate) mod ingredients;
+pub(crate) mod logging;
+pub(crate) mod nutriscore;
+pub(crate) mod quality;
+pub(crate) mod weight;
+pub(crate) mod report;
+pub(crate) mod usage;
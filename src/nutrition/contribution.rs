@@ -0,0 +1,153 @@
+use crate::models::goals::GOAL_NUTRIENTS;
+use crate::models::food::FoodItem;
+use crate::models::meal::Meal;
+use crate::usda::search::NutrientValues;
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+pub(crate) struct FoodContribution {
+    pub(crate) food: String,
+    pub(crate) amount: f32,
+    // share of the total of the nutrient over all foods
+    pub(crate) percent: f32,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct NutrientContribution {
+    pub(crate) name: String,
+    pub(crate) unit: String,
+    pub(crate) total: f32,
+    // ranked by amount, largest first
+    pub(crate) foods: Vec<FoodContribution>,
+}
+
+/// Ranks the foods eaten between `start` and `end` (inclusive) by how much they contributed
+/// to every nutrient, the same food in several meals is summed up by its name
+pub(crate) fn contributions(
+    meals: &[Meal],
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Vec<NutrientContribution> {
+    let mut per_food: BTreeMap<String, NutrientValues> = BTreeMap::new();
+    for content in meals
+        .iter()
+        .filter(|m| m.date >= start && m.date <= end)
+        .flat_map(|m| m.contents.iter())
+    {
+        let values = content.nutrients_per_100g() * content.quantity * 0.01;
+        let sum = per_food.entry(content.display_name()).or_default();
+        *sum = sum.clone() + values;
+    }
+    GOAL_NUTRIENTS
+        .iter()
+        .map(|(name, unit, ..)| {
+            let mut foods: Vec<FoodContribution> = per_food
+                .iter()
+                .filter_map(|(food, values)| {
+                    Some(FoodContribution {
+                        food: food.clone(),
+                        amount: values.get(name)?,
+                        percent: 0.0,
+                    })
+                })
+                .filter(|f| f.amount > 0.0)
+                .collect();
+            let total: f32 = foods.iter().map(|f| f.amount).sum();
+            for f in foods.iter_mut() {
+                f.percent = f.amount / total * 100.0;
+            }
+            foods.sort_by(|a, b| b.amount.total_cmp(&a.amount));
+            NutrientContribution {
+                name: name.to_string(),
+                unit: unit.to_string(),
+                total,
+                foods,
+            }
+        })
+        .collect()
+}
+
+impl NutrientContribution {
+    /// Only keeps the `n` largest contributors
+    pub(crate) fn top(mut self, n: usize) -> NutrientContribution {
+        self.foods.truncate(n);
+        self
+    }
+
+    pub fn label(&self) -> String {
+        crate::models::goals::goal_label(&self.name)
+    }
+
+    fn rounded(&self, value: f32) -> String {
+        if self.unit == "kcal" {
+            format!("{:.0}", value)
+        } else {
+            format!("{:.1}", value)
+        }
+    }
+
+    pub fn total_rounded(&self) -> String {
+        self.rounded(self.total)
+    }
+
+    pub fn amount_rounded(&self, food: &FoodContribution) -> String {
+        self.rounded(food.amount)
+    }
+}
+
+impl FoodContribution {
+    pub fn percent_rounded(&self) -> String {
+        format!("{:.0}", self.percent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::meal::{MealContent, MealType};
+    use crate::usda::nutrients;
+    use crate::models::food::StoredFood;
+    use crate::usda::search::{Food, Nutrient};
+    use uuid::Uuid;
+
+    fn meal(date: NaiveDate, contents: Vec<(&Food, f32)>) -> Meal {
+        Meal {
+            contents: contents
+                .into_iter()
+                .map(|(food, quantity)| MealContent::new(StoredFood::Usda(food.clone()), quantity))
+                .collect(),
+            id: Uuid::new_v4(),
+            username: "12345".to_string(),
+            date,
+            meal_type: MealType::Dinner,
+        }
+    }
+
+    #[test]
+    fn test_sodium_contribution() {
+        let sodium = |mg: f32| vec![Nutrient::new(nutrients::SODIUM, "Sodium, Na", mg, "MG")];
+        let soup = Food::new(1, "Soup", sodium(800.0));
+        let bread = Food::new(2, "Bread", sodium(400.0));
+        let apple = Food::new(3, "Apple", vec![]);
+        let day = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let meals = vec![
+            meal(day, vec![(&soup, 100.0), (&bread, 50.0), (&apple, 150.0)]),
+            meal(day, vec![(&bread, 50.0)]),
+            // outside of the range
+            meal(
+                day.succ_opt().unwrap(),
+                vec![(&apple, 100.0), (&bread, 500.0)],
+            ),
+        ];
+        let result = contributions(&meals, day, day);
+        let sodium = result.iter().find(|c| c.name == "sodium").unwrap();
+        assert!((sodium.total - 1.2).abs() < 0.001);
+        // apple contributed nothing and is left out
+        assert_eq!(sodium.foods.len(), 2);
+        assert_eq!(sodium.foods[0].food, soup.display_name());
+        assert!((sodium.foods[0].percent - 66.67).abs() < 0.1);
+        assert!((sodium.foods[1].amount - 0.4).abs() < 0.001);
+        assert_eq!(sodium.clone().top(1).foods.len(), 1);
+    }
+}
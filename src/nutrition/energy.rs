@@ -0,0 +1,195 @@
+use crate::models::goals::{GoalTarget, NutritionGoals};
+use crate::models::profile::{BodyProfile, Sex};
+use crate::usda::nutrients;
+use chrono::NaiveDate;
+use std::fmt::Display;
+
+// grams of protein per kg of body weight proposed as daily minimum
+const PROTEIN_G_PER_KG: f32 = 1.6;
+const FAT_PERCENT_OF_ENERGY: f32 = 30.0;
+// dietary guidelines: 14 g fiber per 1000 kcal
+const FIBER_G_PER_1000_KCAL: f32 = 14.0;
+// WHO: free sugars below 10% of energy, less than 5 g salt per day
+const SUGAR_PERCENT_OF_ENERGY: f32 = 10.0;
+const SALT_LIMIT_G: f32 = 5.0;
+// common rule of thumb for the daily fluid intake
+const WATER_ML_PER_KG: f32 = 35.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BmrFormula {
+    MifflinStJeor,
+    HarrisBenedict,
+    KatchMcArdle,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct EnergyProposal {
+    pub(crate) formula: BmrFormula,
+    pub(crate) bmr: f32,
+    pub(crate) tdee: f32,
+    pub(crate) goals: NutritionGoals,
+}
+
+impl BmrFormula {
+    pub(crate) const ALL: [BmrFormula; 3] = [
+        BmrFormula::MifflinStJeor,
+        BmrFormula::HarrisBenedict,
+        BmrFormula::KatchMcArdle,
+    ];
+
+    pub fn key(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<BmrFormula> {
+        BmrFormula::ALL.into_iter().find(|f| f.key() == s)
+    }
+
+    /// Basal metabolic rate in kcal per day, None if the profile lacks a required value
+    pub(crate) fn bmr(&self, profile: &BodyProfile, today: NaiveDate) -> Option<f32> {
+        match self {
+            BmrFormula::MifflinStJeor => {
+                let (weight, height) = (profile.weight_kg?, profile.height_cm?);
+                let age = profile.age(today)? as f32;
+                let base = 10.0 * weight + 6.25 * height - 5.0 * age;
+                Some(match profile.sex? {
+                    Sex::Male => base + 5.0,
+                    Sex::Female => base - 161.0,
+                })
+            }
+            // revised by Roza and Shizgal (1984)
+            BmrFormula::HarrisBenedict => {
+                let (weight, height) = (profile.weight_kg?, profile.height_cm?);
+                let age = profile.age(today)? as f32;
+                Some(match profile.sex? {
+                    Sex::Male => 88.362 + 13.397 * weight + 4.799 * height - 5.677 * age,
+                    Sex::Female => 447.593 + 9.247 * weight + 3.098 * height - 4.330 * age,
+                })
+            }
+            BmrFormula::KatchMcArdle => Some(370.0 + 21.6 * profile.lean_body_mass_kg()?),
+        }
+    }
+}
+
+impl Display for BmrFormula {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BmrFormula::MifflinStJeor => write!(f, "Mifflin-St Jeor"),
+            BmrFormula::HarrisBenedict => write!(f, "Harris-Benedict"),
+            BmrFormula::KatchMcArdle => write!(f, "Katch-McArdle"),
+        }
+    }
+}
+
+/// Goals for a daily energy expenditure, protein scales with body weight and carbohydrates fill the rest
+pub(crate) fn propose_goals(tdee: f32, weight_kg: Option<f32>) -> NutritionGoals {
+    let energy = tdee.round();
+    let fats = energy * FAT_PERCENT_OF_ENERGY * 0.01 / nutrients::KCAL_PER_G_FAT;
+    let proteins = weight_kg
+        .map(|w| w * PROTEIN_G_PER_KG)
+        .unwrap_or(energy * 0.2 / nutrients::KCAL_PER_G_PROTEIN);
+    let carbohydrates = (energy
+        - fats * nutrients::KCAL_PER_G_FAT
+        - proteins * nutrients::KCAL_PER_G_PROTEIN)
+        .max(0.0)
+        / nutrients::KCAL_PER_G_CARBOHYDRATE;
+    NutritionGoals {
+        energy: Some(energy),
+        carbohydrates: Some(GoalTarget::Absolute(carbohydrates.round())),
+        proteins: Some(GoalTarget::Absolute(proteins.round())),
+        fats: Some(GoalTarget::PercentOfEnergy(FAT_PERCENT_OF_ENERGY)),
+        fiber: Some(GoalTarget::Absolute((energy * 0.001 * FIBER_G_PER_1000_KCAL).round())),
+        sugar: Some(GoalTarget::PercentOfEnergy(SUGAR_PERCENT_OF_ENERGY)),
+        salt: Some(GoalTarget::Absolute(SALT_LIMIT_G)),
+        sodium: None,
+        water_ml: weight_kg.map(|w| (w * WATER_ML_PER_KG / 100.0).round() * 100.0),
+    }
+}
+
+pub(crate) fn propose(profile: &BodyProfile, formula: BmrFormula, today: NaiveDate) -> Option<EnergyProposal> {
+    let bmr = formula.bmr(profile, today)?;
+    let tdee = bmr * profile.activity_level.factor();
+    Some(EnergyProposal {
+        formula,
+        bmr,
+        tdee,
+        goals: propose_goals(tdee, profile.weight_kg),
+    })
+}
+
+/// Proposals of every formula the profile has enough data for
+pub(crate) fn propose_all(profile: &BodyProfile, today: NaiveDate) -> Vec<EnergyProposal> {
+    BmrFormula::ALL
+        .iter()
+        .filter_map(|formula| propose(profile, *formula, today))
+        .collect()
+}
+
+impl EnergyProposal {
+    pub fn bmr_rounded(&self) -> String {
+        format!("{:.0}", self.bmr)
+    }
+
+    pub fn tdee_rounded(&self) -> String {
+        format!("{:.0}", self.tdee)
+    }
+
+    pub fn goal_summary(&self) -> String {
+        ["proteins", "fats", "carbohydrates", "fiber"]
+            .iter()
+            .filter_map(|name| Some(format!("{:.0} g {}", self.goals.resolved(name)?, name)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::profile::ActivityLevel;
+
+    fn profile() -> BodyProfile {
+        BodyProfile {
+            sex: Some(Sex::Male),
+            birth_date: NaiveDate::from_ymd_opt(1994, 1, 1),
+            height_cm: Some(180.0),
+            weight_kg: Some(80.0),
+            body_fat_percent: Some(20.0),
+            activity_level: ActivityLevel::Moderate,
+        }
+    }
+
+    #[test]
+    fn test_bmr_formulas() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let p = profile();
+        assert_eq!(BmrFormula::MifflinStJeor.bmr(&p, today), Some(1780.0));
+        let hb = BmrFormula::HarrisBenedict.bmr(&p, today).unwrap();
+        assert!((hb - 1853.63).abs() < 0.01);
+        let km = BmrFormula::KatchMcArdle.bmr(&p, today).unwrap();
+        assert!((km - 1752.4).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_missing_values() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let p = BodyProfile {
+            body_fat_percent: None,
+            ..profile()
+        };
+        assert_eq!(BmrFormula::KatchMcArdle.bmr(&p, today), None);
+        assert_eq!(propose_all(&p, today).len(), 2);
+        assert!(propose_all(&BodyProfile::default(), today).is_empty());
+    }
+
+    #[test]
+    fn test_proposed_goals_add_up() {
+        let goals = propose_goals(2500.0, Some(80.0));
+        let fats = goals.resolved("fats").unwrap();
+        let proteins = goals.resolved("proteins").unwrap();
+        let carbohydrates = goals.resolved("carbohydrates").unwrap();
+        assert_eq!(proteins, 128.0);
+        let energy = fats * 9.0 + proteins * 4.0 + carbohydrates * 4.0;
+        assert!((energy - 2500.0).abs() < 5.0);
+    }
+}
@@ -0,0 +1,329 @@
+// Ingredient lists of packaged foods as declared on the label, e.g. "Sugar, palm oil,
+// hazelnuts (13%), emulsifier (lecithins (soy)), vanillin", parsed into nested ingredients
+// with their additives, added sugars and allergens
+use crate::models::diet::{derive_flags, FoodFlag};
+use crate::usda::search::Food;
+
+// additives often declared by name instead of their E-number
+const ADDITIVE_NAMES: [(&str, &str); 24] = [
+    ("sorbic acid", "E200"),
+    ("potassium sorbate", "E202"),
+    ("sodium benzoate", "E211"),
+    ("sodium nitrite", "E250"),
+    ("ascorbic acid", "E300"),
+    ("tocopherol", "E306"),
+    ("lecithin", "E322"),
+    ("citric acid", "E330"),
+    ("carrageenan", "E407"),
+    ("locust bean gum", "E410"),
+    ("guar gum", "E412"),
+    ("xanthan gum", "E415"),
+    ("pectin", "E440"),
+    ("cellulose gum", "E466"),
+    ("mono and diglycerides", "E471"),
+    ("sodium bicarbonate", "E500"),
+    ("baking soda", "E500"),
+    ("monosodium glutamate", "E621"),
+    ("acesulfame", "E950"),
+    ("aspartame", "E951"),
+    ("cyclamate", "E952"),
+    ("saccharin", "E954"),
+    ("sucralose", "E955"),
+    ("caramel color", "E150"),
+];
+
+// sugars and syrups that are added, not part of a whole ingredient like fruit
+const ADDED_SUGARS: [&str; 16] = [
+    "sugar",
+    "cane sugar",
+    "glucose",
+    "fructose",
+    "dextrose",
+    "sucrose",
+    "maltose",
+    "lactose",
+    "syrup",
+    "honey",
+    "molasses",
+    "agave",
+    "maltodextrin",
+    "invert sugar",
+    "juice concentrate",
+    "treacle",
+];
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct Ingredient {
+    pub(crate) name: String,
+    /// Declared share of the food, e.g. 13 for "hazelnuts 13%"
+    pub(crate) percent: Option<f32>,
+    /// E-number of an additive, e.g. "E322"
+    pub(crate) additive: Option<String>,
+    pub(crate) added_sugar: bool,
+    pub(crate) flags: Vec<FoodFlag>,
+    /// Ingredients of a compound ingredient or the additives of a class like "emulsifier"
+    pub(crate) sub_ingredients: Vec<Ingredient>,
+}
+
+impl Ingredient {
+    /// The ingredient followed by all its sub-ingredients
+    pub(crate) fn flatten(&self) -> Vec<&Ingredient> {
+        let mut all = vec![self];
+        all.extend(self.sub_ingredients.iter().flat_map(|i| i.flatten()));
+        all
+    }
+
+    pub fn percent_text(&self) -> String {
+        self.percent.map(|p| format!("{}%", p)).unwrap_or_default()
+    }
+}
+
+/// E-number in a text like "E471", "e 150d" or "emulsifier E-330", only a single space or
+/// hyphen may separate the e from the digits
+pub(crate) fn e_number(text: &str) -> Option<String> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let boundary = |i: usize| text.get(i).is_none_or(|c| !c.is_alphanumeric());
+    (0..text.len()).find_map(|start| {
+        if text[start] != 'e' || (start > 0 && !boundary(start - 1)) {
+            return None;
+        }
+        let mut i = start + 1;
+        if matches!(text.get(i), Some(' ' | '-')) {
+            i += 1;
+        }
+        let digits = text[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+        let mut end = i + digits;
+        if text.get(end).is_some_and(|c| c.is_ascii_lowercase()) {
+            end += 1;
+        }
+        ((3..=4).contains(&digits) && boundary(end))
+            .then(|| format!("E{}", text[i..end].iter().collect::<String>()).to_uppercase())
+    })
+}
+
+// whole words, so "sugar" matches "cane sugar" but not "sugared"
+fn contains_words(text: &str, phrase: &str) -> bool {
+    format!(" {} ", text).contains(&format!(" {} ", phrase))
+}
+
+fn words(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.strip_suffix('s').filter(|s| s.len() > 2).unwrap_or(w))
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+// splits at commas and semicolons outside of parentheses and brackets, a comma between
+// digits is a decimal comma
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    let bytes = text.as_bytes();
+    let digit = |i: Option<usize>| i.and_then(|i| bytes.get(i)).is_some_and(u8::is_ascii_digit);
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if digit(i.checked_sub(1)) && digit(Some(i + 1)) => {}
+            ',' | ';' if depth <= 0 => {
+                parts.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+        .into_iter()
+        .map(|p| p.trim().trim_end_matches('.').trim())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+// "13%" or "13.5 %", also with a decimal comma
+fn percent(text: &str) -> Option<f32> {
+    let before = text.split('%').next().filter(|_| text.contains('%'))?;
+    let number: String = before
+        .chars()
+        .rev()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ',' || *c == ' ')
+        .collect::<Vec<char>>()
+        .into_iter()
+        .rev()
+        .collect();
+    number.trim().replace(',', ".").parse().ok()
+}
+
+fn parse_ingredient(part: &str) -> Ingredient {
+    let (name, inner) = match (part.find(['(', '[']), part.rfind([')', ']'])) {
+        (Some(open), Some(close)) if open < close => (
+            format!("{} {}", &part[..open], &part[close + 1..]),
+            Some(&part[open + 1..close]),
+        ),
+        _ => (part.to_string(), None),
+    };
+    let declared = percent(&name);
+    let share = declared.or_else(|| inner.and_then(percent));
+    // the share follows the name, "hazelnuts 13%"
+    let name = match declared {
+        Some(_) => name
+            .split('%')
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ',' || c == ' ')
+            .to_string(),
+        None => name,
+    }
+    .trim()
+    .to_string();
+    let sub_ingredients = inner
+        .filter(|inner| percent(inner).is_none() || inner.contains(','))
+        .map(parse_ingredients)
+        .unwrap_or_default();
+    let normalized = words(&name);
+    let additive = e_number(&name).or_else(|| {
+        ADDITIVE_NAMES
+            .iter()
+            .find(|(n, _)| contains_words(&normalized, &words(n)))
+            .map(|(_, code)| code.to_string())
+    });
+    Ingredient {
+        added_sugar: ADDED_SUGARS
+            .iter()
+            .any(|s| contains_words(&normalized, &words(s))),
+        flags: derive_flags(&name),
+        name,
+        percent: share,
+        additive,
+        sub_ingredients,
+    }
+}
+
+/// Ingredients of a label text in the declared order
+pub(crate) fn parse_ingredients(text: &str) -> Vec<Ingredient> {
+    let text = text.trim();
+    let text = match text.split_once(':') {
+        Some((label, rest)) if label.trim().eq_ignore_ascii_case("ingredients") => rest,
+        _ => text,
+    };
+    split_top_level(text)
+        .into_iter()
+        .map(parse_ingredient)
+        .collect()
+}
+
+impl Food {
+    pub fn ingredient_list(&self) -> Vec<Ingredient> {
+        self.ingredients
+            .as_deref()
+            .map(parse_ingredients)
+            .unwrap_or_default()
+    }
+
+    /// E-numbers of the additives, in the order of the ingredients
+    pub fn additives(&self) -> Vec<String> {
+        let mut additives: Vec<String> = vec![];
+        for ingredient in self.ingredient_list().iter() {
+            for code in ingredient
+                .flatten()
+                .into_iter()
+                .filter_map(|i| i.additive.clone())
+            {
+                if !additives.contains(&code) {
+                    additives.push(code);
+                }
+            }
+        }
+        additives
+    }
+
+    pub fn has_added_sugar(&self) -> bool {
+        self.ingredient_list()
+            .iter()
+            .any(|i| i.flatten().iter().any(|i| i.added_sugar))
+    }
+
+    /// Allergen and diet flags of the ingredients
+    pub(crate) fn ingredient_flags(&self) -> Vec<FoodFlag> {
+        let mut flags: Vec<FoodFlag> = self
+            .ingredient_list()
+            .iter()
+            .flat_map(|i| i.flatten())
+            .flat_map(|i| i.flags.clone())
+            .collect();
+        flags.sort();
+        flags.dedup();
+        flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NUTELLA: &str = "Sugar, palm oil, hazelnuts 13%, skimmed milk powder 8,7%, fat-reduced \
+        cocoa 7.4%, emulsifier (lecithins [soy]), vanillin.";
+
+    #[test]
+    fn test_parse_ingredients() {
+        let ingredients = parse_ingredients(NUTELLA);
+        let names: Vec<&str> = ingredients.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Sugar",
+                "palm oil",
+                "hazelnuts",
+                "skimmed milk powder",
+                "fat-reduced cocoa",
+                "emulsifier",
+                "vanillin"
+            ]
+        );
+        assert!(ingredients[0].added_sugar);
+        assert_eq!(ingredients[2].percent, Some(13.0));
+        assert_eq!(ingredients[2].flags, [FoodFlag::TreeNuts]);
+        assert_eq!(ingredients[3].percent, Some(8.7));
+        assert_eq!(ingredients[3].flags, [FoodFlag::Milk]);
+        let lecithin = &ingredients[5].sub_ingredients[0];
+        assert_eq!(lecithin.additive.as_deref(), Some("E322"));
+        assert_eq!(lecithin.sub_ingredients[0].flags, [FoodFlag::Soy]);
+    }
+
+    #[test]
+    fn test_e_numbers() {
+        assert_eq!(e_number("E471").as_deref(), Some("E471"));
+        assert_eq!(e_number("colour: e 150d").as_deref(), Some("E150D"));
+        assert_eq!(e_number("E-330").as_deref(), Some("E330"));
+        assert_eq!(e_number("emulsifier E471").as_deref(), Some("E471"));
+        assert_eq!(e_number("sweetener E951").as_deref(), Some("E951"));
+        assert_eq!(e_number("antioxidant E 306").as_deref(), Some("E306"));
+        assert_eq!(e_number("e  330"), None);
+        assert_eq!(e_number("e33012"), None);
+        assert_eq!(e_number("egg yolk"), None);
+        assert_eq!(e_number("e12"), None);
+        let ingredients = parse_ingredients("Ingredients: water, thickener (E415, E412), E330");
+        assert_eq!(ingredients[0].name, "water");
+        assert_eq!(ingredients[1].sub_ingredients.len(), 2);
+        assert_eq!(ingredients[2].additive.as_deref(), Some("E330"));
+    }
+
+    #[test]
+    fn test_food_ingredients() {
+        let mut food = Food::new(1, "Hazelnut spread", vec![]);
+        assert!(food.ingredient_list().is_empty());
+        food.ingredients = Some(NUTELLA.to_string());
+        assert_eq!(food.additives(), ["E322"]);
+        assert!(food.has_added_sugar());
+        assert_eq!(
+            food.ingredient_flags(),
+            [FoodFlag::Milk, FoodFlag::TreeNuts, FoodFlag::Soy]
+        );
+        food.ingredients = Some("Apples, water, vitamin B12".to_string());
+        assert!(!food.has_added_sugar());
+        assert_eq!(food.ingredient_list()[2].name, "vitamin B12");
+    }
+}
@@ -0,0 +1,147 @@
+use crate::models::meal::{DailyMealCombo, Meal, REQUIRED_SLOTS};
+use chrono::{Duration, NaiveDate};
+use std::collections::BTreeMap;
+
+// days the completeness and gaps are calculated for
+pub(crate) const LOGGING_WINDOW_DAYS: i64 = 28;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LoggingStats {
+    // consecutive days with food logged up to today, today may still be open
+    pub(crate) current_streak: usize,
+    pub(crate) longest_streak: usize,
+    // days in the window, starts with the first logged day for new users
+    pub(crate) days: usize,
+    pub(crate) days_logged: usize,
+    pub(crate) complete_days: usize,
+    // share of the required slots that were logged in the window
+    pub(crate) completeness: f32,
+    // past days in the window without any food, newest first
+    pub(crate) gaps: Vec<NaiveDate>,
+    pub(crate) logged_today: bool,
+}
+
+/// Streaks, completeness and gaps of the logging up to `today`
+pub(crate) fn logging_stats(meals: &[Meal], today: NaiveDate) -> LoggingStats {
+    let combos: BTreeMap<NaiveDate, DailyMealCombo> = DailyMealCombo::group_by_day(meals)
+        .into_iter()
+        .filter(|(date, combo)| *date <= today && combo.has_logged())
+        .collect();
+
+    let mut longest_streak = 0;
+    let mut streak = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for date in combos.keys() {
+        streak = match previous {
+            Some(p) if *date - p == Duration::days(1) => streak + 1,
+            _ => 1,
+        };
+        longest_streak = longest_streak.max(streak);
+        previous = Some(*date);
+    }
+    let logged_today = combos.contains_key(&today);
+    let mut current_streak = 0;
+    let mut day = if logged_today {
+        today
+    } else {
+        today - Duration::days(1)
+    };
+    while combos.contains_key(&day) {
+        current_streak += 1;
+        day -= Duration::days(1);
+    }
+
+    let window_start = today - Duration::days(LOGGING_WINDOW_DAYS - 1);
+    let start = combos
+        .keys()
+        .next()
+        .map_or(today, |first| (*first).max(window_start));
+    let window: Vec<NaiveDate> = start.iter_days().take_while(|d| *d <= today).collect();
+    let slots_logged: usize = window
+        .iter()
+        .filter_map(|d| combos.get(d))
+        .map(|c| REQUIRED_SLOTS.len() - c.missing_slots().len())
+        .sum();
+    let gaps = window
+        .iter()
+        .rev()
+        .filter(|d| **d < today && !combos.contains_key(d))
+        .cloned()
+        .collect();
+
+    LoggingStats {
+        current_streak,
+        longest_streak,
+        days: window.len(),
+        days_logged: window.iter().filter(|d| combos.contains_key(d)).count(),
+        complete_days: window
+            .iter()
+            .filter(|d| combos.get(d).is_some_and(|c| c.is_complete()))
+            .count(),
+        completeness: slots_logged as f32 / (window.len() * REQUIRED_SLOTS.len()) as f32 * 100.0,
+        gaps,
+        logged_today,
+    }
+}
+
+impl LoggingStats {
+    pub fn completeness_rounded(&self) -> String {
+        format!("{:.0}", self.completeness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::meal::{MealContent, MealType};
+    use crate::models::models::RedisORM;
+    use crate::models::food::StoredFood;
+    use crate::usda::search::Food;
+    use uuid::Uuid;
+
+    fn meal(date: NaiveDate, meal_type: MealType) -> Meal {
+        Meal {
+            contents: vec![MealContent::new(StoredFood::Usda(Food::example()), 100.0)],
+            id: Uuid::new_v4(),
+            username: "12345".to_string(),
+            date,
+            meal_type,
+        }
+    }
+
+    #[test]
+    fn test_logging_stats() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let day = |n: i64| today - Duration::days(n);
+        let mut meals = vec![
+            // three day streak long ago
+            meal(day(9), MealType::Lunch),
+            meal(day(8), MealType::Lunch),
+            meal(day(7), MealType::Lunch),
+            // complete day
+            meal(day(2), MealType::Breakfast),
+            meal(day(2), MealType::Lunch),
+            meal(day(2), MealType::Dinner),
+            meal(day(1), MealType::Snack),
+        ];
+        // an empty meal is not logged
+        let mut empty = meal(day(4), MealType::Dinner);
+        empty.contents.clear();
+        meals.push(empty);
+
+        let stats = logging_stats(&meals, today);
+        assert!(!stats.logged_today);
+        assert_eq!(stats.current_streak, 2);
+        assert_eq!(stats.longest_streak, 3);
+        assert_eq!(stats.days, 10);
+        assert_eq!(stats.days_logged, 5);
+        assert_eq!(stats.complete_days, 1);
+        assert_eq!(stats.gaps, vec![day(3), day(4), day(5), day(6)]);
+        // 3 + 3 slots of 30
+        assert!((stats.completeness - 20.0).abs() < 0.01);
+
+        meals.push(meal(today, MealType::Breakfast));
+        assert_eq!(logging_stats(&meals, today).current_streak, 3);
+        assert_eq!(logging_stats(&[], today).current_streak, 0);
+    }
+}
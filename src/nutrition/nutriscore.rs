@@ -0,0 +1,667 @@
+// Nutri-Score after the 2023 update of the algorithm, computed from the nutrients per 100 g
+// and the category of a food, and the NOVA group estimated from the ingredients. Of the 2023
+// changes, beverages with non-nutritive sweeteners get 4 extra points and olive, rapeseed and
+// walnut oils count as fruit, vegetables and legumes.
+use crate::models::food::{FoodItem, StoredFood};
+use crate::models::meal::{Meal, MealContent};
+use crate::usda::nutrients::KJ_PER_KCAL;
+use crate::usda::search::Food;
+
+// a point for every threshold the value is above
+const ENERGY_KJ: [f32; 10] = [
+    335.0, 670.0, 1005.0, 1340.0, 1675.0, 2010.0, 2345.0, 2680.0, 3015.0, 3350.0,
+];
+const SUGARS: [f32; 15] = [
+    3.4, 6.8, 10.0, 14.0, 17.0, 20.0, 24.0, 27.0, 31.0, 34.0, 37.0, 41.0, 44.0, 48.0, 51.0,
+];
+const SATURATED_FAT: [f32; 10] = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+const SALT: [f32; 20] = [
+    0.2, 0.4, 0.6, 0.8, 1.0, 1.2, 1.4, 1.6, 1.8, 2.0, 2.2, 2.4, 2.6, 2.8, 3.0, 3.2, 3.4, 3.6, 3.8,
+    4.0,
+];
+const PROTEIN: [f32; 7] = [2.4, 4.8, 7.2, 9.6, 12.0, 14.0, 17.0];
+const FIBER: [f32; 5] = [3.0, 4.1, 5.2, 6.3, 7.4];
+// beverages
+const BEVERAGE_ENERGY_KJ: [f32; 10] = [
+    30.0, 90.0, 150.0, 210.0, 240.0, 270.0, 300.0, 330.0, 360.0, 390.0,
+];
+const BEVERAGE_SUGARS: [f32; 10] = [0.5, 2.0, 3.5, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0];
+const BEVERAGE_PROTEIN: [f32; 7] = [1.2, 1.5, 1.8, 2.1, 2.4, 2.7, 3.0];
+// fats, oils, nuts and seeds are judged by the energy from saturated fat and its share of the fat
+const SATURATED_ENERGY_KJ: [f32; 10] = [
+    120.0, 240.0, 360.0, 480.0, 600.0, 720.0, 840.0, 960.0, 1080.0, 1200.0,
+];
+const SATURATED_SHARE: [f32; 10] = [10.0, 16.0, 22.0, 28.0, 34.0, 40.0, 46.0, 52.0, 58.0, 64.0];
+const KJ_PER_G_FAT: f32 = 37.0;
+const SWEETENER_POINTS: i32 = 4;
+// non-nutritive sweeteners, polyols like E420 sorbitol have energy and do not count
+const SWEETENERS: [&str; 11] = [
+    "E950", "E951", "E952", "E954", "E955", "E957", "E959", "E960", "E961", "E962", "E969",
+];
+
+// whole FDC category names (SR Legacy, FNDDS, branded) and Open Food Facts category tags
+const CHEESE_CATEGORIES: [&str; 5] = [
+    "cheese",
+    "cottage/ricotta cheese",
+    "cheeses",
+    "hard cheeses",
+    "soft cheeses",
+];
+const FAT_CATEGORIES: [&str; 14] = [
+    "fats and oils",
+    "nut and seed products",
+    "butter and animal fats",
+    "margarine",
+    "nuts and seeds",
+    "vegetable & cooking oils",
+    "nut & seed butters",
+    "butter & spread",
+    "fats",
+    "vegetable oils",
+    "olive oils",
+    "nuts",
+    "seeds",
+    "butters",
+];
+const RED_MEAT_CATEGORIES: [&str; 9] = [
+    "beef products",
+    "pork products",
+    "lamb, veal, and game products",
+    "beef, excludes ground",
+    "ground beef",
+    "pork",
+    "lamb, goat, game",
+    "beef",
+    "lamb",
+];
+// kinds of food named by the description if the category does not tell, see `food_name`
+const CHEESE_NAMES: [&str; 1] = ["cheese"];
+const FAT_NAMES: [&str; 8] = [
+    "oil",
+    "butter",
+    "margarine",
+    "lard",
+    "shortening",
+    "ghee",
+    "nut",
+    "seed",
+];
+const RED_MEAT_NAMES: [&str; 9] = [
+    "beef", "pork", "veal", "lamb", "mutton", "venison", "steak", "chop", "ham",
+];
+// oils that count as fruit, vegetables and legumes since 2023
+const FVL_OILS: [&str; 4] = ["olive", "rapeseed", "canola", "walnut"];
+
+/// Categories with their own thresholds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum NutriCategory {
+    General,
+    Cheese,
+    RedMeat,
+    FatsOilsNutsSeeds,
+    Beverage,
+    Water,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NutriScore {
+    /// Negative minus positive points, lower is better
+    pub(crate) score: i32,
+    pub(crate) grade: char,
+}
+
+impl NutriScore {
+    pub fn class(&self) -> &'static str {
+        match self.grade {
+            'A' => "bg-success",
+            'B' => "bg-info text-dark",
+            'C' => "bg-warning text-dark",
+            'D' => "bg-danger bg-opacity-75",
+            _ => "bg-danger",
+        }
+    }
+}
+
+fn points(value: f32, thresholds: &[f32]) -> i32 {
+    thresholds.iter().filter(|t| value > **t).count() as i32
+}
+
+// lowercase words with the plural s removed, so "Nuts" is "nut" but "Doughnuts" is "doughnut"
+fn words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            w.strip_suffix('s')
+                .filter(|w| w.len() > 2)
+                .unwrap_or(w)
+                .to_string()
+        })
+        .collect()
+}
+
+// the kind of food, the last word of the first part of the description: "cheese" for
+// "Cream cheese" and "Cheese, cheddar", "pizza" for "Pizza, cheese" and "tuna" for "Tuna, canned
+// in oil"
+fn food_name(food: &Food) -> String {
+    let first = food.description.split(',').next().unwrap_or_default();
+    words(first).pop().unwrap_or_default()
+}
+
+pub(crate) fn category(food: &Food) -> NutriCategory {
+    let values = food.nutrients_per_100g();
+    if food.is_beverage() {
+        let water = words(&food.description).iter().any(|w| w == "water");
+        if water && values.energy < 1.0 && values.sugar == 0.0 {
+            return NutriCategory::Water;
+        }
+        return NutriCategory::Beverage;
+    }
+    let categories = food.categories();
+    let in_category = |names: &[&str]| categories.iter().any(|c| names.contains(&c.as_str()));
+    let by_category = [
+        (&CHEESE_CATEGORIES[..], NutriCategory::Cheese),
+        (&FAT_CATEGORIES[..], NutriCategory::FatsOilsNutsSeeds),
+        (&RED_MEAT_CATEGORIES[..], NutriCategory::RedMeat),
+    ]
+    .into_iter()
+    .find(|(names, _)| in_category(names));
+    if let Some((_, category)) = by_category {
+        return category;
+    }
+    let name = food_name(food);
+    if CHEESE_NAMES.contains(&name.as_str()) {
+        NutriCategory::Cheese
+    } else if FAT_NAMES.contains(&name.as_str()) {
+        NutriCategory::FatsOilsNutsSeeds
+    } else if RED_MEAT_NAMES.contains(&name.as_str()) {
+        NutriCategory::RedMeat
+    } else {
+        NutriCategory::General
+    }
+}
+
+// olive, rapeseed and walnut oil, named by the description or an Open Food Facts tag like
+// "olive oils"
+fn is_fvl_oil(food: &Food) -> bool {
+    let named = food_name(food) == "oil"
+        && words(&food.description)
+            .iter()
+            .any(|w| FVL_OILS.contains(&w.as_str()));
+    named
+        || food.categories().iter().any(|c| {
+            c.ends_with(" oils") && words(c).iter().any(|w| FVL_OILS.contains(&w.as_str()))
+        })
+}
+
+// no food data states the share of fruit, vegetables and legumes, whole foods of these
+// categories count fully and everything else not at all
+fn fruit_vegetable_share(food: &Food) -> f32 {
+    let text = food
+        .food_category
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+    let whole = food.ingredients.as_deref().is_none_or(|i| !i.contains(','));
+    if is_fvl_oil(food)
+        || (whole
+            && ["fruit", "vegetable", "legume"]
+                .iter()
+                .any(|w| text.contains(w)))
+    {
+        100.0
+    } else {
+        0.0
+    }
+}
+
+fn fruit_vegetable_points(share: f32, beverage: bool) -> i32 {
+    match (share, beverage) {
+        (s, false) if s > 80.0 => 5,
+        (s, false) if s > 60.0 => 2,
+        (s, false) if s > 40.0 => 1,
+        (s, true) if s > 80.0 => 6,
+        (s, true) if s > 60.0 => 4,
+        (s, true) if s > 40.0 => 2,
+        _ => 0,
+    }
+}
+
+/// Grade of a score in the thresholds of the category
+pub(crate) fn grade(score: i32, category: NutriCategory) -> char {
+    let limits: [i32; 4] = match category {
+        NutriCategory::Water => return 'A',
+        // only water gets an A among the beverages
+        NutriCategory::Beverage => [i32::MIN, 2, 6, 9],
+        NutriCategory::FatsOilsNutsSeeds => [-6, 2, 10, 18],
+        _ => [0, 2, 10, 18],
+    };
+    ['A', 'B', 'C', 'D']
+        .into_iter()
+        .zip(limits)
+        .find(|(_, limit)| score <= *limit)
+        .map_or('E', |(grade, _)| grade)
+}
+
+/// Nutri-Score of a food from its nutrients per 100 g
+pub(crate) fn nutri_score(food: &Food) -> NutriScore {
+    let category = category(food);
+    if category == NutriCategory::Water {
+        return NutriScore {
+            score: 0,
+            grade: 'A',
+        };
+    }
+    let v = food.nutrients_per_100g();
+    let kj = v.energy * KJ_PER_KCAL;
+    let beverage = category == NutriCategory::Beverage;
+    let (energy, sugars, saturated) = match category {
+        NutriCategory::Beverage => (
+            points(kj, &BEVERAGE_ENERGY_KJ),
+            points(v.sugar, &BEVERAGE_SUGARS),
+            points(v.saturated_fat, &SATURATED_FAT),
+        ),
+        NutriCategory::FatsOilsNutsSeeds => {
+            let share = if v.fats > 0.0 {
+                v.saturated_fat / v.fats * 100.0
+            } else {
+                0.0
+            };
+            (
+                points(v.saturated_fat * KJ_PER_G_FAT, &SATURATED_ENERGY_KJ),
+                points(v.sugar, &SUGARS),
+                points(share, &SATURATED_SHARE),
+            )
+        }
+        _ => (
+            points(kj, &ENERGY_KJ),
+            points(v.sugar, &SUGARS),
+            points(v.saturated_fat, &SATURATED_FAT),
+        ),
+    };
+    let sweetened = beverage
+        && food
+            .additives()
+            .iter()
+            .any(|a| SWEETENERS.contains(&a.as_str()));
+    let negative = energy
+        + sugars
+        + saturated
+        + points(v.salt, &SALT)
+        + if sweetened { SWEETENER_POINTS } else { 0 };
+
+    let mut protein = if beverage {
+        points(v.proteins, &BEVERAGE_PROTEIN)
+    } else {
+        points(v.proteins, &PROTEIN)
+    };
+    if category == NutriCategory::RedMeat {
+        protein = protein.min(2);
+    }
+    // protein does not make up for an unhealthy food, except in cheese and beverages
+    let protein_limit = match category {
+        NutriCategory::FatsOilsNutsSeeds => 7,
+        _ => 11,
+    };
+    if negative >= protein_limit
+        && !matches!(category, NutriCategory::Cheese | NutriCategory::Beverage)
+    {
+        protein = 0;
+    }
+    let positive = protein
+        + points(v.fiber, &FIBER)
+        + fruit_vegetable_points(fruit_vegetable_share(food), beverage);
+    let score = negative - positive;
+    NutriScore {
+        score,
+        grade: grade(score, category),
+    }
+}
+
+// ingredients only found in ultra-processed foods, cosmetic additives count as well
+const ULTRA_PROCESSED: [&str; 19] = [
+    "corn syrup",
+    "glucose syrup",
+    "glucose-fructose",
+    "invert sugar",
+    "maltodextrin",
+    "dextrose",
+    "hydrogenated",
+    "interesterified",
+    "isolate",
+    "hydrolysed",
+    "hydrolyzed",
+    "modified starch",
+    "flavour",
+    "flavor",
+    "colour",
+    "color",
+    "emulsifier",
+    "sweetener",
+    "glutamate",
+];
+// E-number ranges of the cosmetic additives that make a food look, taste or feel different:
+// colours, lecithins, emulsifiers, thickeners and stabilisers, flavour enhancers, glazing agents
+// and sweeteners. Acids, antioxidants and preservatives like E330 or E300 are found in processed
+// foods as well, and pectin (E440) in home-made jam.
+const COSMETIC_ADDITIVES: [(u16, u16); 7] = [
+    (100, 199),
+    (322, 322),
+    (400, 439),
+    (441, 499),
+    (620, 650),
+    (900, 909),
+    (950, 969),
+];
+// processed culinary ingredients, they make a food processed when added to others
+const CULINARY: [&str; 9] = [
+    "sugar", "salt", "oil", "butter", "vinegar", "honey", "syrup", "starch", "lard",
+];
+
+// the number of an E-number like "E150D" within the cosmetic ranges
+fn is_cosmetic(code: &str) -> bool {
+    let number: String = code
+        .trim_start_matches('E')
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    number.parse::<u16>().is_ok_and(|n| {
+        COSMETIC_ADDITIVES
+            .iter()
+            .any(|(first, last)| (*first..=*last).contains(&n))
+    })
+}
+
+/// NOVA group from 1 (unprocessed) to 4 (ultra-processed), None if the ingredients are unknown
+pub(crate) fn nova_group(food: &Food) -> Option<u8> {
+    let ingredients = food.ingredient_list();
+    if ingredients.is_empty() {
+        return None;
+    }
+    let names: Vec<String> = ingredients
+        .iter()
+        .flat_map(|i| i.flatten())
+        .map(|i| i.name.to_lowercase())
+        .collect();
+    let has = |words: &[&str]| names.iter().any(|n| words.iter().any(|w| n.contains(w)));
+    let additives = food.additives();
+    if has(&ULTRA_PROCESSED) || additives.iter().any(|code| is_cosmetic(code)) {
+        return Some(4);
+    }
+    match (ingredients.len(), has(&CULINARY) || !additives.is_empty()) {
+        (1, true) => Some(2),
+        (_, true) => Some(3),
+        _ => Some(1),
+    }
+}
+
+impl Food {
+    pub fn nutri_score(&self) -> NutriScore {
+        nutri_score(self)
+    }
+
+    pub fn nova_group(&self) -> Option<u8> {
+        nova_group(self)
+    }
+}
+
+impl StoredFood {
+    /// None for custom foods, which lack the category and ingredients the score needs. A
+    /// recipe scores like the meal of its contents.
+    pub fn nutri_score(&self) -> Option<NutriScore> {
+        match self {
+            StoredFood::Recipe(recipe) => weighted_score(recipe.contents.iter()),
+            _ => self.food().map(|food| nutri_score(&food)),
+        }
+    }
+
+    /// The most processed content of a recipe, None without ingredients
+    pub fn nova_group(&self) -> Option<u8> {
+        match self {
+            StoredFood::Recipe(recipe) => recipe
+                .contents
+                .iter()
+                .filter_map(|c| c.product.nova_group())
+                .max(),
+            _ => nova_group(&self.food()?),
+        }
+    }
+}
+
+// energy weighted mean of the scores of the contents, graded like a general food, contents
+// without a score are left out
+fn weighted_score<'a>(contents: impl Iterator<Item = &'a MealContent>) -> Option<NutriScore> {
+    let mut energy = 0.0;
+    let mut weighted = 0.0;
+    for content in contents {
+        let Some(score) = content.product.nutri_score() else {
+            continue;
+        };
+        let kcal = content.kcal();
+        energy += kcal;
+        weighted += kcal * score.score as f32;
+    }
+    if energy <= 0.0 {
+        return None;
+    }
+    let score = (weighted / energy).round() as i32;
+    Some(NutriScore {
+        score,
+        grade: grade(score, NutriCategory::General),
+    })
+}
+
+/// Energy weighted mean of the scores of the foods eaten, graded like a general food
+pub(crate) fn day_score(meals: &[&Meal]) -> Option<NutriScore> {
+    weighted_score(meals.iter().flat_map(|m| m.contents.iter()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::custom_food::CustomFood;
+    use crate::models::meal::{MealContent, MealType};
+    use crate::models::models::RedisORM;
+    use crate::models::recipe::Recipe;
+    use crate::usda::nutrients;
+    use crate::usda::search::Nutrient;
+    use uuid::Uuid;
+
+    fn food(category: &str, values: &[(i32, f32)]) -> Food {
+        let nutrients = values
+            .iter()
+            .map(|(id, amount)| {
+                let unit = if *id == nutrients::ENERGY {
+                    "KCAL"
+                } else {
+                    "G"
+                };
+                Nutrient::new(*id, "", *amount, unit)
+            })
+            .collect();
+        let mut food = Food::new(1, "Food", nutrients);
+        food.food_category = Some(category.to_string());
+        food
+    }
+
+    fn named(description: &str, category: &str) -> Food {
+        let mut food = food(category, &[]);
+        food.description = description.to_string();
+        food
+    }
+
+    fn nutella() -> Food {
+        food(
+            "Sweet spreads",
+            &[
+                (nutrients::ENERGY, 539.0),
+                (nutrients::TOTAL_FAT, 30.9),
+                (nutrients::SATURATED_FAT, 10.6),
+                (nutrients::SUGARS_TOTAL, 56.3),
+                (nutrients::PROTEIN, 6.3),
+                (nutrients::SODIUM, 0.043),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_general_foods() {
+        // 6 energy + 15 sugar + 10 saturated fat + 0 salt, protein is not counted
+        assert_eq!(
+            nutri_score(&nutella()),
+            NutriScore {
+                score: 31,
+                grade: 'E'
+            }
+        );
+        let apple = food(
+            "Fruits and Fruit Juices",
+            &[
+                (nutrients::ENERGY, 52.0),
+                (nutrients::SUGARS_TOTAL, 10.4),
+                (nutrients::FIBER_TOTAL_DIETARY, 2.4),
+            ],
+        );
+        // 0 energy + 3 sugar - 5 fruit
+        assert_eq!(nutri_score(&apple).score, -2);
+        assert_eq!(nutri_score(&apple).grade, 'A');
+    }
+
+    #[test]
+    fn test_categories() {
+        let cola = food(
+            "Beverages",
+            &[(nutrients::ENERGY, 42.0), (nutrients::SUGARS_TOTAL, 10.6)],
+        );
+        assert_eq!(category(&cola), NutriCategory::Beverage);
+        assert_eq!(nutri_score(&cola).grade, 'E');
+        let water = named("Beverages, water, tap, drinking", "Beverages");
+        assert_eq!(category(&water), NutriCategory::Water);
+        assert_eq!(nutri_score(&water).grade, 'A');
+        let mut olive_oil = food(
+            "Fats and Oils",
+            &[
+                (nutrients::ENERGY, 884.0),
+                (nutrients::TOTAL_FAT, 100.0),
+                (nutrients::SATURATED_FAT, 13.8),
+            ],
+        );
+        olive_oil.description = "Oil, olive, salad or cooking".to_string();
+        assert_eq!(category(&olive_oil), NutriCategory::FatsOilsNutsSeeds);
+        // 4 saturated fat energy + 1 saturated share - 5 for an olive oil
+        assert_eq!(nutri_score(&olive_oil).score, 0);
+        olive_oil.description = "Oil, sunflower".to_string();
+        assert_eq!(nutri_score(&olive_oil).score, 5);
+        assert_eq!(grade(-6, NutriCategory::FatsOilsNutsSeeds), 'A');
+        assert_eq!(grade(-6, NutriCategory::Beverage), 'B');
+    }
+
+    #[test]
+    fn test_category_by_whole_words() {
+        let general = [
+            named("Doughnuts, cake-type, plain", "Baked Products"),
+            named("Cheeseburger, 1 medium patty", "Burgers"),
+            named("Cheesecake", "Cakes and pies"),
+            named("Pizza, cheese, regular crust", "Pizza"),
+            named("Cheese pizza", "Pizza"),
+            named("Tuna, canned in oil", "Finfish and Shellfish Products"),
+            named("Butter cookies", "Cookies & Biscuits"),
+        ];
+        for food in general {
+            assert_eq!(
+                category(&food),
+                NutriCategory::General,
+                "{}",
+                food.description
+            );
+        }
+        let cheddar = named("Cheese, cheddar", "Dairy and Egg Products");
+        assert_eq!(category(&cheddar), NutriCategory::Cheese);
+        let almonds = named("Nuts, almonds", "Nut and Seed Products");
+        assert_eq!(category(&almonds), NutriCategory::FatsOilsNutsSeeds);
+        let beef = named(
+            "Beef, ground, 80% lean meat / 20% fat, raw",
+            "Beef Products",
+        );
+        assert_eq!(category(&beef), NutriCategory::RedMeat);
+        // the category tells the kind of food when the description does not
+        let brie = named("Brie", "Cheese");
+        assert_eq!(category(&brie), NutriCategory::Cheese);
+    }
+
+    #[test]
+    fn test_sweetened_beverages() {
+        let mut cola = food("Diet soft drinks", &[(nutrients::ENERGY, 0.4)]);
+        cola.ingredients = Some("Carbonated water, acid (phosphoric acid)".to_string());
+        assert_eq!(nutri_score(&cola).score, 0);
+        cola.ingredients = Some(
+            "Carbonated water, acid (phosphoric acid), sweeteners (aspartame, acesulfame K)"
+                .to_string(),
+        );
+        assert_eq!(nutri_score(&cola).score, 4);
+        assert_eq!(nutri_score(&cola).grade, 'C');
+    }
+
+    #[test]
+    fn test_nova_group() {
+        let mut oats = food("Cereal", &[]);
+        assert_eq!(nova_group(&oats), None);
+        oats.ingredients = Some("Whole grain oats".to_string());
+        assert_eq!(nova_group(&oats), Some(1));
+        oats.ingredients = Some("Sugar".to_string());
+        assert_eq!(nova_group(&oats), Some(2));
+        oats.ingredients = Some("Whole grain oats, sugar, salt".to_string());
+        assert_eq!(nova_group(&oats), Some(3));
+        oats.ingredients = Some("Oats, sugar, emulsifier (E471)".to_string());
+        assert_eq!(nova_group(&oats), Some(4));
+        oats.ingredients = Some("Oats, e322".to_string());
+        assert_eq!(nova_group(&oats), Some(4));
+        oats.ingredients = Some("Sugar, glucose, colour: E129".to_string());
+        assert_eq!(nova_group(&oats), Some(4));
+    }
+
+    #[test]
+    fn test_nova_group_of_processed_foods_with_additives() {
+        let mut food = food("Canned fruit", &[]);
+        // E300
+        food.ingredients = Some("Apples, water, ascorbic acid".to_string());
+        assert_eq!(nova_group(&food), Some(3));
+        // E440
+        food.ingredients = Some("Strawberries, sugar, gelling agent (pectin)".to_string());
+        assert_eq!(nova_group(&food), Some(3));
+        // E330
+        food.ingredients =
+            Some("Tomatoes, tomato juice, acidity regulator (citric acid)".to_string());
+        assert_eq!(nova_group(&food), Some(3));
+        assert!(!is_cosmetic("E330"));
+        assert!(is_cosmetic("E150D"));
+        assert!(is_cosmetic("E471"));
+    }
+
+    #[test]
+    fn test_day_score() {
+        let meal = Meal {
+            contents: vec![
+                MealContent::new(StoredFood::Usda(nutella()), 20.0),
+                // custom foods have no score and are left out
+                MealContent::new(StoredFood::Custom(CustomFood::example()), 50.0),
+            ],
+            id: Uuid::new_v4(),
+            username: "user".to_string(),
+            date: "2024-05-01".parse().unwrap(),
+            meal_type: MealType::Breakfast,
+        };
+        let score = day_score(&[&meal]).unwrap();
+        assert_eq!(score.grade, 'E');
+        assert_eq!(score, nutri_score(&nutella()));
+        assert_eq!(day_score(&[]), None);
+
+        let recipe = StoredFood::Recipe(Recipe {
+            contents: meal.contents.clone(),
+            ..Recipe::example()
+        });
+        assert_eq!(recipe.nutri_score(), Some(score));
+        assert_eq!(recipe.nova_group(), nova_group(&nutella()));
+        assert_eq!(meal.contents[1].product.nutri_score(), None);
+    }
+}
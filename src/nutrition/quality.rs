@@ -0,0 +1,269 @@
+// Diet quality score from 0 to 100 in the spirit of the Healthy Eating Index, every component
+// is judged by density (per 1000 kcal or share of energy) so days of any size are comparable
+use crate::models::food::FoodItem;
+use crate::models::meal::{DailyMealCombo, Meal};
+use crate::nutrition::report::ReportPeriod;
+use crate::usda::nutrients;
+use crate::usda::search::NutrientValues;
+use chrono::NaiveDate;
+use std::collections::HashSet;
+
+// g of fiber per 1000 kcal for the full score
+const FIBER_DENSITY: f32 = 14.0;
+// % of energy from protein for the full score
+const PROTEIN_SHARE: f32 = 15.0;
+// % of energy from sugar, full score up to the first, none from the second
+const SUGAR_SHARE: (f32, f32) = (10.0, 25.0);
+// g of sodium per 1000 kcal, full score up to the first, none from the second
+const SODIUM_DENSITY: (f32, f32) = (1.1, 2.0);
+// % of energy from fat, full score within the range
+const FAT_SHARE: (f32, f32) = (20.0, 35.0);
+// fat share at which no points are given
+const FAT_SHARE_ZERO: (f32, f32) = (10.0, 45.0);
+// distinct food groups per day for the full score
+const FOOD_GROUPS: usize = 5;
+
+#[derive(Debug, Clone)]
+pub(crate) struct QualityComponent {
+    pub(crate) name: &'static str,
+    pub(crate) score: f32,
+    pub(crate) max: f32,
+    // the measured value, e.g. "12.3 g / 1000 kcal"
+    pub(crate) detail: String,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct DietQuality {
+    pub(crate) components: Vec<QualityComponent>,
+}
+
+// share of the points for `value` growing linearly from `zero` to `full`
+fn linear(value: f32, zero: f32, full: f32) -> f32 {
+    ((value - zero) / (full - zero)).clamp(0.0, 1.0)
+}
+
+fn component(name: &'static str, max: f32, fraction: f32, detail: String) -> QualityComponent {
+    QualityComponent {
+        name,
+        score: max * fraction,
+        max,
+        detail,
+    }
+}
+
+// food group of a food, foods without a category count as their own group
+fn food_group(meal: &Meal) -> impl Iterator<Item = String> + '_ {
+    meal.contents.iter().map(|c| {
+        c.product
+            .food_category()
+            .unwrap_or_else(|| c.display_name())
+            .to_lowercase()
+    })
+}
+
+/// Scores the meals of one day, None if they provide no energy
+pub(crate) fn score_day(meals: &[&Meal]) -> Option<DietQuality> {
+    let values = meals
+        .iter()
+        .fold(NutrientValues::default(), |sum, m| sum + m.get_macros());
+    if values.energy <= 0.0 {
+        return None;
+    }
+    let per_1000_kcal = |grams: f32| grams / values.energy * 1000.0;
+    let share = |grams: f32, kcal_per_gram: f32| grams * kcal_per_gram / values.energy * 100.0;
+
+    let fiber = per_1000_kcal(values.fiber);
+    let protein = share(values.proteins, nutrients::KCAL_PER_G_PROTEIN);
+    let sugar = share(values.sugar, nutrients::KCAL_PER_G_CARBOHYDRATE);
+    let sodium = per_1000_kcal(values.sodium.max(values.salt / nutrients::SALT_PER_SODIUM));
+    let fat = share(values.fats, nutrients::KCAL_PER_G_FAT);
+    let groups = meals
+        .iter()
+        .flat_map(|m| food_group(m))
+        .collect::<HashSet<_>>()
+        .len();
+
+    let fat_fraction = if fat < FAT_SHARE.0 {
+        linear(fat, FAT_SHARE_ZERO.0, FAT_SHARE.0)
+    } else {
+        linear(fat, FAT_SHARE_ZERO.1, FAT_SHARE.1)
+    };
+    Some(DietQuality {
+        components: vec![
+            component(
+                "Fiber",
+                20.0,
+                linear(fiber, 0.0, FIBER_DENSITY),
+                format!("{:.1} g / 1000 kcal", fiber),
+            ),
+            component(
+                "Protein",
+                20.0,
+                linear(protein, 0.0, PROTEIN_SHARE),
+                format!("{:.0}% of energy", protein),
+            ),
+            component(
+                "Sugar",
+                15.0,
+                linear(sugar, SUGAR_SHARE.1, SUGAR_SHARE.0),
+                format!("{:.0}% of energy", sugar),
+            ),
+            component(
+                "Sodium",
+                15.0,
+                linear(sodium, SODIUM_DENSITY.1, SODIUM_DENSITY.0),
+                format!("{:.2} g / 1000 kcal", sodium),
+            ),
+            component(
+                "Fat balance",
+                15.0,
+                fat_fraction,
+                format!("{:.0}% of energy", fat),
+            ),
+            component(
+                "Variety",
+                15.0,
+                linear(groups as f32, 0.0, FOOD_GROUPS as f32),
+                format!("{} food groups", groups),
+            ),
+        ],
+    })
+}
+
+/// Average of the daily scores of the week containing `date`, None if nothing was logged
+pub(crate) fn score_week(meals: &[Meal], date: NaiveDate) -> Option<DietQuality> {
+    let (start, end) = ReportPeriod::Week.range(date);
+    let week: Vec<Meal> = meals
+        .iter()
+        .filter(|m| m.date >= start && m.date <= end)
+        .cloned()
+        .collect();
+    let days: Vec<DietQuality> = DailyMealCombo::group_by_day(&week)
+        .iter()
+        .filter_map(|(_, combo)| score_day(&combo.meals()))
+        .collect();
+    let first = days.first()?;
+    let components = first
+        .components
+        .iter()
+        .enumerate()
+        .map(|(i, c)| QualityComponent {
+            name: c.name,
+            score: days.iter().map(|d| d.components[i].score).sum::<f32>() / days.len() as f32,
+            max: c.max,
+            detail: format!("average of {} days", days.len()),
+        })
+        .collect();
+    Some(DietQuality { components })
+}
+
+/// Bootstrap color of a score
+pub(crate) fn score_class(score: f32) -> &'static str {
+    if score >= 70.0 {
+        "bg-success"
+    } else if score >= 50.0 {
+        "bg-warning"
+    } else {
+        "bg-danger"
+    }
+}
+
+impl DietQuality {
+    pub fn total(&self) -> f32 {
+        self.components.iter().map(|c| c.score).sum()
+    }
+
+    pub fn total_rounded(&self) -> String {
+        format!("{:.0}", self.total())
+    }
+
+    pub fn class(&self) -> &'static str {
+        score_class(self.total())
+    }
+
+    /// Short breakdown for a tooltip
+    pub fn summary(&self) -> String {
+        self.components
+            .iter()
+            .map(|c| format!("{}: {:.0}/{:.0} ({})", c.name, c.score, c.max, c.detail))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::food::StoredFood;
+    use crate::models::meal::{MealContent, MealType};
+    use crate::usda::search::{Food, Nutrient};
+    use uuid::Uuid;
+
+    fn meal(date: NaiveDate, foods: Vec<Food>) -> Meal {
+        Meal {
+            contents: foods
+                .into_iter()
+                .map(|product| MealContent::new(StoredFood::Usda(product), 100.0))
+                .collect(),
+            id: Uuid::new_v4(),
+            username: "12345".to_string(),
+            date,
+            meal_type: MealType::Lunch,
+        }
+    }
+
+    fn food(name: &str, nutrients: &[(i32, f32, &str)]) -> Food {
+        Food::new(
+            1,
+            name,
+            nutrients
+                .iter()
+                .map(|(id, amount, unit)| Nutrient::new(*id, "", *amount, *unit))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_linear() {
+        assert_eq!(linear(7.0, 0.0, 14.0), 0.5);
+        assert_eq!(linear(30.0, 25.0, 10.0), 0.0);
+        assert_eq!(linear(5.0, 25.0, 10.0), 1.0);
+    }
+
+    #[test]
+    fn test_score_day() {
+        let day = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        // 1000 kcal with 14 g fiber, 20% protein, 30% fat, no sugar and sodium
+        let balanced = food(
+            "Balanced",
+            &[
+                (nutrients::ENERGY, 1000.0, "KCAL"),
+                (nutrients::FIBER_TOTAL_DIETARY, 14.0, "G"),
+                (nutrients::PROTEIN, 50.0, "G"),
+                (nutrients::TOTAL_FAT, 33.3, "G"),
+            ],
+        );
+        let meals = [meal(day, vec![balanced])];
+        let quality = score_day(&meals.iter().collect::<Vec<_>>()).unwrap();
+        // everything but variety is perfect
+        assert!((quality.total() - 88.0).abs() < 0.1);
+        assert_eq!(quality.class(), "bg-success");
+
+        // pure sugar
+        let candy = food(
+            "Candy",
+            &[
+                (nutrients::ENERGY, 400.0, "KCAL"),
+                (nutrients::SUGARS_TOTAL, 100.0, "G"),
+            ],
+        );
+        let meals = [meal(day, vec![candy])];
+        let quality = score_day(&meals.iter().collect::<Vec<_>>()).unwrap();
+        // only the sodium limit is met
+        assert!((quality.total() - 18.0).abs() < 0.1);
+
+        assert!(score_day(&[]).is_none());
+        assert!(score_week(&meals, day + chrono::Duration::days(7)).is_none());
+        assert!((score_week(&meals, day).unwrap().total() - 18.0).abs() < 0.1);
+    }
+}
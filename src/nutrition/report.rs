@@ -0,0 +1,330 @@
+use crate::models::goals::{GoalStatus, NutritionGoals, GOAL_NUTRIENTS};
+use crate::models::meal::{DailyMealCombo, Meal};
+use crate::nutrition::contribution::{contributions, NutrientContribution};
+use crate::usda::search::NutrientValues;
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use std::collections::BTreeMap;
+
+// number of foods listed per nutrient
+const TOP_FOODS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ReportPeriod {
+    Week,
+    Month,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct DaySummary {
+    pub(crate) date: NaiveDate,
+    pub(crate) values: NutrientValues,
+    // false if no meal was logged that day
+    pub(crate) logged: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Adherence {
+    pub(crate) name: String,
+    pub(crate) days_on_track: usize,
+    pub(crate) days_logged: usize,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct NutritionReport {
+    pub(crate) period: ReportPeriod,
+    pub(crate) start: NaiveDate,
+    pub(crate) end: NaiveDate,
+    pub(crate) days: Vec<DaySummary>,
+    pub(crate) average: NutrientValues,
+    pub(crate) min_day: Option<DaySummary>,
+    pub(crate) max_day: Option<DaySummary>,
+    pub(crate) adherence: Vec<Adherence>,
+    // largest contributors to every nutrient
+    pub(crate) top_foods: Vec<NutrientContribution>,
+}
+
+impl ReportPeriod {
+    pub(crate) fn parse(s: &str) -> Option<ReportPeriod> {
+        match s {
+            "week" => Some(ReportPeriod::Week),
+            "month" => Some(ReportPeriod::Month),
+            _ => None,
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            ReportPeriod::Week => "week",
+            ReportPeriod::Month => "month",
+        }
+    }
+
+    /// First and last day of the week (starting monday) or month containing `date`
+    pub(crate) fn range(&self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            ReportPeriod::Week => {
+                let start = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+                (start, start + Duration::days(6))
+            }
+            ReportPeriod::Month => {
+                let start = date.with_day(1).unwrap();
+                let end = start + Months::new(1) - Duration::days(1);
+                (start, end)
+            }
+        }
+    }
+
+    pub(crate) fn previous(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            ReportPeriod::Week => start - Duration::days(7),
+            ReportPeriod::Month => start - Months::new(1),
+        }
+    }
+
+    pub(crate) fn next(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            ReportPeriod::Week => start + Duration::days(7),
+            ReportPeriod::Month => start + Months::new(1),
+        }
+    }
+}
+
+/// Aggregates the meals of the period containing `date`, meals outside of it are ignored
+pub(crate) fn build_report(
+    meals: &[Meal],
+    goals: &NutritionGoals,
+    period: ReportPeriod,
+    date: NaiveDate,
+) -> NutritionReport {
+    let (start, end) = period.range(date);
+    let meals: Vec<Meal> = meals
+        .iter()
+        .filter(|m| m.date >= start && m.date <= end)
+        .cloned()
+        .collect();
+    let combos: BTreeMap<NaiveDate, DailyMealCombo> =
+        DailyMealCombo::group_by_day(&meals).into_iter().collect();
+
+    let days: Vec<DaySummary> = start
+        .iter_days()
+        .take_while(|d| *d <= end)
+        .map(|date| match combos.get(&date) {
+            Some(combo) => DaySummary {
+                date,
+                values: combo.get_macros(),
+                logged: true,
+            },
+            None => DaySummary {
+                date,
+                values: NutrientValues::default(),
+                logged: false,
+            },
+        })
+        .collect();
+    let logged: Vec<&DaySummary> = days.iter().filter(|d| d.logged).collect();
+
+    let average = if logged.is_empty() {
+        NutrientValues::default()
+    } else {
+        logged
+            .iter()
+            .fold(NutrientValues::default(), |sum, d| sum + d.values.clone())
+            * (1.0 / logged.len() as f32)
+    };
+    let by_energy = |a: &&&DaySummary, b: &&&DaySummary| a.values.energy.total_cmp(&b.values.energy);
+    let min_day = logged.iter().min_by(by_energy).map(|d| (*d).clone());
+    let max_day = logged.iter().max_by(by_energy).map(|d| (*d).clone());
+
+    let adherence = GOAL_NUTRIENTS
+        .iter()
+        .filter(|(name, ..)| goals.resolved(name).is_some())
+        .map(|(name, ..)| Adherence {
+            name: name.to_string(),
+            days_on_track: logged
+                .iter()
+                .filter(|d| {
+                    goals
+                        .progress(d.values.clone())
+                        .iter()
+                        .any(|p| p.name == *name && p.status == GoalStatus::OnTrack)
+                })
+                .count(),
+            days_logged: logged.len(),
+        })
+        .collect();
+
+    NutritionReport {
+        period,
+        start,
+        end,
+        average,
+        min_day,
+        max_day,
+        adherence,
+        top_foods: contributions(&meals, start, end)
+            .into_iter()
+            .map(|c| c.top(TOP_FOODS))
+            .collect(),
+        days,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CalendarDay {
+    pub(crate) date: NaiveDate,
+    // false for the days of the neighbouring months filling the first and last week
+    pub(crate) in_month: bool,
+    // None if nothing was logged
+    pub(crate) energy: Option<f32>,
+    // adherence to the energy goal, None without a goal
+    pub(crate) status: Option<GoalStatus>,
+}
+
+/// First and last day of the calendar of the month containing `date`, whole weeks from monday
+pub(crate) fn calendar_range(date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let (start, end) = ReportPeriod::Month.range(date);
+    (ReportPeriod::Week.range(start).0, ReportPeriod::Week.range(end).1)
+}
+
+/// Weeks of the month containing `date` with the energy of every logged day
+pub(crate) fn calendar_month(meals: &[Meal], goals: &NutritionGoals, date: NaiveDate) -> Vec<Vec<CalendarDay>> {
+    let (month_start, month_end) = ReportPeriod::Month.range(date);
+    let (start, end) = calendar_range(date);
+    let combos: BTreeMap<NaiveDate, DailyMealCombo> = DailyMealCombo::group_by_day(meals)
+        .into_iter()
+        .filter(|(_, combo)| combo.has_logged())
+        .collect();
+    let days: Vec<CalendarDay> = start
+        .iter_days()
+        .take_while(|d| *d <= end)
+        .map(|date| {
+            let values = combos.get(&date).map(|c| c.get_macros());
+            CalendarDay {
+                date,
+                in_month: date >= month_start && date <= month_end,
+                energy: values.as_ref().map(|v| v.energy),
+                status: values.and_then(|v| {
+                    goals
+                        .progress(v)
+                        .into_iter()
+                        .find(|p| p.name == "energy")
+                        .map(|p| p.status)
+                }),
+            }
+        })
+        .collect();
+    days.chunks(7).map(|week| week.to_vec()).collect()
+}
+
+impl CalendarDay {
+    pub fn day(&self) -> u32 {
+        self.date.day()
+    }
+
+    pub fn energy_text(&self) -> String {
+        self.energy.map(|e| format!("{:.0} kcal", e)).unwrap_or_default()
+    }
+
+    pub fn class(&self) -> &'static str {
+        match self.status {
+            Some(GoalStatus::OnTrack) => "table-success",
+            Some(GoalStatus::Under) => "table-info",
+            Some(GoalStatus::Over) => "table-danger",
+            None => "",
+        }
+    }
+}
+
+impl NutritionReport {
+    pub fn days_logged(&self) -> usize {
+        self.days.iter().filter(|d| d.logged).count()
+    }
+}
+
+impl DaySummary {
+    pub fn energy_text(&self) -> String {
+        format!("{:.0}", self.values.energy)
+    }
+}
+
+impl Adherence {
+    pub fn percent(&self) -> u32 {
+        if self.days_logged == 0 {
+            return 0;
+        }
+        (self.days_on_track as f32 / self.days_logged as f32 * 100.0).round() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::food::FoodItem;
+    use crate::models::meal::{MealContent, MealType};
+    use crate::usda::nutrients;
+    use crate::models::food::StoredFood;
+    use crate::usda::search::{Food, Nutrient};
+    use uuid::Uuid;
+
+    fn meal(date: NaiveDate, food: &Food, quantity: f32) -> Meal {
+        Meal {
+            contents: vec![MealContent::new(StoredFood::Usda(food.clone()), quantity)],
+            id: Uuid::new_v4(),
+            username: "12345".to_string(),
+            date,
+            meal_type: MealType::Lunch,
+        }
+    }
+
+    #[test]
+    fn test_period_range() {
+        let date = NaiveDate::from_ymd_opt(2024, 2, 14).unwrap();
+        let (start, end) = ReportPeriod::Week.range(date);
+        assert_eq!(start, NaiveDate::from_ymd_opt(2024, 2, 12).unwrap());
+        assert_eq!(end, NaiveDate::from_ymd_opt(2024, 2, 18).unwrap());
+        let (start, end) = ReportPeriod::Month.range(date);
+        assert_eq!(start, NaiveDate::from_ymd_opt(2024, 2, 1).unwrap());
+        assert_eq!(end, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+        assert_eq!(ReportPeriod::Month.previous(start), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+    }
+
+    #[test]
+    fn test_calendar_month() {
+        let oats = Food::new(1, "Oats", vec![Nutrient::new(nutrients::ENERGY, "Energy", 400.0, "KCAL")]);
+        let day = NaiveDate::from_ymd_opt(2024, 2, 14).unwrap();
+        let goals = NutritionGoals {
+            energy: Some(2000.0),
+            ..NutritionGoals::default()
+        };
+        let weeks = calendar_month(&[meal(day, &oats, 500.0)], &goals, day);
+        // february 2024 starts on a thursday and ends on a thursday
+        assert_eq!(weeks.len(), 5);
+        assert_eq!(weeks[0][0].date, NaiveDate::from_ymd_opt(2024, 1, 29).unwrap());
+        assert!(!weeks[0][0].in_month);
+        let logged = weeks.iter().flatten().find(|d| d.date == day).unwrap();
+        assert_eq!(logged.energy, Some(2000.0));
+        assert_eq!(logged.status, Some(GoalStatus::OnTrack));
+        assert_eq!(weeks.iter().flatten().filter(|d| d.energy.is_some()).count(), 1);
+    }
+
+    #[test]
+    fn test_report_aggregation() {
+        let oats = Food::new(1, "Oats", vec![Nutrient::new(nutrients::ENERGY, "Energy", 400.0, "KCAL")]);
+        let monday = NaiveDate::from_ymd_opt(2024, 2, 12).unwrap();
+        let meals = vec![
+            meal(monday, &oats, 100.0),
+            meal(monday + Duration::days(2), &oats, 300.0),
+            // next week, not part of the report
+            meal(monday + Duration::days(7), &oats, 1000.0),
+        ];
+        let goals = NutritionGoals::default();
+        let report = build_report(&meals, &goals, ReportPeriod::Week, monday);
+        assert_eq!(report.days.len(), 7);
+        assert_eq!(report.days_logged(), 2);
+        assert!(report.adherence.is_empty());
+        assert_eq!(report.min_day.unwrap().date, monday);
+        assert_eq!(report.max_day.unwrap().date, monday + Duration::days(2));
+        assert_eq!(report.top_foods[0].foods.len(), 1);
+        assert_eq!(report.top_foods[0].foods[0].food, oats.display_name());
+    }
+}
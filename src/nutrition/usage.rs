@@ -0,0 +1,143 @@
+use crate::models::food::{FoodItem, FoodKey, StoredFood};
+use crate::models::meal::{Meal, MealType};
+use chrono::NaiveDate;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+// days of history the quick-pick lists are built from
+pub(crate) const USAGE_WINDOW_DAYS: i64 = 90;
+// foods shown per quick-pick list
+pub(crate) const QUICK_PICK_SIZE: usize = 8;
+
+#[derive(Debug, Clone)]
+pub(crate) struct FoodUsage {
+    pub(crate) food: StoredFood,
+    pub(crate) uses: usize,
+    pub(crate) last_used: NaiveDate,
+    pub(crate) last_meal_type: MealType,
+    // grams added the last time, offered for adding it again
+    pub(crate) last_quantity: f32,
+}
+
+impl FoodUsage {
+    pub fn last_quantity_rounded(&self) -> String {
+        format!("{:.0}", self.last_quantity)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct QuickPicks {
+    pub(crate) recent: Vec<FoodUsage>,
+    pub(crate) frequent: Vec<FoodUsage>,
+}
+
+impl QuickPicks {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.recent.is_empty() && self.frequent.is_empty()
+    }
+
+    /// Title and foods of each list
+    pub(crate) fn lists(&self) -> [(&'static str, &[FoodUsage]); 2] {
+        [("Recent", &self.recent), ("Frequent", &self.frequent)]
+    }
+}
+
+/// How often and when each food was logged, optionally only in meals of one slot
+pub(crate) fn food_usage(meals: &[Meal], slot: Option<&MealType>) -> Vec<FoodUsage> {
+    let mut meals: Vec<&Meal> = meals
+        .iter()
+        .filter(|m| slot.is_none_or(|slot| m.meal_type == *slot))
+        .collect();
+    // oldest first, so later additions overwrite the last quantity
+    meals.sort_by(|a, b| a.date.cmp(&b.date).then(a.meal_type.cmp(&b.meal_type)));
+    let mut usage: HashMap<FoodKey, FoodUsage> = HashMap::new();
+    for meal in meals {
+        for content in meal.contents.iter() {
+            let entry = usage.entry(content.key()).or_insert(FoodUsage {
+                food: content.product.clone(),
+                uses: 0,
+                last_used: meal.date,
+                last_meal_type: meal.meal_type.clone(),
+                last_quantity: content.quantity,
+            });
+            entry.uses += 1;
+            entry.last_used = meal.date;
+            entry.last_meal_type = meal.meal_type.clone();
+            entry.last_quantity = content.quantity;
+        }
+    }
+    usage.into_values().collect()
+}
+
+/// The most recently and the most often logged foods
+pub(crate) fn quick_picks(meals: &[Meal], slot: Option<&MealType>) -> QuickPicks {
+    let usage = food_usage(meals, slot);
+    let mut recent = usage.clone();
+    recent.sort_by_key(|u| {
+        (
+            Reverse(u.last_used),
+            Reverse(u.last_meal_type.clone()),
+            u.food.key().to_string(),
+        )
+    });
+    recent.truncate(QUICK_PICK_SIZE);
+    let mut frequent = usage;
+    frequent.sort_by_key(|u| {
+        (
+            Reverse(u.uses),
+            Reverse(u.last_used),
+            u.food.key().to_string(),
+        )
+    });
+    frequent.truncate(QUICK_PICK_SIZE);
+    QuickPicks { recent, frequent }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::meal::MealContent;
+    use crate::usda::search::Food;
+    use uuid::Uuid;
+
+    fn meal(date: &str, meal_type: MealType, foods: &[(i32, f32)]) -> Meal {
+        Meal {
+            contents: foods
+                .iter()
+                .map(|(id, quantity)| {
+                    let food = Food::new(*id, format!("Food {}", id), vec![]);
+                    MealContent::new(StoredFood::Usda(food), *quantity)
+                })
+                .collect(),
+            id: Uuid::new_v4(),
+            username: "user".to_string(),
+            date: date.parse().unwrap(),
+            meal_type,
+        }
+    }
+
+    #[test]
+    fn test_quick_picks() {
+        let meals = vec![
+            meal("2024-05-01", MealType::Breakfast, &[(1, 50.0), (2, 200.0)]),
+            meal("2024-05-02", MealType::Breakfast, &[(1, 60.0)]),
+            meal("2024-05-02", MealType::Dinner, &[(3, 150.0)]),
+            meal("2024-05-03", MealType::Breakfast, &[(1, 40.0)]),
+        ];
+        let picks = quick_picks(&meals, None);
+        let ids = |usage: &[FoodUsage]| {
+            usage
+                .iter()
+                .filter_map(|u| u.food.fdc_id())
+                .collect::<Vec<i32>>()
+        };
+        assert_eq!(ids(&picks.recent), [1, 3, 2]);
+        assert_eq!(ids(&picks.frequent), [1, 3, 2]);
+        assert_eq!(picks.frequent[0].uses, 3);
+        assert_eq!(picks.frequent[0].last_quantity, 40.0);
+
+        let dinner = quick_picks(&meals, Some(&MealType::Dinner));
+        assert_eq!(ids(&dinner.recent), [3]);
+        assert!(quick_picks(&[], None).is_empty());
+    }
+}
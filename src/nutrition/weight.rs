@@ -0,0 +1,91 @@
+use chrono::NaiveDate;
+
+// Smoothing factor per day, weighs today's scale reading with 10%
+pub(crate) const TREND_SMOOTHING: f32 = 0.1;
+// kcal stored in one kg of body weight change
+pub(crate) const KCAL_PER_KG: f32 = 7700.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TrendPoint {
+    pub(crate) date: NaiveDate,
+    pub(crate) weight: f32,
+    pub(crate) trend: f32,
+}
+
+/// Exponentially smoothed weight trend, the readings have to be sorted by date.
+/// Gaps of several days apply the daily smoothing once per missing day.
+pub(crate) fn trend(readings: &[(NaiveDate, f32)]) -> Vec<TrendPoint> {
+    let mut points: Vec<TrendPoint> = Vec::with_capacity(readings.len());
+    for (date, weight) in readings.iter() {
+        let trend = match points.last() {
+            None => *weight,
+            Some(last) => {
+                let days = (*date - last.date).num_days().max(1) as i32;
+                let alpha = 1.0 - (1.0 - TREND_SMOOTHING).powi(days);
+                last.trend + alpha * (weight - last.trend)
+            }
+        };
+        points.push(TrendPoint {
+            date: *date,
+            weight: *weight,
+            trend,
+        });
+    }
+    points
+}
+
+/// Change of the trend in kg per week between the first and the last point
+pub(crate) fn weekly_rate(points: &[TrendPoint]) -> Option<f32> {
+    let (first, last) = (points.first()?, points.last()?);
+    let days = (last.date - first.date).num_days();
+    if days == 0 {
+        return None;
+    }
+    Some((last.trend - first.trend) / days as f32 * 7.0)
+}
+
+/// Energy expenditure from the average intake and the weekly weight change:
+/// whatever was eaten but did not end up on the scale was burned.
+pub(crate) fn estimate_tdee(average_intake_kcal: f32, weekly_rate_kg: f32) -> f32 {
+    average_intake_kcal - weekly_rate_kg * KCAL_PER_KG / 7.0
+}
+
+impl TrendPoint {
+    pub fn weight_text(&self) -> String {
+        format!("{:.1}", self.weight)
+    }
+
+    pub fn trend_text(&self) -> String {
+        format!("{:.1}", self.trend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    #[test]
+    fn test_trend_smoothing() {
+        let points = trend(&[(date(1), 80.0), (date(2), 81.0), (date(4), 80.0)]);
+        assert_eq!(points[0].trend, 80.0);
+        assert!((points[1].trend - 80.1).abs() < 0.0001);
+        // two days gap: alpha = 1 - 0.9^2 = 0.19
+        assert!((points[2].trend - (80.1 - 0.19 * 0.1)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_weekly_rate_and_tdee() {
+        let points = vec![
+            TrendPoint { date: date(1), weight: 80.0, trend: 80.0 },
+            TrendPoint { date: date(15), weight: 79.0, trend: 79.0 },
+        ];
+        assert_eq!(weekly_rate(&points), Some(-0.5));
+        assert_eq!(weekly_rate(&points[..1]), None);
+        // losing 0.5 kg per week on 2000 kcal means spending 2550 kcal
+        assert_eq!(estimate_tdee(2000.0, -0.5), 2550.0);
+    }
+}
@@ -1,41 +1,81 @@
 pub mod models;
+pub mod product;
 pub mod sdk;
 mod db_connection;
 
 //tests
 #[cfg(test)]
 mod tests {
-    use super::sdk::search_openff;
-    use super::models::{ OpenFoodFactsQuery};
+    use super::models::OpenFoodFactsQuery;
+    use super::sdk::{OffClient, OffConfig};
+    use crate::usda::client::tests::mock_routes;
+
+    const NUTELLA: &str = r#"{"count": 2, "page": 1, "products": [
+        {"code": "3017620422003", "product_name": "Nutella", "nutrition_grades": "e",
+         "nutriments": {"energy-kcal_100g": 539, "proteins_100g": "6,3"}},
+        {"code": "59032823", "product_name": "", "nutriments": {}}
+    ]}"#;
+
+    fn ok(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\n\r\n{}",
+            body
+        )
+    }
+
+    fn client(base_url: String) -> OffClient {
+        OffClient::new(OffConfig {
+            base_url,
+            ..OffConfig::default()
+        })
+    }
 
     #[tokio::test]
     async fn test_search_nutella() {
-        let query = OpenFoodFactsQuery {
-            search_query: "Nutella".to_string(),
-            tags: vec!["de".to_string()],
-        };
-        match search_openff(query).await {
-            Ok(products) => {
-                for product in products {
-                    println!("Product code: {:?}", product.code);
-                    println!("Product name: {:?}", product.product_name);
-                    println!("Nutrition grade: {:?}", product.nutrition_grades);
-                    println!("Nutrients: {:?}", product.nutriments);
-                    println!("Energy: {:?} ", product.nutriments.clone().unwrap().energy_kcal_100g, );
-                    println!("----------------------");
-                }
-            }
-            Err(e) => eprintln!("Error: {:?}", e),
-        }
+        let off = client(mock_routes(vec![("search_terms=Nutella", ok(NUTELLA))]).await);
+        let products = off.search_openff("Nutella").await.unwrap();
+        assert_eq!(products.len(), 2);
+        assert_eq!(products[0].code, "3017620422003");
+        assert_eq!(products[0].nutrition_grades.as_deref(), Some("e"));
+        let nutriments = products[0].nutriments.clone().unwrap();
+        assert_eq!(nutriments.energy_kcal_100g, Some(539.0));
+        assert_eq!(nutriments.proteins_100g, Some(6.3));
     }
+
     #[tokio::test]
     async fn test_search_pizza() {
+        let off = client(mock_routes(vec![("tag_0=pizzas", ok(r#"{"products": []}"#))]).await);
         let query = OpenFoodFactsQuery {
             search_query: "pizza".to_string(),
-            tags: vec!["pizza".to_string()],
+            tags: vec!["pizzas".to_string()],
         };
-        let result = search_openff(query).await;
-        assert!(result.is_ok());
+        let result = off.search_openff(query).await;
+        assert!(result.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_product_by_code() {
+        let nutella = r#"{"code": "3017620422003", "status": 1, "status_verbose": "product found",
+            "product": {"code": "3017620422003", "product_name": "Nutella",
+                "nutriments": {"energy-kcal_100g": 539}}}"#;
+        let unknown = r#"{"code": "1", "status": 0, "status_verbose": "product not found"}"#;
+        let off = client(
+            mock_routes(vec![
+                ("/api/v2/product/3017620422003.json", ok(nutella)),
+                ("/api/v2/product/1.json", ok(unknown)),
+            ])
+            .await,
+        );
+        let product = off.product("3017620422003").await.unwrap().unwrap();
+        assert_eq!(product.product_name.as_deref(), Some("Nutella"));
+        assert!(off.product("1").await.unwrap().is_none());
+        assert!(off.product("2").await.unwrap().is_none());
     }
 
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn test_search_error() {
+        let off = client(mock_routes(vec![]).await);
+        let err = off.search_openff("pizza").await.unwrap_err();
+        assert_eq!(err.to_string(), "Open Food Facts answered 404 Not Found");
+    }
+}
@@ -1,7 +1,84 @@
+// Search parameters and nutrition facts as named by the Open Food Facts API
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[derive(Debug, Clone, Default)]
+pub struct OpenFoodFactsQuery {
+    pub(crate) search_query: String,
+    /// Categories the products have to be in, e.g. "pizzas"
+    pub(crate) tags: Vec<String>,
+}
+
+impl From<&str> for OpenFoodFactsQuery {
+    fn from(search_query: &str) -> Self {
+        OpenFoodFactsQuery {
+            search_query: search_query.to_string(),
+            tags: vec![],
+        }
+    }
+}
+
+impl From<String> for OpenFoodFactsQuery {
+    fn from(search_query: String) -> Self {
+        OpenFoodFactsQuery {
+            search_query,
+            tags: vec![],
+        }
+    }
+}
+
+// OFF sends most values as numbers but some as strings like "12,5" or ""
+pub(crate) fn number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f32>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Number(f32),
+        Text(String),
+    }
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Number(n)) => Some(n),
+        Some(Value::Text(t)) => t.trim().replace(',', ".").parse().ok(),
+        None => None,
+    })
+}
+
+/// Nutrition facts per 100 g (or 100 ml), masses in g
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Nutriments {
+    #[serde(rename = "energy-kcal_100g", default, deserialize_with = "number")]
+    pub(crate) energy_kcal_100g: Option<f32>,
+    #[serde(rename = "energy-kj_100g", default, deserialize_with = "number")]
+    pub(crate) energy_kj_100g: Option<f32>,
+    #[serde(default, deserialize_with = "number")]
+    pub(crate) fat_100g: Option<f32>,
+    #[serde(rename = "saturated-fat_100g", default, deserialize_with = "number")]
+    pub(crate) saturated_fat_100g: Option<f32>,
+    #[serde(default, deserialize_with = "number")]
+    pub(crate) carbohydrates_100g: Option<f32>,
+    #[serde(default, deserialize_with = "number")]
+    pub(crate) sugars_100g: Option<f32>,
+    #[serde(default, deserialize_with = "number")]
+    pub(crate) fiber_100g: Option<f32>,
+    #[serde(default, deserialize_with = "number")]
+    pub(crate) proteins_100g: Option<f32>,
+    #[serde(default, deserialize_with = "number")]
+    pub(crate) salt_100g: Option<f32>,
+    #[serde(default, deserialize_with = "number")]
+    pub(crate) sodium_100g: Option<f32>,
+}
 
 #[cfg(test)]
-mod test_models{
+mod test_models {
     use super::*;
 
+    #[test]
+    fn test_nutriments_numbers_and_strings() {
+        let json = r#"{"energy-kcal_100g": 539, "fat_100g": "30,9", "sugars_100g": "",
+            "proteins_100g": 6.3, "salt_100g": null, "nova-group_100g": 4}"#;
+        let nutriments: Nutriments = serde_json::from_str(json).unwrap();
+        assert_eq!(nutriments.energy_kcal_100g, Some(539.0));
+        assert_eq!(nutriments.fat_100g, Some(30.9));
+        assert_eq!(nutriments.sugars_100g, None);
+        assert_eq!(nutriments.salt_100g, None);
+        assert_eq!(nutriments.fiber_100g, None);
+    }
 }
-
@@ -1,95 +1,138 @@
-use std::fmt::Debug;
-use redis::{Connection, RedisResult};
-use serde::{Deserialize, Serialize};
-use uuid::Uuid;
-use crate::db::connector::{default_fetch, default_fetch_all, default_save};
-use crate::models::meal::MealContent;
+use crate::models::food::{FoodItem, FoodSource};
 use crate::models::models::RedisORM;
+use crate::open_food_facts::models::{number, Nutriments};
+use crate::usda::barcode;
+use crate::usda::nutrients;
+use crate::usda::search::{Food, Nutrient, Portion};
+use redis::Connection;
+use serde::{Deserialize, Serialize};
+
+/// Data set of the foods converted from Open Food Facts products
+pub(crate) const OFF_DATA_SET: &str = "Open Food Facts";
 
-#[derive( Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Product {
+    /// EAN/UPC barcode
     pub(crate) code: String,
+    #[serde(default)]
     pub(crate) nutrition_grades: Option<String>,
+    #[serde(default)]
     pub(crate) product_name: Option<String>,
+    #[serde(default)]
+    pub(crate) brands: Option<String>,
+    #[serde(default)]
+    pub(crate) categories_tags_en: Vec<String>,
+    #[serde(default)]
+    pub(crate) serving_size: Option<String>,
+    #[serde(default, deserialize_with = "number")]
+    pub(crate) serving_quantity: Option<f32>,
+    #[serde(default)]
     pub(crate) nutriments: Option<Nutriments>,
+    #[serde(default)]
+    pub(crate) ingredients_text_en: Option<String>,
+    /// Ingredients in the language of the label
+    #[serde(default)]
+    pub(crate) ingredients_text: Option<String>,
+    /// Allergens declared on the label, e.g. "en:milk"
+    #[serde(default)]
+    pub(crate) allergens_tags: Vec<String>,
+    /// Allergens the product may contain traces of
+    #[serde(default)]
+    pub(crate) traces_tags: Vec<String>,
 }
 
+/// Negative id of a product listed among the FDC search results, FDC ids are always positive.
+/// Products are stored and keyed by their barcode, the id only tells them apart in the list.
+pub(crate) fn food_id(code: &str) -> i32 {
+    // FNV-1a, stable across runs unlike the std hasher
+    let hash = code
+        .bytes()
+        .fold(0x811c_9dc5_u32, |h, b| (h ^ b as u32).wrapping_mul(0x0100_0193));
+    -((hash & 0x7fff_ffff) as i32) - 1
+}
 
 impl Product {
-    pub(crate) fn search_local(con: &mut Connection, query: &str) -> Vec<Product> {
-        let product_name = query.to_lowercase();
-        let keys: Vec<String> = redis::Cmd::keys("product_name:*".to_string() + &product_name +"*").query(con).unwrap();
-        let mut products: Vec<Product> = vec![];
-        for key in keys.iter() {
-            let product_id =  default_fetch(con, key).unwrap();
-            let product = Product::fetch_from_uuid(con, &product_id.unwrap());
-
-            dbg!(product.clone());
-            if product.is_some() {
-                products.push(product.unwrap());
-            }
-        }
-        products
-    }
-    pub(crate) fn get_numerical_macros(&self) -> Nutriments {
-        let nutriments = self.nutriments.clone().unwrap();
-        let protein = nutriments.proteins_100g.unwrap_or(OpenFFValue::None);
-        let fat = nutriments.fat_100g.unwrap_or(OpenFFValue::None);
-        let carbs = nutriments.carbohydrates_100g.unwrap_or(OpenFFValue::None);
-        let kcal = nutriments.energy_kcal_100g.unwrap_or(OpenFFValue::None);
-        let fiber = nutriments.fiber_100g.unwrap_or(OpenFFValue::None);
-        let salt = nutriments.salt_100g.unwrap_or(OpenFFValue::None);
-        let sodium = nutriments.sodium_100g.unwrap_or(OpenFFValue::None);
-        let sugar = nutriments.sugars_100g.unwrap_or(OpenFFValue::None);
-
-        Nutriments{
-            carbohydrates_100g: Some(carbs),
-            sugars_100g: Some(sugar),
-            proteins_100g: Some(protein),
-            fat_100g: Some(fat),
-            energy_kcal_100g: Some(kcal),
-            fiber_100g: Some(fiber),
-            salt_100g: Some(salt),
-            sodium_100g: Some(sodium),
+    /// The product as a food that can be searched and added to meals, None without a name or
+    /// nutrition facts
+    pub(crate) fn into_food(self) -> Option<Food> {
+        let name = self.product_name.filter(|n| !n.trim().is_empty())?;
+        let n = self.nutriments?;
+        let energy = match (n.energy_kcal_100g, n.energy_kj_100g) {
+            (Some(kcal), _) => Some((nutrients::ENERGY, kcal, "KCAL")),
+            (None, Some(kj)) => Some((nutrients::ENERGY_KJ, kj, "kJ")),
+            (None, None) => None,
+        };
+        let sodium = n
+            .sodium_100g
+            .or(n.salt_100g.map(|salt| salt / nutrients::SALT_PER_SODIUM));
+        let grams = [
+            (nutrients::PROTEIN, "Protein", n.proteins_100g),
+            (nutrients::TOTAL_FAT, "Total lipid (fat)", n.fat_100g),
+            (nutrients::SATURATED_FAT, "Fatty acids, total saturated", n.saturated_fat_100g),
+            (
+                nutrients::CARBOHYDRATE_BY_DIFFERENCE,
+                "Carbohydrate, by difference",
+                n.carbohydrates_100g,
+            ),
+            (nutrients::SUGARS_TOTAL, "Sugars, total", n.sugars_100g),
+            (
+                nutrients::FIBER_TOTAL_DIETARY,
+                "Fiber, total dietary",
+                n.fiber_100g,
+            ),
+            (nutrients::SODIUM, "Sodium, Na", sodium),
+        ];
+        let nutrients = energy
+            .map(|(id, amount, unit)| Nutrient::new(id, "Energy", amount, unit))
+            .into_iter()
+            .chain(grams.into_iter().filter_map(|(id, name, amount)| {
+                Some(Nutrient::new(id, name, amount?, "G"))
+            }))
+            .collect();
+        let mut food = Food::new(food_id(&self.code), name, nutrients);
+        food.data_set = OFF_DATA_SET.to_string();
+        food.source = Some(FoodSource::OpenFoodFacts);
+        food.brand_owner = self.brands.filter(|b| !b.trim().is_empty());
+        food.gtin_upc = Some(self.code);
+        food.food_category = Some(self.categories_tags_en.join(", ")).filter(|c| !c.is_empty());
+        food.ingredients = self
+            .ingredients_text_en
+            .filter(|i| !i.trim().is_empty())
+            .or(self.ingredients_text)
+            .filter(|i| !i.trim().is_empty());
+        food.allergen_tags = self.allergens_tags;
+        food.allergen_tags.extend(self.traces_tags);
+        if let Some(grams) = self.serving_quantity.filter(|g| *g > 0.0) {
+            food.portions.push(Portion {
+                description: self.serving_size.unwrap_or("1 serving".to_string()),
+                gram_weight: grams,
+            });
         }
-    }
-}
-impl Debug for Product {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Product")
-            .field("product_name", &self.product_name)
-            .field("macros", &self.get_numerical_macros())
-            .finish()
+        // a product is complete, there is no detail record to fetch
+        food.detailed = true;
+        Some(food)
     }
 }
 
-impl RedisORM for Product{
-    fn save(&self, con: &mut Connection) -> RedisResult<()> {
-        default_save(con, "product", &self.code, &self)?;
-        let product_name= self.product_name.clone();
-        let val = &*product_name.clone().unwrap().to_lowercase();
-        if product_name.is_some(){
-            let result = redis::cmd("SET").arg("product_name:".to_string()+ val).arg(&self.code).query(con);
-            return result
-        }
-        return Ok(())
+impl RedisORM for Product {
+    // off_food:<barcode>
+    fn save(&self, con: &mut Connection) -> redis::RedisResult<()> {
+        barcode::record(con, &self.code, &self.key())?;
+        crate::db::connector::default_save(con, &Self::redis_type_name(), &self.redis_id(), self)
     }
 
-    fn example() -> Self where Self: Sized {
-        Product{
-            code: "".to_string(),
-            nutrition_grades: None,
-            product_name: None,
-            nutriments: None,
+    fn example() -> Self
+    where
+        Self: Sized,
+    {
+        Product {
+            code: "0".to_string(),
+            ..Default::default()
         }
     }
 
-    fn all(con: &mut Connection) -> Vec<Self> where Self: Sized {
-        default_fetch_all(con,"product")
-    }
-
     fn redis_type_name() -> String {
-        "product".to_string()
+        "off_food".to_string()
     }
 
     fn redis_id(&self) -> String {
@@ -97,4 +140,60 @@ impl RedisORM for Product{
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    #[test]
+    fn test_into_food() {
+        let json = r#"{"code": "3017620422003", "product_name": "Nutella", "brands": "Ferrero",
+            "nutrition_grades": "e", "categories_tags_en": ["Spreads", "Sweet spreads"],
+            "serving_size": "15 g", "serving_quantity": "15",
+            "nutriments": {"energy-kcal_100g": 539, "fat_100g": 30.9, "saturated-fat_100g": 10.6,
+                "carbohydrates_100g": 57.5, "sugars_100g": 56.3, "proteins_100g": 6.3, "salt_100g": 0.107},
+            "ingredients_text_en": "", "ingredients_text": "Sugar, palm oil, hazelnuts 13%",
+            "allergens_tags": ["en:milk", "en:nuts"], "traces_tags": ["en:gluten"]}"#;
+        let product: Product = serde_json::from_str(json).unwrap();
+        let food = product.into_food().unwrap();
+        assert_eq!(food.id, food_id("3017620422003"));
+        assert!(food.id < 0);
+        assert_eq!(food.data_set, OFF_DATA_SET);
+        assert_eq!(food.brand_owner.as_deref(), Some("Ferrero"));
+        assert_eq!(food.nutrient_values.energy, 539.0);
+        assert_eq!(food.nutrient_values.fats, 30.9);
+        assert!((food.nutrient_values.salt - 0.107).abs() < 0.001);
+        assert_eq!(
+            food.portions,
+            [Portion {
+                description: "15 g".to_string(),
+                gram_weight: 15.0
+            }]
+        );
+        assert!(food.detailed);
+        assert_eq!(food.gtin_upc.as_deref(), Some("3017620422003"));
+        assert_eq!(
+            food.ingredients.as_deref(),
+            Some("Sugar, palm oil, hazelnuts 13%")
+        );
+        assert_eq!(food.allergen_tags, ["en:milk", "en:nuts", "en:gluten"]);
+    }
+
+    #[test]
+    fn test_incomplete_products_are_skipped() {
+        let unnamed = Product {
+            code: "1".to_string(),
+            product_name: Some(" ".to_string()),
+            nutriments: Some(Nutriments::default()),
+            ..Default::default()
+        };
+        assert!(unnamed.into_food().is_none());
+        let no_facts = Product {
+            code: "2".to_string(),
+            product_name: Some("Water".to_string()),
+            ..Default::default()
+        };
+        assert!(no_facts.into_food().is_none());
+        assert_eq!(food_id("2"), food_id("2"));
+        assert_ne!(food_id("2"), food_id("3"));
+    }
+}
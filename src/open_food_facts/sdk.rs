@@ -1,89 +1,189 @@
-use redis::{Connection, RedisResult};
-use serde::{Deserialize, Serialize};
-use crate::db::connector::default_save_expire;
-use crate::models::models::{NutritionistSearchQuery, RedisORM};
-use crate::models::product::Product;
+// Client for the Open Food Facts API (https://openfoodfacts.github.io/openfoodfacts-server/api/)
+use crate::models::models::RedisORM;
+use crate::open_food_facts::models::OpenFoodFactsQuery;
+use crate::open_food_facts::product::{Product, OFF_DATA_SET};
+use crate::usda::search::{cache_aside, data_type_key, CachedSearch, Food};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
 
+const DEFAULT_BASE_URL: &str = "https://world.openfoodfacts.org";
+const FIELDS: &str =
+    "code,product_name,brands,nutrition_grades,categories_tags_en,serving_size,serving_quantity,nutriments,ingredients_text_en,ingredients_text,allergens_tags,traces_tags";
+// OFF asks every client to identify itself
+const USER_AGENT: &str = concat!("nutritionist/", env!("CARGO_PKG_VERSION"));
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
-pub struct SearchResult {
-    pub(crate) products: Vec<Product>,
-    #[serde(skip)]
-    pub(crate) query: NutritionistSearchQuery,
+#[derive(Debug, Clone)]
+pub(crate) struct OffConfig {
+    pub(crate) base_url: String,
+    pub(crate) timeout: Duration,
+    pub(crate) page_size: u32,
 }
 
-
-impl RedisORM for  SearchResult{
-    fn save(&self, con: &mut Connection) -> RedisResult<()> {
-        println!("Saving search result {:?}", self.query.query);
-        default_save_expire(con, "ffsearch", &self.query.query, self, 60 * 60 * 24 * 7)
+impl Default for OffConfig {
+    fn default() -> Self {
+        OffConfig {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            timeout: Duration::from_secs(10),
+            page_size: 50,
+        }
     }
+}
 
-    fn example() -> Self where Self: Sized {
-        SearchResult {
-            products: vec![],
-            query: NutritionistSearchQuery {
-                query: "".to_string(),
-            }
+impl OffConfig {
+    /// Configured by OFF_BASE_URL, OFF_TIMEOUT_SECS and OFF_PAGE_SIZE
+    pub(crate) fn from_env() -> OffConfig {
+        let default = OffConfig::default();
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        OffConfig {
+            base_url: var("OFF_BASE_URL")
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or(default.base_url),
+            timeout: var("OFF_TIMEOUT_SECS")
+                .and_then(|t| t.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(default.timeout),
+            page_size: var("OFF_PAGE_SIZE")
+                .and_then(|p| p.parse().ok())
+                .unwrap_or(default.page_size)
+                .clamp(1, 100),
         }
     }
+}
 
+#[derive(Debug)]
+pub(crate) enum OffError {
+    Status(StatusCode),
+    Http(reqwest::Error),
+}
 
-    fn redis_type_name() -> String {
-        "ffsearch".to_string()
+impl Display for OffError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OffError::Status(status) => write!(f, "Open Food Facts answered {}", status),
+            OffError::Http(e) => write!(f, "Open Food Facts is not reachable: {}", e),
+        }
     }
+}
+
+impl std::error::Error for OffError {}
 
-    fn redis_id(&self) -> String {
-        self.query.query.clone()
+impl From<reqwest::Error> for OffError {
+    fn from(e: reqwest::Error) -> Self {
+        OffError::Http(e)
     }
 }
-pub async fn cached_search(search: impl Into<OpenFoodFactsQuery>) -> Result<Vec<Product>, reqwest::Error> {
-    let search = search.into();
-    let mut con = crate::db::connector::get_connection().expect("Could not connect to redis,maybe redis is not running");
-    let cache = SearchResult::fetch_from_uuid(&mut con, &search.search_query);
 
-    if cache.is_some() {
-        return Ok(cache.unwrap().products)
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct SearchResult {
+    #[serde(default)]
+    pub(crate) products: Vec<Product>,
+}
+
+// answer of the product lookup, status is 0 for unknown barcodes
+#[derive(Debug, Deserialize)]
+struct ProductResult {
+    #[serde(default)]
+    status: i32,
+    #[serde(default)]
+    product: Option<Product>,
+}
+
+/// Cheap to clone, the connection pool and configuration are shared
+#[derive(Clone)]
+pub(crate) struct OffClient {
+    http: reqwest::Client,
+    config: Arc<OffConfig>,
+}
+
+impl OffClient {
+    pub(crate) fn new(config: OffConfig) -> OffClient {
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .user_agent(USER_AGENT)
+            .build()
+            .expect("valid Open Food Facts http client");
+        OffClient {
+            http,
+            config: Arc::new(config),
+        }
     }
-    let result =  search_openff(search.clone()).await?;
-    // Shit Code, DONT do it like that
-    let bind = result.clone();
-    for product in bind.iter() {
-        product.save(&mut con).expect("Could not save product");
+
+    pub(crate) fn from_env() -> OffClient {
+        OffClient::new(OffConfig::from_env())
     }
-    let search_result = SearchResult {
-        products: result.clone(),
-        query: NutritionistSearchQuery {
-            query: search.search_query,
+
+    /// Products matching the search terms, tags restrict them to categories
+    pub(crate) async fn search_openff(
+        &self,
+        search: impl Into<OpenFoodFactsQuery>,
+    ) -> Result<Vec<Product>, OffError> {
+        let search = search.into();
+        let url = format!("{}/cgi/search.pl", self.config.base_url);
+        let page_size = self.config.page_size.to_string();
+        let mut params = vec![
+            ("search_terms".to_string(), search.search_query.clone()),
+            ("search_simple".to_string(), "1".to_string()),
+            ("action".to_string(), "process".to_string()),
+            ("json".to_string(), "1".to_string()),
+            ("page_size".to_string(), page_size),
+            ("fields".to_string(), FIELDS.to_string()),
+        ];
+        for (i, tag) in search.tags.iter().enumerate() {
+            params.push((format!("tagtype_{}", i), "categories".to_string()));
+            params.push((format!("tag_contains_{}", i), "contains".to_string()));
+            params.push((format!("tag_{}", i), tag.clone()));
         }
-    };
-    search_result.save(&mut con).expect("Could not save search result");
-    return Ok(result)
+        let response = self.http.get(&url).query(&params).send().await?;
+        if !response.status().is_success() {
+            return Err(OffError::Status(response.status()));
+        }
+        let result: SearchResult = response.json().await?;
+        Ok(result.products)
+    }
+
+    /// The product with the barcode, None if OFF does not know it
+    pub(crate) async fn product(&self, code: &str) -> Result<Option<Product>, OffError> {
+        let url = format!("{}/api/v2/product/{}.json", self.config.base_url, code);
+        let response = self.http.get(&url).query(&[("fields", FIELDS)]).send().await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => return Ok(None),
+            status if !status.is_success() => return Err(OffError::Status(status)),
+            _ => {}
+        }
+        let result: ProductResult = response.json().await?;
+        Ok(result.product.filter(|_| result.status == 1))
+    }
+
+    /// Products with a name and nutrition facts as foods, unranked. The products are stored so
+    /// the foods can be added to meals.
+    pub(crate) async fn search_foods(&self, search_term: &str) -> Result<Vec<Food>, OffError> {
+        let products: Vec<(Product, Food)> = self
+            .search_openff(search_term)
+            .await?
+            .into_iter()
+            .filter_map(|p| Some((p.clone(), p.into_food()?)))
+            .collect();
+        store_products(products.iter().map(|(product, _)| product));
+        Ok(products.into_iter().map(|(_, food)| food).collect())
+    }
 }
 
+// without redis the search still answers, its foods just can not be added to meals
+fn store_products<'a>(mut products: impl Iterator<Item = &'a Product>) {
+    let stored = crate::db::connector::get_connection()
+        .and_then(|mut con| products.try_for_each(|p| p.save(&mut con)));
+    if let Err(e) = stored {
+        println!("Storing the Open Food Facts products failed: {}", e);
+    }
+}
 
-pub async fn search_openff(search: impl Into<OpenFoodFactsQuery>) -> Result<Vec<Product>, reqwest::Error> {
-    let url = "https://world.openfoodfacts.org/cgi/search.pl";
-    let search = search.into();
-
-    let params = [
-        ("search_terms", &*search.search_query),
-        ("search_simple", "1"),
-        // add tags to the search
-        ("action", "process"),
-        ("json", "1"),
-        ("fields", "code,nutrition_grades,categories_tags_en,product_name,nutriments"),
-    ];
-    let client = reqwest::Client::new();
-    let response = dbg!(client.get(url).query(&params)).send().await?;
-    let mut search_result: SearchResult = response.json().await?;
-    search_result.query = NutritionistSearchQuery {
-        query: search.search_query,
-    };
-    let mut con = crate::db::connector::get_connection().expect("Could not connect to redis,maybe redis is not running");
-    search_result.save(&mut con).expect("Could not save search result");
-    let prods = search_result.products.into_iter().filter(|x| {
-        x.product_name.clone().is_some() && x.nutriments.is_some() && x.product_name.clone().unwrap() != ""
-    }).collect();
-    Ok(prods)
+/// The cached or live Open Food Facts search, cached like a USDA data type
+pub(crate) async fn cached_search(off: &OffClient, query: &str) -> Result<CachedSearch, OffError> {
+    let mut con = crate::db::connector::get_connection().unwrap();
+    let key = data_type_key(OFF_DATA_SET, query);
+    let result = cache_aside(&mut con, key, off.search_foods(query)).await?;
+    Ok(result.ranked(&mut con, query))
 }
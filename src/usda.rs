@@ -1 +1,8 @@
+pub mod barcode;
+pub mod client;
+pub mod details;
+pub mod filter;
+pub mod import;
+pub mod index;
+pub mod nutrients;
 pub mod search;
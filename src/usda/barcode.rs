@@ -0,0 +1,143 @@
+// Lookup of packaged foods by their EAN/UPC barcode: the stored foods first, then the
+// Open Food Facts product and the FDC branded food with the same GTIN
+use crate::models::food::{FoodKey, FoodSource};
+use crate::models::models::RedisORM;
+use crate::open_food_facts::sdk::{OffClient, OffError};
+use crate::usda::client::{UsdaClient, UsdaError};
+use crate::usda::search::{CacheStatus, Food};
+use redis::Connection;
+use std::fmt::{Display, Formatter};
+
+/// Source and normalized barcode -> key of the stored food, e.g. "usda:41570054161" ->
+/// "usda:2099145", so a GTIN known to OFF and FDC resolves to the food of each source
+pub(crate) const BARCODES_KEY: &str = "food_barcodes";
+
+/// The barcode without leading zeros, so UPC-A and its EAN-13 form match, None if it is no GTIN
+pub(crate) fn normalize(code: &str) -> Option<String> {
+    let code = code.trim();
+    if !(8..=14).contains(&code.len()) || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(code.trim_start_matches('0').to_string())
+}
+
+/// The command remembering the barcode of a food, None if the code is no GTIN
+pub(crate) fn record_cmd(code: &str, key: &FoodKey) -> Option<redis::Cmd> {
+    let code = normalize(code)?;
+    let mut cmd = redis::cmd("HSET");
+    cmd.arg(BARCODES_KEY)
+        .arg(format!("{}:{}", key.source.prefix(), code))
+        .arg(key.to_string());
+    Some(cmd)
+}
+
+/// Remembers the barcode of a food so it is found without asking OFF or FDC again
+pub(crate) fn record(con: &mut Connection, code: &str, key: &FoodKey) -> redis::RedisResult<()> {
+    match record_cmd(code, key) {
+        Some(cmd) => cmd.query(con),
+        None => Ok(()),
+    }
+}
+
+/// The stored food with the barcode, the Open Food Facts product before the FDC food
+pub(crate) fn stored_food(con: &mut Connection, code: &str) -> Option<Food> {
+    let code = normalize(code)?;
+    [FoodSource::OpenFoodFacts, FoodSource::Usda]
+        .into_iter()
+        .find_map(|source| {
+            let key: Option<String> = redis::cmd("HGET")
+                .arg(BARCODES_KEY)
+                .arg(format!("{}:{}", source.prefix(), code))
+                .query(con)
+                .ok()?;
+            key?.parse::<FoodKey>().ok()?.stored(con)?.food()
+        })
+}
+
+#[derive(Debug)]
+pub(crate) enum BarcodeError {
+    Invalid,
+    NotFound,
+    Off(OffError),
+    Usda(UsdaError),
+    Redis(redis::RedisError),
+}
+
+impl Display for BarcodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BarcodeError::Invalid => write!(f, "A barcode has 8 to 14 digits"),
+            BarcodeError::NotFound => write!(f, "No product with this barcode was found"),
+            BarcodeError::Off(e) => write!(f, "{}", e),
+            BarcodeError::Usda(e) => write!(f, "{}", e),
+            BarcodeError::Redis(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for BarcodeError {}
+
+// the FDC branded food with the GTIN, the full record if FDC sends it
+async fn fdc_food(usda: &UsdaClient, code: &str) -> Result<Option<Food>, UsdaError> {
+    let result = usda.search_page(code, "Branded", 1).await?;
+    let Some(found) = result
+        .foods
+        .into_iter()
+        .find(|f| f.gtin_upc.as_deref().and_then(normalize) == normalize(code))
+    else {
+        return Ok(None);
+    };
+    match usda.get_food(found.id).await {
+        Ok(food) => Ok(Some(food)),
+        Err(_) => Ok(Some(found.generate_nutrient_values())),
+    }
+}
+
+/// The food with the barcode and where it came from, stored for the next lookup
+pub(crate) async fn lookup(
+    usda: &UsdaClient,
+    off: &OffClient,
+    code: &str,
+) -> Result<(Food, CacheStatus), BarcodeError> {
+    let code = code.trim();
+    normalize(code).ok_or(BarcodeError::Invalid)?;
+    let mut con = crate::db::connector::get_connection().map_err(BarcodeError::Redis)?;
+    if let Some(food) = stored_food(&mut con, code) {
+        return Ok((food, CacheStatus::Local));
+    }
+    // OFF knows most packaged foods, an unreachable OFF only matters if FDC misses it too
+    let off_error = match off.product(code).await {
+        Ok(product) => {
+            if let Some(product) = product {
+                if let Some(food) = product.clone().into_food() {
+                    product.save(&mut con).map_err(BarcodeError::Redis)?;
+                    return Ok((food, CacheStatus::Miss));
+                }
+            }
+            None
+        }
+        Err(e) => Some(e),
+    };
+    match fdc_food(usda, code).await {
+        Ok(Some(food)) => {
+            food.save(&mut con).map_err(BarcodeError::Redis)?;
+            Ok((food, CacheStatus::Miss))
+        }
+        Ok(None) => Err(off_error.map_or(BarcodeError::NotFound, BarcodeError::Off)),
+        Err(e) => Err(off_error.map_or(BarcodeError::Usda(e), BarcodeError::Off)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("041570054161"), Some("41570054161".to_string()));
+        assert_eq!(normalize("0041570054161"), normalize("041570054161"));
+        assert_eq!(normalize(" 3017620422003 "), Some("3017620422003".to_string()));
+        assert_eq!(normalize("12345"), None);
+        assert_eq!(normalize("30176204x2003"), None);
+    }
+}
@@ -0,0 +1,553 @@
+// Client for the FoodData Central API (https://fdc.nal.usda.gov/api-guide.html)
+use crate::models::models::RedisORM;
+use crate::usda::details::FoodDetails;
+use crate::usda::search::{Food, SearchResult};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+const DEFAULT_BASE_URL: &str = "https://api.nal.usda.gov/fdc/v1";
+// the FDC API accepts at most 200 foods per page
+const MAX_PAGE_SIZE: u32 = 200;
+// deeper pages are rarely relevant and every page counts against the rate limit
+const MAX_PAGES: u32 = 10;
+// the /foods endpoint takes at most 20 ids
+const MAX_BATCH: usize = 20;
+// a rate limited request is retried this often if the server asks for a short wait
+const MAX_RETRIES: u32 = 2;
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub(crate) struct UsdaConfig {
+    pub(crate) api_key: String,
+    pub(crate) base_url: String,
+    pub(crate) timeout: Duration,
+    pub(crate) page_size: u32,
+    /// How many pages are fetched per data type
+    pub(crate) pages: u32,
+    pub(crate) data_types: Vec<String>,
+}
+
+impl Default for UsdaConfig {
+    fn default() -> Self {
+        UsdaConfig {
+            api_key: "DEMO_KEY".to_string(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            timeout: Duration::from_secs(10),
+            page_size: 100,
+            pages: 1,
+            data_types: ["Foundation", "SR Legacy", "Survey (FNDDS)", "Branded"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+impl UsdaConfig {
+    /// Configured by FDC_API_KEY, FDC_BASE_URL, FDC_TIMEOUT_SECS, FDC_PAGE_SIZE, FDC_PAGES and
+    /// FDC_DATA_TYPES
+    pub(crate) fn from_env() -> UsdaConfig {
+        UsdaConfig::from_vars(|name| std::env::var(name).ok())
+    }
+
+    // the settings of from_env, looked up by name
+    fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> UsdaConfig {
+        let default = UsdaConfig::default();
+        let var = |name: &str| lookup(name).filter(|v| !v.is_empty());
+        UsdaConfig {
+            api_key: var("FDC_API_KEY").unwrap_or(default.api_key),
+            base_url: var("FDC_BASE_URL")
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or(default.base_url),
+            timeout: var("FDC_TIMEOUT_SECS")
+                .and_then(|t| t.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(default.timeout),
+            page_size: var("FDC_PAGE_SIZE")
+                .and_then(|p| p.parse().ok())
+                .unwrap_or(default.page_size)
+                .clamp(1, MAX_PAGE_SIZE),
+            pages: var("FDC_PAGES")
+                .and_then(|p| p.parse().ok())
+                .unwrap_or(default.pages)
+                .clamp(1, MAX_PAGES),
+            data_types: var("FDC_DATA_TYPES")
+                .map(|types| {
+                    types
+                        .split(',')
+                        .map(|t| t.trim().to_string())
+                        .filter(|t| !t.is_empty())
+                        .collect()
+                })
+                .unwrap_or(default.data_types),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum UsdaError {
+    /// The API key exceeded its hourly request limit
+    RateLimited {
+        retry_after: Option<Duration>,
+    },
+    Status(StatusCode),
+    Http(reqwest::Error),
+}
+
+impl Display for UsdaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UsdaError::RateLimited {
+                retry_after: Some(wait),
+            } => write!(
+                f,
+                "The USDA food database rate limit was reached, try again in {} seconds",
+                wait.as_secs().max(1)
+            ),
+            UsdaError::RateLimited { retry_after: None } => write!(
+                f,
+                "The USDA food database rate limit was reached, try again later"
+            ),
+            UsdaError::Status(status) => write!(f, "The USDA food database answered {}", status),
+            UsdaError::Http(e) => write!(f, "The USDA food database is not reachable: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for UsdaError {}
+
+impl From<reqwest::Error> for UsdaError {
+    fn from(e: reqwest::Error) -> Self {
+        UsdaError::Http(e)
+    }
+}
+
+/// Cheap to clone, the connection pool and configuration are shared
+#[derive(Clone)]
+pub(crate) struct UsdaClient {
+    http: reqwest::Client,
+    config: Arc<UsdaConfig>,
+}
+
+impl UsdaClient {
+    pub(crate) fn new(config: UsdaConfig) -> UsdaClient {
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .expect("valid USDA http client");
+        UsdaClient {
+            http,
+            config: Arc::new(config),
+        }
+    }
+
+    pub(crate) fn from_env() -> UsdaClient {
+        UsdaClient::new(UsdaConfig::from_env())
+    }
+
+    /// One page of the search restricted to a data type
+    pub(crate) async fn search_page(
+        &self,
+        query: &str,
+        data_type: &str,
+        page: u32,
+    ) -> Result<SearchResult, UsdaError> {
+        let url = format!("{}/foods/search", self.config.base_url);
+        let page_size = self.config.page_size.to_string();
+        let page = page.to_string();
+        let params = [
+            ("query", query),
+            ("dataType", data_type),
+            ("pageSize", &page_size),
+            ("pageNumber", &page),
+            ("sortBy", "dataType.keyword"),
+            ("sortOrder", "asc"),
+            ("api_key", &self.config.api_key),
+        ];
+        let response = self.send(self.http.get(&url).query(&params)).await?;
+        Ok(response.json().await?)
+    }
+
+    // sends the request, waiting and retrying as long as the rate limit allows it
+    async fn send(&self, request: RequestBuilder) -> Result<Response, UsdaError> {
+        let mut retries = 0;
+        loop {
+            let response = request
+                .try_clone()
+                .expect("USDA requests have no streaming body")
+                .send()
+                .await?;
+            match response.status() {
+                StatusCode::TOO_MANY_REQUESTS => {
+                    let retry_after = retry_after(&response);
+                    match retry_after {
+                        Some(wait) if retries < MAX_RETRIES && wait <= MAX_RETRY_WAIT => {
+                            retries += 1;
+                            tokio::time::sleep(wait).await;
+                        }
+                        _ => return Err(UsdaError::RateLimited { retry_after }),
+                    }
+                }
+                status if status.is_success() => return Ok(response),
+                status => return Err(UsdaError::Status(status)),
+            }
+        }
+    }
+
+    // full records of the ids, /food/{id} for a single one and /foods for batches
+    async fn fetch_details(&self, ids: &[i32]) -> Result<Vec<FoodDetails>, UsdaError> {
+        let key = [("api_key", &self.config.api_key)];
+        if let [id] = ids {
+            let url = format!("{}/food/{}", self.config.base_url, id);
+            let response = self.send(self.http.get(&url).query(&key)).await?;
+            return Ok(vec![response.json().await?]);
+        }
+        let url = format!("{}/foods", self.config.base_url);
+        let mut details = vec![];
+        for batch in ids.chunks(MAX_BATCH) {
+            let body = serde_json::json!({ "fdcIds": batch, "format": "full" });
+            let response = self
+                .send(self.http.post(&url).query(&key).json(&body))
+                .await?;
+            details.append(&mut response.json().await?);
+        }
+        Ok(details)
+    }
+
+    /// Full foods with portions, ingredients and nutrient metadata, fetched only if not cached.
+    /// Foods unknown to FDC are left out.
+    pub(crate) async fn get_foods(&self, ids: &[i32]) -> Result<Vec<Food>, UsdaError> {
+        let mut con = crate::db::connector::get_connection().unwrap();
+        let mut foods: Vec<Food> = ids
+            .iter()
+            .filter_map(|id| Food::fetch_from_uuid(&mut con, &id.to_string()))
+            .filter(|food| food.detailed)
+            .collect();
+        let missing: Vec<i32> = ids
+            .iter()
+            .filter(|id| !foods.iter().any(|food| food.id == **id))
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            for details in self.fetch_details(&missing).await? {
+                let food = details.into_food();
+                food.save(&mut con).unwrap();
+                foods.push(food);
+            }
+        }
+        foods.sort_by_key(|food| ids.iter().position(|id| *id == food.id));
+        Ok(foods
+            .into_iter()
+            .map(Food::generate_nutrient_values)
+            .collect())
+    }
+
+    pub(crate) async fn get_food(&self, id: i32) -> Result<Food, UsdaError> {
+        self.get_foods(&[id])
+            .await?
+            .pop()
+            .ok_or(UsdaError::Status(StatusCode::NOT_FOUND))
+    }
+
+    pub(crate) fn data_types(&self) -> &[String] {
+        &self.config.data_types
+    }
+
+    /// All configured pages of a data type, the pages after the first are requested concurrently
+    pub(crate) async fn search_data_type(
+        &self,
+        query: &str,
+        data_type: &str,
+    ) -> Result<SearchResult, UsdaError> {
+        let mut result = self.search_page(query, data_type, 1).await?;
+        let pages = result.total_pages.min(self.config.pages);
+        let mut requests = JoinSet::new();
+        for page in 2..=pages {
+            let (client, query, data_type) =
+                (self.clone(), query.to_string(), data_type.to_string());
+            requests
+                .spawn(async move { (page, client.search_page(&query, &data_type, page).await) });
+        }
+        let mut pages = join_all(requests).await;
+        pages.sort_by_key(|(page, _)| *page);
+        for (_, page) in pages {
+            result.foods.append(&mut page?.foods);
+        }
+        for food in result.foods.iter_mut() {
+            food.nutrient_values = food.get_numerical_macros();
+        }
+        Ok(result)
+    }
+
+    /// Foods of each data type without duplicates, searched concurrently and in the order of
+    /// `data_types`
+    pub(crate) async fn fetch_data_types(
+        &self,
+        search_term: &str,
+        data_types: &[String],
+    ) -> Vec<Result<Vec<Food>, UsdaError>> {
+        let mut requests = JoinSet::new();
+        for (i, data_type) in data_types.iter().enumerate() {
+            let (client, query, data_type) =
+                (self.clone(), search_term.to_string(), data_type.clone());
+            requests.spawn(async move { (i, client.search_foods_of(&query, &data_type).await) });
+        }
+        let mut responses = join_all(requests).await;
+        responses.sort_by_key(|(i, _)| *i);
+        responses.into_iter().map(|(_, foods)| foods).collect()
+    }
+
+    /// Foods of one data type without duplicates, neither stored nor ranked
+    pub(crate) async fn search_foods_of(
+        &self,
+        search_term: &str,
+        data_type: &str,
+    ) -> Result<Vec<Food>, UsdaError> {
+        let resp = self.search_data_type(search_term, data_type).await?;
+        Ok(merge_foods(&[], &resp.foods))
+    }
+}
+
+/// Merges the foods of the data types in their order, so the first data type wins duplicates.
+/// Fails only if no data type could be searched.
+pub(crate) fn merge_data_types(
+    data_types: &[String],
+    results: Vec<Result<Vec<Food>, UsdaError>>,
+) -> Result<Vec<Food>, UsdaError> {
+    let mut result = vec![];
+    let mut error = None;
+    for (data_type, foods) in data_types.iter().zip(results) {
+        match foods {
+            Ok(foods) => {
+                let mut unique_foods = merge_foods(&result, &foods);
+                result.append(&mut unique_foods);
+            }
+            Err(e) => {
+                println!("USDA search in {} failed: {}", data_type, e);
+                error.get_or_insert(e);
+            }
+        }
+    }
+    match (result.is_empty(), error) {
+        (true, Some(e)) => Err(e),
+        _ => Ok(result),
+    }
+}
+
+// results of all tasks, in the order they finished
+async fn join_all<T: 'static>(mut tasks: JoinSet<T>) -> Vec<T> {
+    let mut results = vec![];
+    while let Some(result) = tasks.join_next().await {
+        results.push(result.expect("USDA request task panicked"));
+    }
+    results
+}
+
+/// Foods of `new` not already in `known` and without duplicates among themselves
+pub(crate) fn merge_foods(known: &[Food], new: &[Food]) -> Vec<Food> {
+    let mut unique_foods: Vec<Food> = vec![];
+    for food in new {
+        if !known
+            .iter()
+            .chain(unique_foods.iter())
+            .any(|x| x.compare_food(food))
+        {
+            unique_foods.push(food.clone());
+        }
+    }
+    unique_foods
+}
+
+// wait requested by a 429 response, FDC sends seconds
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // answers each connection with the next response, returns the base url
+    async fn mock_server(responses: Vec<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 4096];
+                let _ = socket.read(&mut buf).await.unwrap();
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    // answers every request with the response of the first route found in the request
+    pub(crate) async fn mock_routes(routes: Vec<(&'static str, String)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 4096];
+                let n = socket.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let response = routes
+                    .iter()
+                    .find(|(pattern, _)| request.contains(pattern))
+                    .map(|(_, response)| response.clone())
+                    .unwrap_or(
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_string(),
+                    );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    fn client(base_url: String) -> UsdaClient {
+        UsdaClient::new(UsdaConfig {
+            base_url,
+            ..UsdaConfig::default()
+        })
+    }
+
+    const FOODS: &str = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\n\r\n{\"foods\": [{\"fdcId\": 1, \"description\": \"Apple\", \"dataType\": \"SR Legacy\", \"foodNutrients\": []}]}";
+    const RATE_LIMITED: &str = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const RATE_LIMITED_LONG: &str = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 3600\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+    #[tokio::test]
+    async fn test_search_page_retries_after_rate_limit() {
+        let client = client(mock_server(vec![RATE_LIMITED, FOODS]).await);
+        let result = client.search_page("apple", "SR Legacy", 1).await.unwrap();
+        assert_eq!(result.foods.len(), 1);
+        assert_eq!(result.foods[0].description, "Apple");
+    }
+
+    #[tokio::test]
+    async fn test_search_page_rate_limited() {
+        let client = client(mock_server(vec![RATE_LIMITED_LONG]).await);
+        let err = client
+            .search_page("apple", "SR Legacy", 1)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            UsdaError::RateLimited {
+                retry_after: Some(wait)
+            } if wait == Duration::from_secs(3600)
+        ));
+    }
+
+    pub(crate) fn page(foods: &str, page: u32) -> String {
+        let body = format!(
+            "{{\"foods\": [{}], \"totalPages\": 5, \"currentPage\": {}}}",
+            foods, page
+        );
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\n\r\n{}",
+            body
+        )
+    }
+
+    #[tokio::test]
+    async fn test_search_data_type_paginates() {
+        let responses = vec![
+            page(
+                r#"{"fdcId": 1, "description": "Apple", "dataType": "Branded", "foodNutrients": []}"#,
+                1,
+            ),
+            page(
+                r#"{"fdcId": 2, "description": "Apple pie", "dataType": "Branded", "foodNutrients": []}"#,
+                2,
+            ),
+        ];
+        let responses = responses.into_iter().map(|r| &*r.leak()).collect();
+        let client = UsdaClient::new(UsdaConfig {
+            base_url: mock_server(responses).await,
+            pages: 2,
+            ..UsdaConfig::default()
+        });
+        let result = client.search_data_type("apple", "Branded").await.unwrap();
+        let mut ids: Vec<i32> = result.foods.iter().map(|f| f.id).collect();
+        ids.sort();
+        assert_eq!(ids, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_details_batch() {
+        let body = r#"[{"fdcId": 1, "description": "Apple", "dataType": "Foundation", "foodPortions": [
+            {"amount": 1.0, "gramWeight": 182.0, "measureUnit": {"name": "medium"}}]},
+            {"fdcId": 2, "description": "Pear", "dataType": "Foundation"}]"#;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\n\r\n{}",
+            body
+        );
+        let client = client(mock_server(vec![response.leak()]).await);
+        let details = client.fetch_details(&[1, 2]).await.unwrap();
+        let foods: Vec<Food> = details.into_iter().map(FoodDetails::into_food).collect();
+        assert_eq!(foods.len(), 2);
+        assert_eq!(foods[0].portions[0].description, "1 medium");
+        assert_eq!(foods[0].portions[0].gram_weight, 182.0);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_foods_without_duplicates() {
+        let foundation = page(
+            r#"{"fdcId": 1, "description": "Apples, raw", "dataType": "Foundation", "foodNutrients": []},
+               {"fdcId": 1, "description": "Apples, raw", "dataType": "Foundation", "foodNutrients": []}"#,
+            1,
+        );
+        let legacy = page(
+            r#"{"fdcId": 2, "description": "Apples, raw", "dataType": "SR Legacy", "foodNutrients": []},
+               {"fdcId": 3, "description": "Apple juice", "dataType": "SR Legacy", "foodNutrients": []}"#,
+            1,
+        );
+        let client = UsdaClient::new(UsdaConfig {
+            base_url: mock_routes(vec![("Foundation", foundation), ("Legacy", legacy)]).await,
+            data_types: vec!["Foundation".to_string(), "SR Legacy".to_string()],
+            ..UsdaConfig::default()
+        });
+        let results = client.fetch_data_types("apple", client.data_types()).await;
+        let foods = merge_data_types(client.data_types(), results).unwrap();
+        let ids: Vec<i32> = foods.iter().map(|f| f.id).collect();
+        assert_eq!(ids, [1, 3]);
+    }
+
+    #[test]
+    fn test_merge_foods() {
+        let known = vec![Food::new(1, "Apples, raw", vec![])];
+        let new = vec![
+            Food::new(2, "Apples, raw", vec![]),
+            Food::new(3, "Apple juice", vec![]),
+            Food::new(4, "Apple juice", vec![]),
+        ];
+        let merged = merge_foods(&known, &new);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].id, 3);
+    }
+
+    #[test]
+    fn test_page_size_is_clamped() {
+        let config = UsdaConfig::from_vars(|name| match name {
+            "FDC_PAGE_SIZE" => Some("1000".to_string()),
+            "FDC_DATA_TYPES" => Some("Foundation, SR Legacy".to_string()),
+            _ => None,
+        });
+        assert_eq!(config.page_size, MAX_PAGE_SIZE);
+        assert_eq!(config.data_types, ["Foundation", "SR Legacy"]);
+    }
+}
@@ -0,0 +1,246 @@
+// Full food records as returned by /food/{id}, /foods and the bulk downloads. Nutrients,
+// categories and portions are nested objects unlike in the search results.
+use crate::usda::search::{Food, Nutrient, Portion};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+struct NutrientInfo {
+    id: i32,
+    #[serde(default)]
+    number: Option<String>,
+    name: String,
+    #[serde(default)]
+    rank: Option<i32>,
+    #[serde(rename = "unitName")]
+    unit_name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Derivation {
+    description: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct DetailNutrient {
+    nutrient: NutrientInfo,
+    amount: Option<f32>,
+    #[serde(default)]
+    median: Option<f32>,
+    #[serde(rename = "foodNutrientDerivation", default)]
+    derivation: Option<Derivation>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Category {
+    description: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct WweiaCategory {
+    #[serde(rename = "wweiaFoodCategoryDescription")]
+    description: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct MeasureUnit {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct DetailPortion {
+    #[serde(default)]
+    amount: Option<f32>,
+    #[serde(rename = "gramWeight")]
+    gram_weight: Option<f32>,
+    #[serde(default)]
+    modifier: Option<String>,
+    #[serde(rename = "portionDescription", default)]
+    description: Option<String>,
+    #[serde(rename = "measureUnit", default)]
+    measure_unit: Option<MeasureUnit>,
+}
+
+impl DetailPortion {
+    // FNDDS describes the whole portion, SR Legacy and Foundation split it into amount, unit and modifier
+    fn into_portion(self) -> Option<Portion> {
+        let gram_weight = self.gram_weight.filter(|g| *g > 0.0)?;
+        let description = match self.description {
+            Some(d) if !d.is_empty() && d != "Quantity not specified" => d,
+            _ => {
+                let unit = self
+                    .measure_unit
+                    .map(|u| u.name)
+                    .filter(|u| u != "undetermined");
+                [
+                    self.amount.map(|a| a.to_string()),
+                    unit,
+                    self.modifier.filter(|m| !m.is_empty()),
+                ]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<String>>()
+                .join(" ")
+            }
+        };
+        Some(Portion {
+            description,
+            gram_weight,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct FoodDetails {
+    #[serde(rename = "fdcId")]
+    id: i32,
+    description: String,
+    #[serde(rename = "dataType")]
+    data_type: String,
+    #[serde(rename = "foodCategory", default)]
+    food_category: Option<Category>,
+    #[serde(rename = "wweiaFoodCategory", default)]
+    wweia_category: Option<WweiaCategory>,
+    #[serde(rename = "brandedFoodCategory", default)]
+    branded_category: Option<String>,
+    #[serde(rename = "brandOwner", default)]
+    brand_owner: Option<String>,
+    #[serde(rename = "brandName", default)]
+    brand_name: Option<String>,
+    #[serde(rename = "gtinUpc", default)]
+    gtin_upc: Option<String>,
+    #[serde(rename = "foodNutrients", default)]
+    nutrients: Vec<DetailNutrient>,
+    #[serde(rename = "foodPortions", default)]
+    portions: Vec<DetailPortion>,
+    #[serde(rename = "servingSize", default)]
+    serving_size: Option<f32>,
+    #[serde(rename = "servingSizeUnit", default)]
+    serving_size_unit: Option<String>,
+    #[serde(rename = "householdServingFullText", default)]
+    household_serving: Option<String>,
+    #[serde(default)]
+    ingredients: Option<String>,
+}
+
+impl FoodDetails {
+    pub(crate) fn into_food(self) -> Food {
+        let nutrients = self
+            .nutrients
+            .into_iter()
+            .filter_map(|n| {
+                let mut nutrient = Nutrient::new(
+                    n.nutrient.id,
+                    n.nutrient.name,
+                    n.amount?,
+                    n.nutrient.unit_name,
+                );
+                nutrient.number = n.nutrient.number;
+                nutrient.rank = n.nutrient.rank;
+                nutrient.median = n.median;
+                nutrient.derivation = n.derivation.map(|d| d.description);
+                Some(nutrient)
+            })
+            .collect();
+        let mut food = Food::new(self.id, self.description, nutrients);
+        food.data_set = self.data_type;
+        food.food_category = self
+            .food_category
+            .map(|c| c.description)
+            .or(self.wweia_category.map(|c| c.description))
+            .or(self.branded_category);
+        food.brand_owner = self.brand_owner;
+        food.brand_name = self.brand_name;
+        food.gtin_upc = self.gtin_upc;
+        food.portions = self
+            .portions
+            .into_iter()
+            .filter_map(DetailPortion::into_portion)
+            .collect();
+        // branded foods only state their serving size
+        let serving_grams = match self.serving_size_unit.as_deref() {
+            Some("g" | "G" | "GRM" | "ml" | "ML" | "MLT") => self.serving_size,
+            _ => None,
+        };
+        if let (true, Some(grams)) = (food.portions.is_empty(), serving_grams) {
+            food.portions.push(Portion {
+                description: self
+                    .household_serving
+                    .clone()
+                    .unwrap_or("1 serving".to_string()),
+                gram_weight: grams,
+            });
+        }
+        food.serving_size = self.serving_size;
+        food.serving_size_unit = self.serving_size_unit;
+        food.household_serving = self.household_serving;
+        food.ingredients = self.ingredients;
+        food.detailed = true;
+        food
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sr_legacy_details() {
+        let json = r#"{"fdcId": 171688, "description": "Apples, raw, with skin", "dataType": "SR Legacy",
+            "foodCategory": {"id": 9, "code": "0900", "description": "Fruits and Fruit Juices"},
+            "foodNutrients": [
+                {"type": "FoodNutrient", "id": 1, "amount": 52.0, "median": 52.0,
+                 "nutrient": {"id": 1008, "number": "208", "name": "Energy", "rank": 300, "unitName": "kcal"},
+                 "foodNutrientDerivation": {"code": "NC", "description": "Calculated"}}
+            ],
+            "foodPortions": [
+                {"id": 1, "amount": 1.0, "gramWeight": 125.0, "modifier": "cup, chopped",
+                 "measureUnit": {"id": 9999, "name": "undetermined", "abbreviation": "undetermined"}},
+                {"id": 2, "amount": 1.0, "gramWeight": 0.0, "modifier": "NLEA serving",
+                 "measureUnit": {"id": 9999, "name": "undetermined", "abbreviation": "undetermined"}}
+            ]}"#;
+        let food = serde_json::from_str::<FoodDetails>(json)
+            .unwrap()
+            .into_food();
+        assert!(food.detailed);
+        assert_eq!(
+            food.food_category.as_deref(),
+            Some("Fruits and Fruit Juices")
+        );
+        assert_eq!(
+            food.portions,
+            [Portion {
+                description: "1 cup, chopped".to_string(),
+                gram_weight: 125.0
+            }]
+        );
+        let energy = &food.nutrient_listing()[0];
+        assert_eq!(energy.number.as_deref(), Some("208"));
+        assert_eq!(energy.derivation.as_deref(), Some("Calculated"));
+        assert_eq!(food.nutrient_values.energy, 52.0);
+    }
+
+    #[test]
+    fn test_branded_serving() {
+        let json = r#"{"fdcId": 2, "description": "GRANOLA", "dataType": "Branded",
+            "brandOwner": "Acme", "brandedFoodCategory": "Cereal", "gtinUpc": "041570054161",
+            "ingredients": "WHOLE GRAIN OATS, SUGAR, ALMONDS.",
+            "servingSize": 55.0, "servingSizeUnit": "g", "householdServingFullText": "2/3 cup",
+            "foodNutrients": []}"#;
+        let food = serde_json::from_str::<FoodDetails>(json)
+            .unwrap()
+            .into_food();
+        assert_eq!(food.food_category.as_deref(), Some("Cereal"));
+        assert_eq!(food.gtin_upc.as_deref(), Some("041570054161"));
+        assert_eq!(
+            food.ingredients.as_deref(),
+            Some("WHOLE GRAIN OATS, SUGAR, ALMONDS.")
+        );
+        assert_eq!(
+            food.portions,
+            [Portion {
+                description: "2/3 cup".to_string(),
+                gram_weight: 55.0
+            }]
+        );
+    }
+}
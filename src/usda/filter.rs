@@ -0,0 +1,238 @@
+// Filters and sort orders of the food search, applied to the found foods
+use crate::models::goals::GOAL_NUTRIENTS;
+use crate::models::models::NutritionistSearchQuery;
+use crate::open_food_facts::product::OFF_DATA_SET;
+use crate::usda::search::{normalize_query, Food};
+use std::cmp::Ordering;
+
+/// Data sets a search can be restricted to, the FDC data types and Open Food Facts
+pub(crate) const DATA_TYPES: [&str; 5] = [
+    "Foundation",
+    "SR Legacy",
+    "Survey (FNDDS)",
+    "Branded",
+    OFF_DATA_SET,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum SortOrder {
+    #[default]
+    Relevance,
+    /// least energy per 100 g first
+    Energy,
+    /// most protein per kcal first
+    ProteinDensity,
+}
+
+impl SortOrder {
+    pub(crate) const ALL: [SortOrder; 3] = [
+        SortOrder::Relevance,
+        SortOrder::Energy,
+        SortOrder::ProteinDensity,
+    ];
+
+    pub(crate) fn key(&self) -> &'static str {
+        match self {
+            SortOrder::Relevance => "relevance",
+            SortOrder::Energy => "energy",
+            SortOrder::ProteinDensity => "protein_density",
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            SortOrder::Relevance => "Relevance",
+            SortOrder::Energy => "Energy (low first)",
+            SortOrder::ProteinDensity => "Protein per kcal",
+        }
+    }
+
+    pub(crate) fn parse(key: &str) -> SortOrder {
+        SortOrder::ALL
+            .into_iter()
+            .find(|s| s.key() == key)
+            .unwrap_or_default()
+    }
+}
+
+/// Bounds of a nutrient per 100 g, e.g. proteins >= 20
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NutrientRange {
+    pub(crate) nutrient: String,
+    pub(crate) min: Option<f32>,
+    pub(crate) max: Option<f32>,
+}
+
+impl NutrientRange {
+    fn contains(&self, food: &Food) -> bool {
+        let Some(value) = food.nutrient_values.get(&self.nutrient) else {
+            return true;
+        };
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct FoodFilter {
+    pub(crate) data_type: Option<String>,
+    pub(crate) brand: Option<String>,
+    pub(crate) category: Option<String>,
+    pub(crate) nutrients: Vec<NutrientRange>,
+    pub(crate) sort: SortOrder,
+}
+
+// protein in g per 100 kcal, foods without energy come last
+fn protein_density(food: &Food) -> f32 {
+    let values = &food.nutrient_values;
+    if values.energy > 0.0 {
+        values.proteins / values.energy * 100.0
+    } else {
+        0.0
+    }
+}
+
+// case and accent insensitive substring match
+fn contains(text: Option<&str>, part: &str) -> bool {
+    text.is_some_and(|text| normalize_query(text).contains(&normalize_query(part)))
+}
+
+impl FoodFilter {
+    fn matches(&self, food: &Food) -> bool {
+        self.data_type.as_ref().is_none_or(|d| *d == food.data_set)
+            && self.brand.as_ref().is_none_or(|b| {
+                contains(food.brand_owner.as_deref(), b) || contains(food.brand_name.as_deref(), b)
+            })
+            && self
+                .category
+                .as_ref()
+                .is_none_or(|c| contains(food.food_category.as_deref(), c))
+            && self.nutrients.iter().all(|range| range.contains(food))
+    }
+
+    /// The matching foods in the chosen order, relevance keeps the order of the search
+    pub(crate) fn apply(&self, foods: Vec<Food>) -> Vec<Food> {
+        let mut foods: Vec<Food> = foods.into_iter().filter(|f| self.matches(f)).collect();
+        match self.sort {
+            SortOrder::Relevance => {}
+            SortOrder::Energy => foods.sort_by(|a, b| {
+                a.nutrient_values
+                    .energy
+                    .partial_cmp(&b.nutrient_values.energy)
+                    .unwrap_or(Ordering::Equal)
+            }),
+            SortOrder::ProteinDensity => foods.sort_by(|a, b| {
+                protein_density(b)
+                    .partial_cmp(&protein_density(a))
+                    .unwrap_or(Ordering::Equal)
+            }),
+        }
+        foods
+    }
+}
+
+impl NutritionistSearchQuery {
+    // empty fields of the form mean no filter
+    pub(crate) fn filter(&self) -> FoodFilter {
+        let text = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+        let number = |s: &str| s.trim().parse::<f32>().ok();
+        let range = GOAL_NUTRIENTS
+            .iter()
+            .find(|(name, ..)| *name == self.nutrient)
+            .map(|(name, ..)| NutrientRange {
+                nutrient: name.to_string(),
+                min: number(&self.min),
+                max: number(&self.max),
+            })
+            .filter(|r| r.min.is_some() || r.max.is_some());
+        FoodFilter {
+            data_type: text(&self.data_type).filter(|d| DATA_TYPES.contains(&d.as_str())),
+            brand: text(&self.brand),
+            category: text(&self.category),
+            nutrients: range.into_iter().collect(),
+            sort: SortOrder::parse(&self.sort),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usda::nutrients;
+    use crate::usda::search::Nutrient;
+
+    fn food(id: i32, data_set: &str, brand: Option<&str>, energy: f32, protein: f32) -> Food {
+        let mut food = Food::new(
+            id,
+            "Food",
+            vec![
+                Nutrient::new(nutrients::ENERGY, "Energy", energy, "KCAL"),
+                Nutrient::new(nutrients::PROTEIN, "Protein", protein, "G"),
+            ],
+        );
+        food.data_set = data_set.to_string();
+        food.brand_owner = brand.map(String::from);
+        food
+    }
+
+    fn ids(foods: Vec<Food>) -> Vec<i32> {
+        foods.iter().map(|f| f.id).collect()
+    }
+
+    #[test]
+    fn test_filter_from_form() {
+        let query = NutritionistSearchQuery {
+            query: "chicken".to_string(),
+            data_type: "Branded".to_string(),
+            brand: " ".to_string(),
+            nutrient: "proteins".to_string(),
+            min: "20".to_string(),
+            sort: "protein_density".to_string(),
+            ..Default::default()
+        };
+        let filter = query.filter();
+        assert_eq!(filter.data_type.as_deref(), Some("Branded"));
+        assert_eq!(filter.brand, None);
+        assert_eq!(
+            filter.nutrients,
+            [NutrientRange {
+                nutrient: "proteins".to_string(),
+                min: Some(20.0),
+                max: None
+            }]
+        );
+        assert_eq!(filter.sort, SortOrder::ProteinDensity);
+        assert_eq!(
+            NutritionistSearchQuery::default().filter(),
+            FoodFilter::default()
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let foods = vec![
+            food(1, "Branded", Some("Nestlé"), 400.0, 10.0),
+            food(2, "SR Legacy", None, 165.0, 31.0),
+            food(3, "Branded", Some("Acme"), 120.0, 25.0),
+        ];
+        let high_protein = FoodFilter {
+            nutrients: vec![NutrientRange {
+                nutrient: "proteins".to_string(),
+                min: Some(20.0),
+                max: None,
+            }],
+            sort: SortOrder::Energy,
+            ..Default::default()
+        };
+        assert_eq!(ids(high_protein.apply(foods.clone())), [3, 2]);
+        let nestle = FoodFilter {
+            brand: Some("nestle".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(nestle.apply(foods.clone())), [1]);
+        let density = FoodFilter {
+            sort: SortOrder::ProteinDensity,
+            ..Default::default()
+        };
+        assert_eq!(ids(density.apply(foods)), [3, 2, 1]);
+    }
+}
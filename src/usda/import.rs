@@ -0,0 +1,191 @@
+// Importer for the FDC bulk JSON downloads (https://fdc.nal.usda.gov/download-datasets.html),
+// the files are streamed so even the multi gigabyte branded foods fit in memory
+use crate::usda::details::FoodDetails;
+use crate::usda::index;
+use crate::usda::search::Food;
+use serde::de::{DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserializer;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+// keys of the food lists in the Foundation, SR Legacy, FNDDS and Branded files
+const FOOD_LIST_KEYS: [&str; 4] = [
+    "FoundationFoods",
+    "SRLegacyFoods",
+    "SurveyFoods",
+    "BrandedFoods",
+];
+// foods written to redis per round trip
+const BATCH_SIZE: usize = 500;
+
+// walks the top level object and hands every food of the known lists to the callback
+struct FoodsFile<F>(F);
+
+impl<'de, F: FnMut(Food) -> Result<(), Box<dyn Error>>> Visitor<'de> for FoodsFile<F> {
+    type Value = usize;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an FDC bulk download object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<usize, A::Error> {
+        let mut count = 0;
+        while let Some(key) = map.next_key::<String>()? {
+            if FOOD_LIST_KEYS.contains(&key.as_str()) {
+                count += map.next_value_seed(FoodList(&mut self.0))?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(count)
+    }
+}
+
+struct FoodList<'a, F>(&'a mut F);
+
+impl<'de, F: FnMut(Food) -> Result<(), Box<dyn Error>>> DeserializeSeed<'de> for FoodList<'_, F> {
+    type Value = usize;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: FnMut(Food) -> Result<(), Box<dyn Error>>> Visitor<'de> for FoodList<'_, F> {
+    type Value = usize;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a list of foods")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<usize, A::Error> {
+        let mut count = 0;
+        while let Some(food) = seq.next_element::<FoodDetails>()? {
+            (self.0)(food.into_food()).map_err(serde::de::Error::custom)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Streams the foods of a bulk file to `on_food`, returns how many were read. Reading stops at
+/// the first error of `on_food`.
+pub(crate) fn read_foods(
+    reader: impl Read,
+    on_food: impl FnMut(Food) -> Result<(), Box<dyn Error>>,
+) -> Result<usize, serde_json::Error> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    deserializer.deserialize_map(FoodsFile(on_food))
+}
+
+/// Stores and indexes every food of the bulk file in redis
+pub(crate) fn import_file(
+    con: &mut redis::Connection,
+    path: &str,
+) -> Result<usize, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut batch: Vec<Food> = Vec::with_capacity(BATCH_SIZE);
+    let count = read_foods(reader, |food| {
+        batch.push(food);
+        if batch.len() == BATCH_SIZE {
+            index::save_and_index(con, &batch)?;
+            batch.clear();
+        }
+        Ok(())
+    })?;
+    index::save_and_index(con, &batch)?;
+    Ok(count)
+}
+
+/// Entry point of `nutritionist import-fdc <file.json>...`
+pub fn run(paths: &[String]) {
+    if paths.is_empty() {
+        println!("usage: nutritionist import-fdc <FoodData_Central_*.json>...");
+        println!("only the JSON downloads are accepted, not the CSV ones");
+        return;
+    }
+    let mut con = crate::db::connector::get_connection()
+        .expect("Could not connect to redis,maybe redis is not running");
+    for path in paths {
+        match import_file(&mut con, path) {
+            Ok(count) => println!("imported {} foods from {}", count, path),
+            Err(e) => println!("import of {} failed: {}", path, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::food::FoodItem;
+    use crate::usda::barcode;
+
+    const SAMPLE: &str = r#"{"SRLegacyFoods": [
+        {"fdcId": 171688, "description": "Apples, raw, with skin", "dataType": "SR Legacy",
+         "foodCategory": {"description": "Fruits and Fruit Juices"}, "publicationDate": "4/1/2019",
+         "foodNutrients": [
+            {"type": "FoodNutrient", "id": 1, "amount": 52.0,
+             "nutrient": {"id": 1008, "number": "208", "name": "Energy", "rank": 300, "unitName": "kcal"}},
+            {"type": "FoodNutrient", "id": 2, "amount": 1.0,
+             "nutrient": {"id": 1093, "number": "307", "name": "Sodium, Na", "rank": 5800, "unitName": "mg"}},
+            {"type": "FoodNutrient", "id": 3,
+             "nutrient": {"id": 1003, "number": "203", "name": "Protein", "rank": 600, "unitName": "g"}}
+         ]},
+        {"fdcId": 2345, "description": "Cola", "dataType": "Survey (FNDDS)",
+         "wweiaFoodCategory": {"wweiaFoodCategoryCode": 7202, "wweiaFoodCategoryDescription": "Soft drinks"},
+         "foodNutrients": []}
+    ], "version": 1}"#;
+
+    #[test]
+    fn test_read_bulk_foods() {
+        let mut foods = vec![];
+        let count = read_foods(SAMPLE.as_bytes(), |food| {
+            foods.push(food);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(foods[0].id, 171688);
+        assert_eq!(foods[0].data_set, "SR Legacy");
+        assert_eq!(
+            foods[0].food_category.as_deref(),
+            Some("Fruits and Fruit Juices")
+        );
+        let values = foods[0].get_numerical_macros();
+        assert_eq!(values.energy, 52.0);
+        assert!((values.sodium - 0.001).abs() < 0.000_01);
+        // the nutrient without an amount is skipped
+        assert_eq!(values.proteins, 0.0);
+        assert_eq!(foods[1].food_category.as_deref(), Some("Soft drinks"));
+        assert!(foods[1].is_beverage());
+
+        let mut read = 0;
+        let error = read_foods(SAMPLE.as_bytes(), |_| {
+            read += 1;
+            Err("redis is gone".into())
+        })
+        .unwrap_err();
+        assert_eq!(read, 1);
+        assert!(error.to_string().contains("redis is gone"));
+    }
+
+    #[test]
+    #[ignore = "needs a running redis"]
+    fn test_imported_barcode_is_found() {
+        let json = r#"{"BrandedFoods": [
+            {"fdcId": 2099145, "description": "GRANOLA", "dataType": "Branded",
+             "brandOwner": "Acme", "gtinUpc": "041570054161", "foodNutrients": []}
+        ]}"#;
+        let path = std::env::temp_dir().join("fdc_import_barcode_test.json");
+        std::fs::write(&path, json).unwrap();
+        let mut con = crate::db::connector::get_connection()
+            .expect("Could not connect to redis,maybe redis is not running");
+        assert_eq!(import_file(&mut con, path.to_str().unwrap()).unwrap(), 1);
+
+        let food = barcode::stored_food(&mut con, "0041570054161").expect("DIDNT FIND BARCODE");
+        assert_eq!(food.id, 2099145);
+        assert_eq!(food.key().to_string(), "usda:2099145");
+    }
+}
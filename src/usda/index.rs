@@ -0,0 +1,352 @@
+// Local full text index over the stored foods: every term of a food points to its FDC ids.
+// Terms are folded to ASCII and stemmed, so "Köllnflocken" is found by "kollnflocken" and
+// "apples" by "apple", query terms missing from the index are matched against similar terms.
+use crate::models::food::FoodItem;
+use crate::models::models::RedisORM;
+use crate::usda::barcode;
+use crate::usda::search::Food;
+use redis::Connection;
+use rust_stemmers::{Algorithm, Stemmer};
+use std::collections::{HashMap, HashSet};
+
+// number of foods returned by a local search
+pub(crate) const SEARCH_LIMIT: usize = 100;
+// all indexed terms, used to find the terms close to a misspelled query term
+const TERMS_KEY: &str = "food_index_terms";
+// FDC id -> number of terms of the food
+const LENGTH_KEY: &str = "food_index_length";
+// FDC id -> data set of the food
+const DATA_SET_KEY: &str = "food_index_data_set";
+// FDC id -> how often the food was added to a meal
+const USAGE_KEY: &str = "food_usage";
+// a term found through a typo counts less than an exact match
+const FUZZY_WEIGHT: f32 = 0.5;
+
+fn term_key(term: &str) -> String {
+    format!("food_index:{}", term)
+}
+
+/// Folded, stemmed and deduplicated words of a text
+pub(crate) fn terms(text: &str) -> Vec<String> {
+    let stemmer = Stemmer::create(Algorithm::English);
+    let mut terms: Vec<String> = deunicode::deunicode(text)
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| stemmer.stem(w).into_owned())
+        .collect();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+// typos allowed between two terms, short terms have to match exactly
+fn max_typos(a: &str, b: &str) -> usize {
+    match a.len().max(b.len()) {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Weight of the best match of a query term among the terms of a food, None if nothing matches
+pub(crate) fn match_weight<'a>(
+    query_term: &str,
+    terms: impl IntoIterator<Item = &'a String>,
+) -> Option<f32> {
+    let mut best = None;
+    for term in terms {
+        if term == query_term {
+            return Some(1.0);
+        }
+        if strsim::levenshtein(query_term, term) <= max_typos(query_term, term) {
+            best = Some(FUZZY_WEIGHT);
+        }
+    }
+    best
+}
+
+// Foundation and SR Legacy have lab analysed values and generic names, branded foods come last
+fn data_set_priority(data_set: &str) -> f32 {
+    match data_set {
+        "Foundation" | "SR Legacy" => 1.0,
+        "Survey (FNDDS)" => 0.6,
+        _ => 0.0,
+    }
+}
+
+/// Relevance of a food: how well and how completely the query matches, then data set and usage
+pub(crate) fn score(weights: &[f32], food_terms: usize, data_set: &str, uses: u32) -> f32 {
+    if weights.is_empty() {
+        return 0.0;
+    }
+    let text = weights.iter().sum::<f32>() / weights.len() as f32;
+    let coverage = weights.len() as f32 / food_terms.max(weights.len()) as f32;
+    let usage = ((1.0 + uses as f32).ln() / 3.0).min(1.0);
+    4.0 * text + coverage + data_set_priority(data_set) + 2.0 * usage
+}
+
+// text a food is found by
+fn indexed_text(food: &Food) -> String {
+    [
+        Some(food.description.as_str()),
+        food.brand_name.as_deref(),
+        food.brand_owner.as_deref(),
+        food.food_category.as_deref(),
+    ]
+    .iter()
+    .flatten()
+    .cloned()
+    .collect::<Vec<&str>>()
+    .join(" ")
+}
+
+/// Queues the food and its index entries on a pipeline
+pub(crate) fn add_to_pipeline(pipe: &mut redis::Pipeline, food: &Food) {
+    pipe.cmd("SET")
+        .arg(format!("{}:{}", Food::redis_type_name(), food.redis_id()))
+        .arg(serde_json::to_string(food).unwrap())
+        .ignore();
+    if let Some(cmd) = food
+        .gtin_upc
+        .as_deref()
+        .and_then(|code| barcode::record_cmd(code, &food.key()))
+    {
+        pipe.add_command(cmd).ignore();
+    }
+    let terms = terms(&indexed_text(food));
+    pipe.cmd("HSET")
+        .arg(LENGTH_KEY)
+        .arg(food.id)
+        .arg(terms.len())
+        .ignore();
+    pipe.cmd("HSET")
+        .arg(DATA_SET_KEY)
+        .arg(food.id)
+        .arg(&food.data_set)
+        .ignore();
+    for term in terms {
+        pipe.cmd("SADD").arg(term_key(&term)).arg(food.id).ignore();
+        pipe.cmd("SADD").arg(TERMS_KEY).arg(term).ignore();
+    }
+}
+
+/// Stores the foods and indexes them in one round trip
+pub(crate) fn save_and_index(con: &mut Connection, foods: &[Food]) -> redis::RedisResult<()> {
+    let mut pipe = redis::pipe();
+    for food in foods {
+        add_to_pipeline(&mut pipe, food);
+    }
+    pipe.query(con)
+}
+
+/// Counts the food as used, often used foods rank higher
+pub(crate) fn record_use(con: &mut Connection, id: i32) -> redis::RedisResult<()> {
+    redis::cmd("ZINCRBY")
+        .arg(USAGE_KEY)
+        .arg(1)
+        .arg(id)
+        .query(con)
+}
+
+/// How often the foods were added to a meal
+pub(crate) fn uses(con: &mut Connection, ids: &[i32]) -> Vec<u32> {
+    if ids.is_empty() {
+        return vec![];
+    }
+    let scores: Vec<Option<f64>> = redis::cmd("ZMSCORE")
+        .arg(USAGE_KEY)
+        .arg(ids)
+        .query(con)
+        .unwrap_or_default();
+    ids.iter()
+        .enumerate()
+        .map(|(i, _)| scores.get(i).copied().flatten().unwrap_or(0.0) as u32)
+        .collect()
+}
+
+// ids of the foods containing the term or, if no food does, a similar term
+fn term_matches(
+    con: &mut Connection,
+    term: &str,
+    vocabulary: &mut Option<Vec<String>>,
+) -> (Vec<String>, f32) {
+    let exact: Vec<String> = redis::cmd("SMEMBERS")
+        .arg(term_key(term))
+        .query(con)
+        .unwrap_or_default();
+    if !exact.is_empty() {
+        return (exact, 1.0);
+    }
+    let vocabulary = vocabulary.get_or_insert_with(|| {
+        redis::cmd("SMEMBERS")
+            .arg(TERMS_KEY)
+            .query(con)
+            .unwrap_or_default()
+    });
+    let keys: Vec<String> = vocabulary
+        .iter()
+        .filter(|t| strsim::levenshtein(term, t) <= max_typos(term, t))
+        .map(|t| term_key(t))
+        .collect();
+    if keys.is_empty() {
+        return (vec![], FUZZY_WEIGHT);
+    }
+    let ids = redis::cmd("SUNION")
+        .arg(keys)
+        .query(con)
+        .unwrap_or_default();
+    (ids, FUZZY_WEIGHT)
+}
+
+/// Foods matching every term of the query, best matches first
+pub(crate) fn search(con: &mut Connection, query: &str) -> Vec<Food> {
+    let query_terms = terms(query);
+    if query_terms.is_empty() {
+        return vec![];
+    }
+    let mut vocabulary = None;
+    let mut weights: HashMap<String, Vec<f32>> = HashMap::new();
+    for (i, term) in query_terms.iter().enumerate() {
+        let (ids, weight) = term_matches(con, term, &mut vocabulary);
+        if i == 0 {
+            weights = ids.into_iter().map(|id| (id, vec![weight])).collect();
+        } else {
+            let ids: HashSet<String> = ids.into_iter().collect();
+            weights.retain(|id, found| {
+                found.push(weight);
+                ids.contains(id)
+            });
+        }
+        if weights.is_empty() {
+            return vec![];
+        }
+    }
+
+    let ids: Vec<i32> = weights.keys().filter_map(|id| id.parse().ok()).collect();
+    let lengths: Vec<Option<usize>> = redis::cmd("HMGET")
+        .arg(LENGTH_KEY)
+        .arg(&ids)
+        .query(con)
+        .unwrap_or_default();
+    let data_sets: Vec<Option<String>> = redis::cmd("HMGET")
+        .arg(DATA_SET_KEY)
+        .arg(&ids)
+        .query(con)
+        .unwrap_or_default();
+    let uses = uses(con, &ids);
+    let mut ranked: Vec<(f32, i32)> = ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let score = score(
+                &weights[&id.to_string()],
+                lengths
+                    .get(i)
+                    .copied()
+                    .flatten()
+                    .unwrap_or(query_terms.len()),
+                data_sets
+                    .get(i)
+                    .cloned()
+                    .flatten()
+                    .as_deref()
+                    .unwrap_or_default(),
+                uses.get(i).copied().unwrap_or(0),
+            );
+            (score, *id)
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+    ranked
+        .iter()
+        .take(SEARCH_LIMIT)
+        .filter_map(|(_, id)| Food::fetch_from_uuid(con, &id.to_string()))
+        .map(Food::generate_nutrient_values)
+        .collect()
+}
+
+/// Foods of a live search matching every query term, ranked like the local search
+pub(crate) fn rank(query: &str, foods: Vec<Food>, uses: &[u32]) -> Vec<Food> {
+    let query_terms = terms(query);
+    let mut ranked: Vec<(f32, Food)> = foods
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, food)| {
+            let food_terms = terms(&indexed_text(&food));
+            let weights = query_terms
+                .iter()
+                .map(|term| match_weight(term, &food_terms))
+                .collect::<Option<Vec<f32>>>()?;
+            let score = score(
+                &weights,
+                food_terms.len(),
+                &food.data_set,
+                uses.get(i).copied().unwrap_or(0),
+            );
+            Some((score, food))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+    ranked.into_iter().map(|(_, food)| food).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn food(id: i32, description: &str, data_set: &str) -> Food {
+        let mut food = Food::new(id, description, vec![]);
+        food.data_set = data_set.to_string();
+        food
+    }
+
+    #[test]
+    fn test_terms() {
+        assert_eq!(
+            terms("Apples, raw, with skin")[..],
+            ["appl", "raw", "skin", "with"]
+        );
+        assert_eq!(
+            terms("Milk, whole, 3.25% milkfat"),
+            ["25", "3", "milk", "milkfat", "whole"]
+        );
+        assert_eq!(terms("Köllnflocken"), terms("kollnflocken"));
+        assert_eq!(terms("Crème fraîche"), terms("creme fraiche"));
+        assert!(terms(" ,.; ").is_empty());
+    }
+
+    #[test]
+    fn test_typo_tolerance() {
+        let food_terms = terms("Köllnflocken Haferflocken");
+        assert_eq!(
+            match_weight(&terms("Köllnflocken")[0], &food_terms),
+            Some(1.0)
+        );
+        assert_eq!(
+            match_weight(&terms("Koellnflocken")[0], &food_terms),
+            Some(FUZZY_WEIGHT)
+        );
+        assert_eq!(
+            match_weight(&terms("brocoli")[0], &terms("Broccoli, raw")),
+            Some(FUZZY_WEIGHT)
+        );
+        // short terms have to match exactly
+        assert_eq!(match_weight("egg", &terms("Eel, raw")), None);
+    }
+
+    #[test]
+    fn test_rank() {
+        let foods = vec![
+            food(1, "APPLE PIE FILLING", "Branded"),
+            food(2, "Apples, raw, with skin", "SR Legacy"),
+            food(3, "Bananas, raw", "Foundation"),
+            food(4, "Apple juice", "Survey (FNDDS)"),
+        ];
+        let ids = |foods: Vec<Food>| foods.iter().map(|f| f.id).collect::<Vec<i32>>();
+        assert_eq!(ids(rank("apples", foods.clone(), &[])), [2, 4, 1]);
+        // frequently used foods come first
+        assert_eq!(ids(rank("apple", foods.clone(), &[50, 0, 0, 0])), [1, 2, 4]);
+        assert_eq!(ids(rank("aple juice", foods, &[])), [4]);
+    }
+}
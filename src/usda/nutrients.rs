@@ -0,0 +1,102 @@
+// FDC nutrient ids, see https://fdc.nal.usda.gov/portal-data/external/dataDictionary
+pub const PROTEIN: i32 = 1003;
+pub const TOTAL_FAT: i32 = 1004;
+pub const SATURATED_FAT: i32 = 1258;
+pub const CARBOHYDRATE_BY_DIFFERENCE: i32 = 1005;
+pub const CARBOHYDRATE_BY_SUMMATION: i32 = 1050;
+pub const ENERGY: i32 = 1008;
+pub const ENERGY_KJ: i32 = 1062;
+pub const ENERGY_ATWATER_GENERAL: i32 = 2047;
+pub const ENERGY_ATWATER_SPECIFIC: i32 = 2048;
+pub const FIBER_TOTAL_DIETARY: i32 = 1079;
+pub const SUGARS_TOTAL: i32 = 2000;
+pub const SUGARS_TOTAL_NLEA: i32 = 1063;
+pub const SODIUM: i32 = 1093;
+pub const WATER: i32 = 1051;
+
+// Preferred order when a food lists several energy values
+pub const ENERGY_IDS: [i32; 4] = [
+    ENERGY,
+    ENERGY_ATWATER_SPECIFIC,
+    ENERGY_ATWATER_GENERAL,
+    ENERGY_KJ,
+];
+pub const CARBOHYDRATE_IDS: [i32; 2] = [CARBOHYDRATE_BY_DIFFERENCE, CARBOHYDRATE_BY_SUMMATION];
+pub const SUGAR_IDS: [i32; 2] = [SUGARS_TOTAL, SUGARS_TOTAL_NLEA];
+
+pub const KJ_PER_KCAL: f32 = 4.184;
+// Salt is sodium chloride, sodium makes up ~40% of its mass
+pub const SALT_PER_SODIUM: f32 = 2.5;
+
+// Atwater general factors in kcal per gram
+pub const KCAL_PER_G_PROTEIN: f32 = 4.0;
+pub const KCAL_PER_G_CARBOHYDRATE: f32 = 4.0;
+pub const KCAL_PER_G_FAT: f32 = 9.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    Kcal,
+    KJ,
+    G,
+    Mg,
+    Ug,
+}
+
+impl Unit {
+    /// Parses an FDC `unitName` ("KCAL", "kJ", "G", "MG", "UG", "µg", ...)
+    pub fn parse(unit: &str) -> Option<Unit> {
+        match unit.trim().to_lowercase().as_str() {
+            "kcal" => Some(Unit::Kcal),
+            "kj" => Some(Unit::KJ),
+            "g" => Some(Unit::G),
+            "mg" => Some(Unit::Mg),
+            "ug" | "µg" | "μg" | "mcg" => Some(Unit::Ug),
+            _ => None,
+        }
+    }
+
+    /// Converts a mass to grams, None if this is not a mass unit
+    pub fn to_grams(self, amount: f32) -> Option<f32> {
+        match self {
+            Unit::G => Some(amount),
+            Unit::Mg => Some(amount * 0.001),
+            Unit::Ug => Some(amount * 0.000_001),
+            Unit::Kcal | Unit::KJ => None,
+        }
+    }
+
+    /// Converts an energy to kcal, None if this is not an energy unit
+    pub fn to_kcal(self, amount: f32) -> Option<f32> {
+        match self {
+            Unit::Kcal => Some(amount),
+            Unit::KJ => Some(amount / KJ_PER_KCAL),
+            Unit::G | Unit::Mg | Unit::Ug => None,
+        }
+    }
+}
+
+pub fn atwater_energy(proteins: f32, carbohydrates: f32, fats: f32) -> f32 {
+    proteins * KCAL_PER_G_PROTEIN + carbohydrates * KCAL_PER_G_CARBOHYDRATE + fats * KCAL_PER_G_FAT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_parse() {
+        assert_eq!(Unit::parse("KCAL"), Some(Unit::Kcal));
+        assert_eq!(Unit::parse("kJ"), Some(Unit::KJ));
+        assert_eq!(Unit::parse("µg"), Some(Unit::Ug));
+        assert_eq!(Unit::parse("UG"), Some(Unit::Ug));
+        assert_eq!(Unit::parse("IU"), None);
+    }
+
+    #[test]
+    fn test_unit_conversion() {
+        assert_eq!(Unit::Mg.to_grams(500.0), Some(0.5));
+        assert_eq!(Unit::Kcal.to_grams(500.0), None);
+        assert!((Unit::KJ.to_kcal(418.4).unwrap() - 100.0).abs() < 0.001);
+        assert_eq!(Unit::G.to_kcal(1.0), None);
+    }
+}
@@ -1,35 +1,67 @@
-use crate::db::connector::default_save_expire;
+use crate::db::connector::{default_save, default_save_expire};
 use crate::models::models::RedisORM;
+use crate::models::food::{FoodItem, FoodSource};
+use crate::open_food_facts::product::OFF_DATA_SET;
+use crate::usda::client::{merge_data_types, UsdaClient, UsdaError};
+use crate::usda::barcode;
+use crate::usda::index;
+use crate::usda::nutrients;
+use crate::usda::nutrients::Unit;
 use redis::Connection;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use std::fmt::{Debug, Display};
 use std::ops::Mul;
 use uuid::Uuid;
 
 #[derive(Deserialize, Clone, Serialize, Debug)]
-struct Nutrient {
+pub(crate) struct Nutrient {
     #[serde(rename = "nutrientId")]
-    id: i32,
+    pub(crate) id: i32,
     #[serde(rename = "nutrientName")]
-    name: String,
+    pub(crate) name: String,
     #[serde(rename = "value")]
-    amount: Option<f32>,
+    pub(crate) amount: Option<f32>,
     #[serde(rename = "unitName")]
-    unit: String,
-    median: Option<f32>,
+    pub(crate) unit: String,
+    pub(crate) median: Option<f32>,
+    // the legacy nutrient number, e.g. "208" for energy in kcal
+    #[serde(rename = "nutrientNumber", default)]
+    pub(crate) number: Option<String>,
+    // position in the FDC nutrient listing
+    #[serde(default)]
+    pub(crate) rank: Option<i32>,
+    // how the value was obtained, e.g. "Analytical" or "Calculated from a daily value percentage"
+    #[serde(rename = "derivationDescription", default)]
+    pub(crate) derivation: Option<String>,
 }
 
-impl Default for Nutrient {
-    fn default() -> Self {
+impl Nutrient {
+    pub(crate) fn new(id: i32, name: impl Into<String>, amount: f32, unit: impl Into<String>) -> Self {
         Nutrient {
-            id: 0,
-            name: "".to_string(),
-            amount: Some(0.0),
-            unit: "".to_string(),
-            median: Some(0.0),
+            id,
+            name: name.into(),
+            amount: Some(amount),
+            unit: unit.into(),
+            median: None,
+            number: None,
+            rank: None,
+            derivation: None,
         }
     }
+
+    pub(crate) fn amount_text(&self) -> String {
+        match self.amount {
+            Some(amount) => format!("{:.2} {}", amount, self.unit.to_lowercase()),
+            None => "-".to_string(),
+        }
+    }
+}
+
+/// A household measure of a food with its weight, e.g. "1 cup, chopped" = 125 g
+#[derive(Deserialize, Clone, Serialize, Debug, PartialEq)]
+pub struct Portion {
+    pub description: String,
+    pub gram_weight: f32,
 }
 
 impl Mul<f32> for Food {
@@ -41,6 +73,64 @@ impl Mul<f32> for Food {
         f
     }
 }
+// FDC categories (SR Legacy, FNDDS, branded) and Open Food Facts category tags that only
+// hold drinks, compared as whole lowercase names
+const BEVERAGE_CATEGORIES: [&str; 46] = [
+    // SR Legacy
+    "beverages",
+    // FNDDS (WWEIA)
+    "citrus juice",
+    "apple juice",
+    "other fruit juice",
+    "vegetable juice",
+    "soft drinks",
+    "diet soft drinks",
+    "sport and energy drinks",
+    "diet sport and energy drinks",
+    "nutritional beverages",
+    "smoothies and grain drinks",
+    "coffee",
+    "tea",
+    "fruit drinks",
+    "beer",
+    "wine",
+    "liquor and cocktails",
+    "tap water",
+    "bottled water",
+    "flavored or carbonated water",
+    "enhanced or fortified water",
+    "milk, whole",
+    "milk, reduced fat",
+    "milk, lowfat",
+    "milk, nonfat",
+    "flavored milk, whole",
+    "flavored milk, reduced fat",
+    "flavored milk, lowfat",
+    "flavored milk, nonfat",
+    "milk shakes and other dairy drinks",
+    "milk substitutes",
+    // branded
+    "soda",
+    "water",
+    "fruit & vegetable juice, nectars & fruit drinks",
+    "energy, protein & muscle recovery drinks",
+    "iced & bottle tea",
+    "plant based milk",
+    "milk",
+    "sport drinks",
+    "other drinks",
+    "fruit juices",
+    // Open Food Facts
+    "waters",
+    "sodas",
+    "carbonated drinks",
+    "plant-based beverages",
+    "alcoholic beverages",
+];
+// categories of solid foods next to their juices, only foods named like these are drinks
+const JUICE_CATEGORIES: [&str; 2] = ["fruits and fruit juices", "vegetables and vegetable products"];
+const JUICE_WORDS: [&str; 4] = ["juice", "nectar", "drink", "smoothie"];
+
 #[derive(Deserialize, Clone, Serialize)]
 pub struct Food {
     pub description: String,
@@ -54,6 +144,30 @@ pub struct Food {
     nutrients: Vec<Nutrient>,
     #[serde(rename = "fdcId")]
     pub id: i32,
+    #[serde(rename = "foodCategory", default)]
+    pub food_category: Option<String>,
+    // None for foods stored before they had a source, see `source`
+    #[serde(default)]
+    pub(crate) source: Option<FoodSource>,
+    /// EAN/UPC barcode of packaged foods
+    #[serde(rename = "gtinUpc", default)]
+    pub gtin_upc: Option<String>,
+    #[serde(rename = "servingSize", default)]
+    pub serving_size: Option<f32>,
+    #[serde(rename = "servingSizeUnit", default)]
+    pub serving_size_unit: Option<String>,
+    #[serde(rename = "householdServingFullText", default)]
+    pub household_serving: Option<String>,
+    #[serde(default)]
+    pub ingredients: Option<String>,
+    /// Allergen and trace tags of Open Food Facts products, e.g. "en:milk"
+    #[serde(default)]
+    pub allergen_tags: Vec<String>,
+    #[serde(default)]
+    pub portions: Vec<Portion>,
+    // true once the full record was fetched from /food/{id}, search results lack portions
+    #[serde(default)]
+    pub detailed: bool,
     #[serde(skip)]
     pub nutrient_values: NutrientValues,
 }
@@ -81,8 +195,18 @@ impl Food {
             id,
             nutrient_values: NutrientValues::default(),
             brand_name: None,
+            gtin_upc: None,
+            source: Some(FoodSource::Usda),
             brand_owner: None,
             data_set: "Foundation".to_string(),
+            food_category: None,
+            serving_size: None,
+            serving_size_unit: None,
+            household_serving: None,
+            ingredients: None,
+            allergen_tags: vec![],
+            portions: vec![],
+            detailed: false,
         };
         food = food.generate_nutrient_values();
         food
@@ -91,51 +215,41 @@ impl Food {
 
 impl Food{
 
-    pub fn format_name(&self ) -> String{
-        if self.data_set == "Branded" {
-            let mut s= String::from("BRANDED: ");
-            s.push_str(&self.description);
-            if let Some(brand) = &self.brand_name {
-                s.push_str(brand);
-                s.push(' ')
-            }
-            if let Some(brand) = &self.brand_owner {
-                s.push_str(brand);
-            }
-            return s
-        }else if self.data_set == "Survey (FNDDS)"{
-            let mut s= String::from("Survey: ");
-            s.push_str(&*self.description.clone());
-            return s
-        }
-        else if self.data_set == "Foundation"{
-            let mut s= String::from("Foundation: ");
-            s.push_str(&*self.description.clone());
-            return s
-        } else {
-            let mut s= String::from("SR Legacy: ");
-            s.push_str(&*self.description.clone());
-            return s
+    /// Where the food comes from, foods stored without a source are told apart by their data set
+    pub(crate) fn source(&self) -> FoodSource {
+        match (self.source, self.data_set.as_str()) {
+            (Some(source), _) => source,
+            (None, OFF_DATA_SET) => FoodSource::OpenFoodFacts,
+            (None, _) => FoodSource::Usda,
         }
     }
 
+    /// Nutrients with a value in the order FDC lists them
+    pub(crate) fn nutrient_listing(&self) -> Vec<&Nutrient> {
+        let mut nutrients: Vec<&Nutrient> = self.nutrients.iter().filter(|n| n.amount.is_some()).collect();
+        nutrients.sort_by_key(|n| n.rank.unwrap_or(i32::MAX));
+        nutrients
+    }
+
 }
 
 impl RedisORM for Food {
+    // Open Food Facts products are stored as they are, their foods only record the barcode
+    fn save(&self, con: &mut Connection) -> redis::RedisResult<()> {
+        if let Some(code) = &self.gtin_upc {
+            barcode::record(con, code, &self.key())?;
+        }
+        match self.source() {
+            FoodSource::OpenFoodFacts => Ok(()),
+            _ => default_save(con, &Self::redis_type_name(), &self.redis_id(), self),
+        }
+    }
+
     fn example() -> Self
     where
         Self: Sized,
     {
-        Food {
-            description: "".to_string(),
-            nutrients: vec![],
-            id: 1,
-            nutrient_values: NutrientValues::default(),
-            brand_owner: None,
-            brand_name: None,
-            data_set: "Foundation".to_string(),
-
-        }
+        Food::new(1, "", vec![])
     }
 
     fn redis_type_name() -> String {
@@ -157,6 +271,11 @@ pub struct NutrientValues {
     pub salt: f32,
     pub sodium: f32,
     pub sugar: f32,
+    #[serde(default)]
+    pub saturated_fat: f32,
+    // true if any value was derived instead of taken from the food data
+    #[serde(default)]
+    pub estimated: bool,
 }
 impl Default for NutrientValues {
     fn default() -> Self {
@@ -169,6 +288,8 @@ impl Default for NutrientValues {
             salt: 0.0,
             sodium: 0.0,
             sugar: 0.0,
+            saturated_fat: 0.0,
+            estimated: false,
         }
     }
 }
@@ -186,6 +307,8 @@ impl Mul<f32> for NutrientValues {
             salt: self.salt * rhs,
             sodium: self.sodium * rhs,
             sugar: self.sugar * rhs,
+            saturated_fat: self.saturated_fat * rhs,
+            estimated: self.estimated,
         }
     }
 }
@@ -196,128 +319,107 @@ impl Food {
         return self.brand_name == other.brand_name && self.brand_owner == other.brand_owner && self.description == other.description
     }
 
+    // amount of the first listed nutrient of `ids` that has a value, converted with `convert`
+    fn resolve_nutrient(&self, ids: &[i32], convert: impl Fn(Unit, f32) -> Option<f32>) -> Option<f32> {
+        ids.iter().find_map(|id| {
+            self.nutrients
+                .iter()
+                .filter(|n| n.id == *id)
+                .find_map(|n| convert(Unit::parse(&n.unit)?, n.amount?))
+        })
+    }
+
+    fn grams(&self, ids: &[i32]) -> Option<f32> {
+        self.resolve_nutrient(ids, Unit::to_grams)
+    }
+
+    fn energy_kcal(&self) -> Option<f32> {
+        self.resolve_nutrient(&nutrients::ENERGY_IDS, Unit::to_kcal)
+    }
+
+    /// Lowercase category names, the FDC food category or the comma separated category tags of
+    /// an Open Food Facts product
+    pub(crate) fn categories(&self) -> Vec<String> {
+        let category = self
+            .food_category
+            .as_deref()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        match self.source() {
+            FoodSource::OpenFoodFacts => category
+                .split(',')
+                .map(|tag| tag.trim().to_string())
+                .collect(),
+            _ => vec![category],
+        }
+    }
+
+    /// Drinks count towards the daily hydration, detected by the FDC food category or, for
+    /// Open Food Facts products, any of their category tags
+    pub fn is_beverage(&self) -> bool {
+        let category = self
+            .food_category
+            .as_deref()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        if JUICE_CATEGORIES.contains(&category.as_str()) {
+            return self
+                .description
+                .to_lowercase()
+                .split(|c: char| !c.is_alphanumeric())
+                .map(|word| word.strip_suffix('s').unwrap_or(word))
+                .any(|word| JUICE_WORDS.contains(&word));
+        }
+        self.categories()
+            .iter()
+            .any(|c| BEVERAGE_CATEGORIES.contains(&c.as_str()))
+    }
+
+    /// Water in ml per 100 g, None if the food data has no water content
+    pub fn water_per_100g(&self) -> Option<f32> {
+        self.grams(&[nutrients::WATER])
+    }
+
     pub fn get_numerical_macros(&self) -> NutrientValues {
-        let nuts = self.nutrients.clone();
-        let default_nut = Nutrient::default();
-        // let energy either ENERGY or Energy (Atwater General Factors)
+        let proteins = self.grams(&[nutrients::PROTEIN]).unwrap_or(0.0);
+        let carbohydrates = self.grams(&nutrients::CARBOHYDRATE_IDS).unwrap_or(0.0);
+        let fats = self.grams(&[nutrients::TOTAL_FAT]).unwrap_or(0.0);
+        let sodium = self.grams(&[nutrients::SODIUM]).unwrap_or(0.0);
+        // fall back to the Atwater general factors if the food has no energy value
+        let (energy, estimated) = match self.energy_kcal() {
+            Some(energy) => (energy, false),
+            None => (nutrients::atwater_energy(proteins, carbohydrates, fats), true),
+        };
 
         NutrientValues {
-            carbohydrates: nuts
-                .iter()
-                .find(|x| x.name == "Carbohydrate, by difference")
-                .unwrap_or(&default_nut)
-                .amount
-                .unwrap_or(0.0),
-            proteins: nuts
-                .iter()
-                .find(|x| x.name == "Protein")
-                .unwrap_or(&default_nut)
-                .amount
-                .unwrap_or(0.0),
-            fats: nuts
-                .iter()
-                .find(|x| x.name == "Total lipid (fat)")
-                .unwrap_or(&default_nut)
-                .amount
-                .unwrap_or(0.0),
-            energy: nuts
-                .iter()
-                .find(|x| x.name == "Energy" || x.name == "Energy (Atwater Specific Factors)")
-                .unwrap_or(&default_nut)
-                .amount
-                .unwrap_or(0.0),
-            fiber: nuts
-                .iter()
-                .find(|x| x.name == "Fiber, total dietary")
-                .unwrap_or(&default_nut)
-                .amount
-                .unwrap_or(0.0),
-            sodium: nuts
-                .iter()
-                .find(|x| x.name == "Sodium, Na")
-                .unwrap_or(&default_nut)
-                .amount
-                .unwrap_or(0.0) as f32
-                * 0.001,
-            salt: nuts
-                .iter()
-                .find(|x| x.name == "Sodium, Na")
-                .unwrap_or(&default_nut)
-                .amount
-                .unwrap_or(0.0) as f32
-                * 2.5f32
-                * 0.001,
-            sugar: nuts
-                .iter()
-                .find(|x| x.name == "Total Sugars")
-                .unwrap_or(&default_nut)
-                .amount
-                .unwrap_or(0.0),
+            carbohydrates,
+            proteins,
+            fats,
+            energy,
+            fiber: self.grams(&[nutrients::FIBER_TOTAL_DIETARY]).unwrap_or(0.0),
+            salt: sodium * nutrients::SALT_PER_SODIUM,
+            sodium,
+            sugar: self.grams(&nutrients::SUGAR_IDS).unwrap_or(0.0),
+            saturated_fat: self.grams(&[nutrients::SATURATED_FAT]).unwrap_or(0.0),
+            estimated,
         }
     }
 }
 
 #[derive(Deserialize, Clone, Serialize, Debug)]
 pub struct SearchResult {
-    foods: Vec<Food>,
+    pub(crate) foods: Vec<Food>,
+    #[serde(rename = "totalPages", default)]
+    pub(crate) total_pages: u32,
 }
 
-pub(crate) async fn query_usda_food_database(
-    search_term: &str,
-) -> Result<Vec<Food>, Box<dyn Error>> {
-    let mut con = crate::db::connector::get_connection().unwrap();
-    let api_key = "x6VEBsX1defxOWiAjX2MVZMcCh2HACrmeUecluye"; // Replace 'DEMO_KEY' with your actual API key
-    // let url_foundation = format!("https://api.nal.usda.gov/fdc/v1/foods/search?query={}&dataType=Foundation,SR%20Legacy&pageSize=30&pageNumber=1&sortBy=dataType.keyword&sortOrder=asc&api_key={}", search_term, api_key);
-    let mut result = vec![];
-    let sets = vec!["Foundation","SR%20Legacy","Survey%20%28FNDDS%29","Branded"];
-    let search_words = search_term.split_whitespace().map(|w|w.to_lowercase()).collect::<Vec<_>>();
-    println!("{:?}",search_words);
-    for s in sets{
-        let url= format!("https://api.nal.usda.gov/fdc/v1/foods/search?query={}&dataType={}&pageSize=100&pageNumber=1&sortBy=dataType.keyword&sortOrder=asc&api_key={}", search_term,s, "x6VEBsX1defxOWiAjX2MVZMcCh2HACrmeUecluye".to_string());
-        let response = reqwest::get(&url).await?;
-        if response.status().is_success() {
-            let mut resp: SearchResult = response.json().await?;
-            if !resp.foods.is_empty() {
-                for food in resp.foods.iter_mut() {
-                    food.nutrient_values = food.get_numerical_macros();
-                }
-                // make foods unique and put only containging the search terms
-                let mut unique_foods = vec![];
-                for food in resp.foods.iter() {
-                    let food_string = food.format_name().to_lowercase();
-                    if !unique_foods.iter().any(|x: &Food| x.compare_food(food)) {
-                        let mut contains_all_words = true;
-                        for word in search_words.iter(){
-                            if !food_string.contains(word){
-                                contains_all_words = false;
-                                break;
-                            }
-                        }
-                        if contains_all_words{
-                            println!("{} CONTAINS {}",food_string,&contains_all_words);
-                            unique_foods.push(food.clone());
-                        }
-                    }
-                }
-
-                result.append(&mut unique_foods);
-                println!("{}", unique_foods.len());
-                save_foods(&mut con, &mut result, &mut resp);
-            } else {
-                println!("No foods found. {}",s);
-            }
-        } else {
-            println!("Failed to query the USDA Food Database.");
-        }
-    }
-    Ok(result)
-}
-fn save_foods(mut con: &mut Connection, result: &mut Vec<Food>, resp: &mut SearchResult) {
-    let resp = resp.clone();
-    for food in resp.foods {
-        food.save(&mut con).unwrap();
-        result.push(food);
+pub(crate) fn save_foods(con: &mut Connection, foods: &[Food]) -> redis::RedisResult<()> {
+    for food in foods {
+        food.save(con)?;
     }
+    Ok(())
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -351,40 +453,254 @@ impl RedisORM for FoodSearchResult {
     }
 }
 
-pub async fn cached_search(query: &str) -> Vec<Food> {
-    let mut con = crate::db::connector::get_connection().unwrap();
-    let cache = FoodSearchResult::fetch_from_uuid(&mut con, query);
-    let use_cache = false;
-    if cache.is_some() && use_cache{
-        dbg!("Cache hit");
-        return cache.unwrap().products;
-    }
-    let result = query_usda_food_database(query).await.unwrap();
-    print!("{:?}",result.len());
+// counts of local, hit and miss searches
+const CACHE_STATS_KEY: &str = "usda_search_stats";
+
+/// Where the results of a search came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CacheStatus {
+    /// the local index of imported foods
+    Local,
+    Hit,
+    Miss,
+}
+
+impl Display for CacheStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CacheStatus::Local => write!(f, "LOCAL"),
+            CacheStatus::Hit => write!(f, "HIT"),
+            CacheStatus::Miss => write!(f, "MISS"),
+        }
+    }
+}
+
+pub(crate) struct CachedSearch {
+    pub(crate) foods: Vec<Food>,
+    pub(crate) status: CacheStatus,
+}
+
+impl CachedSearch {
+    fn new(con: &mut Connection, foods: Vec<Food>, status: CacheStatus) -> CachedSearch {
+        let _: redis::RedisResult<()> = redis::cmd("HINCRBY")
+            .arg(CACHE_STATS_KEY)
+            .arg(status.to_string().to_lowercase())
+            .arg(1)
+            .query(con);
+        CachedSearch { foods, status }
+    }
+
+    /// The foods ranked for the query with their current uses, cached searches are stored
+    /// unranked so foods added to meals since then move up
+    pub(crate) fn ranked(mut self, con: &mut Connection, query: &str) -> CachedSearch {
+        let ids: Vec<i32> = self.foods.iter().map(|food| food.id).collect();
+        let uses = index::uses(con, &ids);
+        self.foods = index::rank(query, self.foods, &uses);
+        self
+    }
+}
+
+/// Case, whitespace and accent insensitive form of a query, so "Crème  Fraîche" and
+/// "creme fraiche" share a cache entry
+pub(crate) fn normalize_query(query: &str) -> String {
+    deunicode::deunicode(query)
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Cache key of a query in one data type, e.g. "SR Legacy:apple"
+pub(crate) fn data_type_key(data_type: &str, query: &str) -> String {
+    format!("{}:{}", data_type, normalize_query(query))
+}
+
+// the stored foods of a cache key
+fn cached_foods(con: &mut Connection, key: &str) -> Option<Vec<Food>> {
+    let cache = FoodSearchResult::fetch_from_uuid(con, key)?;
+    Some(cache.products.into_iter().map(Food::generate_nutrient_values).collect())
+}
+
+// stores the fetched foods so they can be added to meals and caches them for the next search,
+// without redis the search still answers and the next one asks the API again
+fn cache_foods(con: &mut Connection, key: String, foods: Vec<Food>) -> Vec<Food> {
     let search_result = FoodSearchResult {
-        products: result.clone(),
-        query: query.to_string(),
+        products: foods,
+        query: key,
     };
-    search_result.save(&mut con).unwrap();
-    result
+    if let Err(e) = save_foods(con, &search_result.products).and_then(|_| search_result.save(con)) {
+        println!("Caching the search {} failed: {}", search_result.query, e);
+    }
+    search_result.products
+}
+
+/// Cache-aside: the stored result of `key` or the fetched one, which is stored for the next
+/// search. The foods are unranked.
+pub(crate) async fn cache_aside<E>(
+    con: &mut Connection,
+    key: String,
+    fetch: impl std::future::Future<Output = Result<Vec<Food>, E>>,
+) -> Result<CachedSearch, E> {
+    if let Some(foods) = cached_foods(con, &key) {
+        return Ok(CachedSearch::new(con, foods, CacheStatus::Hit));
+    }
+    let foods = fetch.await?;
+    let foods = cache_foods(con, key, foods);
+    Ok(CachedSearch::new(con, foods, CacheStatus::Miss))
+}
+
+/// Imported foods or the cached foods of every data type, None if only the API can answer
+/// the query
+pub(crate) fn stored_search(
+    con: &mut Connection,
+    query: &str,
+    data_types: &[String],
+) -> Option<CachedSearch> {
+    // foods imported from the bulk downloads are searched without the API
+    let local = index::search(con, query);
+    if !local.is_empty() {
+        return Some(CachedSearch::new(con, local, CacheStatus::Local));
+    }
+    let cached = data_types
+        .iter()
+        .map(|data_type| cached_foods(con, &data_type_key(data_type, query)).map(Ok))
+        .collect::<Option<Vec<_>>>()?;
+    let foods = merge_data_types(data_types, cached).ok()?;
+    Some(CachedSearch::new(con, foods, CacheStatus::Hit).ranked(con, query))
+}
+
+/// Imported foods if the local index knows the query, else the cached or live USDA search.
+/// Every data type is cached on its own, so a failing data type is searched again by the next
+/// query instead of missing from the cache for a week.
+pub(crate) async fn cached_search(usda: &UsdaClient, query: &str) -> Result<CachedSearch, UsdaError> {
+    let mut con = crate::db::connector::get_connection().unwrap();
+    if let Some(result) = stored_search(&mut con, query, usda.data_types()) {
+        return Ok(result);
+    }
+    let data_types = usda.data_types();
+    let cached: Vec<Option<Vec<Food>>> = data_types
+        .iter()
+        .map(|data_type| cached_foods(&mut con, &data_type_key(data_type, query)))
+        .collect();
+    let missing: Vec<String> = data_types
+        .iter()
+        .zip(cached.iter())
+        .filter(|(_, foods)| foods.is_none())
+        .map(|(data_type, _)| data_type.clone())
+        .collect();
+    let mut fetched = usda.fetch_data_types(query, &missing).await.into_iter();
+    let results = data_types
+        .iter()
+        .zip(cached)
+        .map(|(data_type, cached)| match cached {
+            Some(foods) => Ok(foods),
+            None => fetched.next().expect("a result per missing data type").map(|foods| {
+                cache_foods(&mut con, data_type_key(data_type, query), foods)
+            }),
+        })
+        .collect();
+    let foods = merge_data_types(data_types, results)?;
+    Ok(CachedSearch::new(&mut con, foods, CacheStatus::Miss).ranked(&mut con, query))
+}
+
+/// The cached or live USDA search restricted to a data type
+pub(crate) async fn cached_search_of(
+    usda: &UsdaClient,
+    query: &str,
+    data_type: &str,
+) -> Result<CachedSearch, UsdaError> {
+    let mut con = crate::db::connector::get_connection().unwrap();
+    let key = data_type_key(data_type, query);
+    let result = cache_aside(&mut con, key, usda.search_foods_of(query, data_type)).await?;
+    Ok(result.ranked(&mut con, query))
 }
 
 #[cfg(test)]
 mod tests {
 
     #![allow(dead_code)]
+    use crate::models::food::FoodSource;
     use crate::models::models::RedisORM;
     use crate::models::user::User;
-    use crate::usda::search::{query_usda_food_database, Food};
+    use crate::usda::nutrients;
+    use crate::usda::client::tests::{mock_routes, page};
+    use crate::usda::client::{UsdaClient, UsdaConfig};
+    use crate::usda::search::{
+        cached_search, data_type_key, normalize_query, CacheStatus, Food, Nutrient,
+    };
     use crate::{db, models};
     use uuid::Uuid;
 
+    #[test]
+    fn test_normalize_query() {
+        assert_eq!(normalize_query("  Crème   Fraîche "), "creme fraiche");
+        assert_eq!(normalize_query("KÖLLNFLOCKEN"), normalize_query("köllnflocken"));
+    }
+
+    #[test]
+    fn test_is_beverage() {
+        let food = |description: &str, category: &str| {
+            let mut food = Food::new(1, description, vec![]);
+            food.food_category = Some(category.to_string());
+            food
+        };
+        assert!(food("Cola", "Soft drinks").is_beverage());
+        assert!(food("Milk, whole", "Milk, whole").is_beverage());
+        assert!(food("Apple juice, canned", "Fruits and Fruit Juices").is_beverage());
+        assert!(!food("Apples, raw, with skin", "Fruits and Fruit Juices").is_beverage());
+        assert!(!food("Bananas, raw", "Fruits and Fruit Juices").is_beverage());
+        assert!(!food("Tea bags, green", "Tea Bags").is_beverage());
+        assert!(!food("Watermelon, raw", "Melons").is_beverage());
+        // Open Food Facts products have a list of category tags
+        let mut soda = food("Cola", "Beverages, Carbonated drinks, Sodas");
+        soda.source = Some(FoodSource::OpenFoodFacts);
+        assert!(soda.is_beverage());
+    }
+
     #[tokio::test]
-    async fn test_usda_search() {
-        let foods = crate::usda::search::query_usda_food_database("apple")
+    #[ignore = "needs a running redis"]
+    async fn test_cached_search() {
+        let mut con = db::connector::get_connection()
+            .expect("Could not connect to redis,maybe redis is not running");
+        let query = format!("Mock Apple {}", Uuid::new_v4());
+        redis::cmd("DEL")
+            .arg(format!("usda_search:{}", data_type_key("Foundation", &query)))
+            .query::<()>(&mut con)
+            .unwrap();
+        let foods = r#"{"fdcId": 900001, "description": "Apples, raw", "dataType": "Foundation", "foodNutrients": []},
+            {"fdcId": 900002, "description": "Apples, raw", "dataType": "Foundation", "foodNutrients": []}"#;
+        let usda = UsdaClient::new(UsdaConfig {
+            base_url: mock_routes(vec![("Foundation", page(foods, 1))]).await,
+            data_types: vec!["Foundation".to_string()],
+            ..UsdaConfig::default()
+        });
+        let first = cached_search(&usda, &query).await.unwrap();
+        assert_eq!(first.status, CacheStatus::Miss);
+        assert_eq!(first.foods.len(), 1);
+        // differently written queries are served from the cache without asking the API
+        let offline = UsdaClient::new(UsdaConfig {
+            base_url: "http://127.0.0.1:9".to_string(),
+            data_types: vec!["Foundation".to_string()],
+            ..UsdaConfig::default()
+        });
+        let second = cached_search(&offline, &format!(" {} ", query.to_uppercase()))
             .await
             .unwrap();
-        assert!(!foods.is_empty());
+        assert_eq!(second.status, CacheStatus::Hit);
+        assert_eq!(second.foods.len(), 1);
+        assert_eq!(second.foods[0].id, first.foods[0].id);
+    }
+
+    #[tokio::test]
+    async fn test_usda_search() {
+        let foods = r#"{"fdcId": 900003, "description": "Apples, raw", "dataType": "SR Legacy", "foodNutrients": []}"#;
+        let usda = UsdaClient::new(UsdaConfig {
+            base_url: mock_routes(vec![("dataType=SR", page(foods, 1))]).await,
+            data_types: vec!["SR Legacy".to_string()],
+            ..UsdaConfig::default()
+        });
+        let foods = usda.search_foods_of("apple", "SR Legacy").await.unwrap();
+        assert_eq!(foods[0].id, 900003);
     }
     #[tokio::test]
     async fn create_test_data() {
@@ -439,6 +755,57 @@ mod tests {
         let food = Food::fetch_from_uuid(&mut con, "1").unwrap();
         assert_eq!(food.description, "Test Product");
     }
+    fn nutrient(id: i32, amount: f32, unit: &str) -> Nutrient {
+        Nutrient::new(id, "", amount, unit)
+    }
+
+    #[test]
+    fn test_energy_prefers_kcal_over_kj() {
+        let food = Food::new(
+            1,
+            "Oats",
+            vec![
+                nutrient(nutrients::ENERGY_KJ, 1552.0, "kJ"),
+                nutrient(nutrients::ENERGY, 371.0, "KCAL"),
+            ],
+        );
+        assert_eq!(food.nutrient_values.energy, 371.0);
+        let food = Food::new(1, "Oats", vec![nutrient(nutrients::ENERGY_KJ, 418.4, "kJ")]);
+        assert!((food.nutrient_values.energy - 100.0).abs() < 0.001);
+        assert!(!food.nutrient_values.estimated);
+    }
+
+    #[test]
+    fn test_energy_atwater_fallback() {
+        let food = Food::new(
+            1,
+            "Chicken breast",
+            vec![
+                nutrient(nutrients::PROTEIN, 20.0, "G"),
+                nutrient(nutrients::TOTAL_FAT, 2.0, "G"),
+                nutrient(nutrients::CARBOHYDRATE_BY_DIFFERENCE, 1.0, "G"),
+            ],
+        );
+        assert_eq!(food.nutrient_values.energy, 20.0 * 4.0 + 1.0 * 4.0 + 2.0 * 9.0);
+        assert!(food.nutrient_values.estimated);
+    }
+
+    #[test]
+    fn test_sodium_unit_conversion() {
+        let food = Food::new(
+            1,
+            "Salted butter",
+            vec![
+                nutrient(nutrients::ENERGY, 717.0, "KCAL"),
+                nutrient(nutrients::SODIUM, 400.0, "MG"),
+            ],
+        );
+        assert!((food.nutrient_values.sodium - 0.4).abs() < 0.0001);
+        assert!((food.nutrient_values.salt - 1.0).abs() < 0.0001);
+        let food = Food::new(1, "Water", vec![nutrient(nutrients::SODIUM, 4000.0, "µg")]);
+        assert!((food.nutrient_values.sodium - 0.004).abs() < 0.0001);
+    }
+
     #[tokio::test]
     async fn search_test() {
         assert!("vegetable branded: vegan lasagna365 everyday value whole foods market, inc.".contains("lasagna"));